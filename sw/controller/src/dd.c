@@ -86,6 +86,7 @@ struct process {
     uint32_t block_offset;
     dd_drive_type_t drive_type;
     bool sd_mode;
+    bool sd_standalone;
     uint8_t sd_current_disk;
     sd_disk_info_t sd_disk_info[DD_SD_MAX_DISKS];
 };
@@ -130,11 +131,26 @@ static uint32_t dd_fill_sd_sector_table (uint32_t index, uint32_t *sector_table,
     return sectors;
 }
 
+static sd_error_t dd_sd_get_lock (void) {
+    sd_error_t error = sd_get_lock(SD_LOCK_N64);
+    if ((error == SD_OK) || !p.sd_standalone) {
+        return error;
+    }
+    error = sd_try_lock(SD_LOCK_N64);
+    if (error == SD_OK) {
+        error = sd_card_init();
+        if (error != SD_OK) {
+            sd_release_lock(SD_LOCK_N64);
+        }
+    }
+    return error;
+}
+
 static bool dd_block_read_request (void) {
     uint16_t index = dd_track_head_block();
     uint32_t buffer_address = DD_BLOCK_BUFFER_ADDRESS;
     if (p.sd_mode) {
-        sd_error_t error = sd_get_lock(SD_LOCK_N64);
+        sd_error_t error = dd_sd_get_lock();
         if (error == SD_OK) {
             uint32_t sector_table[DD_SD_SECTOR_TABLE_SIZE];
             uint32_t sectors = dd_fill_sd_sector_table(index, sector_table, false);
@@ -161,7 +177,7 @@ static bool dd_block_write_request (void) {
     uint32_t index = dd_track_head_block();
     uint32_t buffer_address = DD_BLOCK_BUFFER_ADDRESS;
     if (p.sd_mode) {
-        sd_error_t error = sd_get_lock(SD_LOCK_N64);
+        sd_error_t error = dd_sd_get_lock();
         if (error == SD_OK) {
             uint32_t sector_table[DD_SD_SECTOR_TABLE_SIZE];
             uint32_t sectors = dd_fill_sd_sector_table(index, sector_table, true);
@@ -258,6 +274,7 @@ void dd_set_sd_mode (bool value) {
 void dd_set_disk_mapping (uint32_t address, uint32_t length) {
     sd_disk_info_t info;
     length /= sizeof(info);
+    p.sd_standalone = false;
     p.sd_current_disk = 0;
     for (int i = 0; i < DD_SD_MAX_DISKS; i++) {
         if (i < length) {
@@ -272,6 +289,11 @@ void dd_set_disk_mapping (uint32_t address, uint32_t length) {
     }
 }
 
+void dd_set_standalone_disk_mapping (uint32_t address, uint32_t length) {
+    dd_set_disk_mapping(address, length);
+    p.sd_standalone = true;
+}
+
 void dd_handle_button (void) {
     led_activity_pulse();
     if (dd_get_disk_state() == DD_DISK_STATE_EJECTED) {
@@ -26,6 +26,7 @@ bool dd_set_disk_state (dd_disk_state_t state);
 bool dd_get_sd_mode (void);
 void dd_set_sd_mode (bool value);
 void dd_set_disk_mapping (uint32_t address, uint32_t length);
+void dd_set_standalone_disk_mapping (uint32_t address, uint32_t length);
 void dd_handle_button (void);
 
 void dd_init (void);
@@ -509,6 +509,16 @@ static void usb_rx_process (void) {
                 p.response_pending = true;
                 break;
 
+            case 'd':
+                if (usb_validate_address_length(p.rx_args[0], p.rx_args[1], true)) {
+                    p.response_error = true;
+                } else {
+                    dd_set_standalone_disk_mapping(p.rx_args[0], p.rx_args[1]);
+                }
+                p.rx_state = RX_STATE_IDLE;
+                p.response_pending = true;
+                break;
+
             case 'W':
                 writeback_enable(WRITEBACK_USB);
                 p.rx_state = RX_STATE_IDLE;
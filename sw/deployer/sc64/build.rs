@@ -2,13 +2,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
 
     cc::Build::new()
-        .file("../bootloader/src/fatfs/ff.c")
-        .file("../bootloader/src/fatfs/ffsystem.c")
-        .file("../bootloader/src/fatfs/ffunicode.c")
+        .file("../../bootloader/src/fatfs/ff.c")
+        .file("../../bootloader/src/fatfs/ffsystem.c")
+        .file("../../bootloader/src/fatfs/ffunicode.c")
         .compile("fatfs");
 
     bindgen::Builder::default()
-        .header("../bootloader/src/fatfs/ff.h")
+        .header("../../bootloader/src/fatfs/ff.h")
         .blocklist_function("get_fattime")
         .generate()
         .expect("Unable to generate FatFs bindings")
@@ -0,0 +1,231 @@
+use super::Error;
+
+pub const IPL3_OFFSET: u32 = 0x40;
+pub const IPL3_LENGTH: usize = 0xFC0;
+
+fn calculate_ipl3_checksum(ipl3: &[u8], seed: u8) -> Result<u64, Error> {
+    if ipl3.len() < IPL3_LENGTH {
+        return Err(Error::InvalidInput("Invalid IPL3 length provided".into()));
+    }
+
+    const MAGIC: u32 = 0x6C078965;
+
+    let get = |offset: u32| {
+        let o: usize = offset as usize * 4;
+        return ((ipl3[o] as u32) << 24)
+            | ((ipl3[o + 1] as u32) << 16)
+            | ((ipl3[o + 2] as u32) << 8)
+            | (ipl3[o + 3] as u32);
+    };
+    let add = |a1: u32, a2: u32| u32::wrapping_add(a1, a2);
+    let sub = |a1: u32, a2: u32| u32::wrapping_sub(a1, a2);
+    let mul = |a1: u32, a2: u32| u32::wrapping_mul(a1, a2);
+    let rol = |a: u32, s: u32| u32::rotate_left(a, s);
+    let ror = |a: u32, s: u32| u32::rotate_right(a, s);
+    let sum = |a0: u32, a1: u32, a2: u32| {
+        let prod = (a0 as u64).wrapping_mul(if a1 == 0 { a2 as u64 } else { a1 as u64 });
+        let hi = ((prod >> 32) & 0xFFFFFFFF) as u32;
+        let lo = (prod & 0xFFFFFFFF) as u32;
+        let diff = hi.wrapping_sub(lo);
+        return if diff == 0 { a0 } else { diff };
+    };
+
+    let init = add(mul(MAGIC, seed as u32), 1) ^ get(0);
+
+    let mut buf = vec![init; 16];
+
+    for i in 1..=1008 as u32 {
+        let prev = get(i.saturating_sub(2));
+        let data = get(i - 1);
+
+        buf[0] = add(buf[0], sum(sub(1007, i), data, i));
+        buf[1] = sum(buf[1], data, i);
+        buf[2] = buf[2] ^ data;
+        buf[3] = add(buf[3], sum(add(data, 5), MAGIC, i));
+        buf[4] = add(buf[4], ror(data, prev & 0x1F));
+        buf[5] = add(buf[5], rol(data, prev >> 27));
+        buf[6] = if data < buf[6] {
+            add(buf[3], buf[6]) ^ add(data, i)
+        } else {
+            add(buf[4], data) ^ buf[6]
+        };
+        buf[7] = sum(buf[7], rol(data, prev & 0x1F), i);
+        buf[8] = sum(buf[8], ror(data, prev >> 27), i);
+        buf[9] = if prev < data {
+            sum(buf[9], data, i)
+        } else {
+            add(buf[9], data)
+        };
+
+        if i == 1008 {
+            break;
+        }
+
+        let next = get(i);
+
+        buf[10] = sum(add(buf[10], data), next, i);
+        buf[11] = sum(buf[11] ^ data, next, i);
+        buf[12] = add(buf[12], buf[8] ^ data);
+        buf[13] = add(buf[13], add(ror(data, data & 0x1F), ror(next, next & 0x1F)));
+        buf[14] = sum(
+            sum(buf[14], ror(data, prev & 0x1F), i),
+            ror(next, data & 0x1F),
+            i,
+        );
+        buf[15] = sum(
+            sum(buf[15], rol(data, prev >> 27), i),
+            rol(next, data >> 27),
+            i,
+        );
+    }
+
+    let mut final_buf = vec![buf[0]; 4];
+
+    for i in 0..16 as u32 {
+        let data = buf[i as usize];
+
+        final_buf[0] = add(final_buf[0], ror(data, data & 0x1F));
+        final_buf[1] = if data < final_buf[0] {
+            add(final_buf[1], data)
+        } else {
+            sum(final_buf[1], data, i)
+        };
+        final_buf[2] = if ((data & 0x02) >> 1) == (data & 0x01) {
+            add(final_buf[2], data)
+        } else {
+            sum(final_buf[2], data, i)
+        };
+        final_buf[3] = if (data & 0x01) == 0x01 {
+            final_buf[3] ^ data
+        } else {
+            sum(final_buf[3], data, i)
+        };
+    }
+
+    let final_sum = sum(final_buf[0], final_buf[1], 16);
+    let final_xor = final_buf[3] ^ final_buf[2];
+
+    let checksum = (((final_sum & 0xFFFF) as u64) << 32) | (final_xor as u64);
+
+    Ok(checksum)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CicVariant {
+    Cic5167,
+    Cic6101,
+    Cic7102,
+    Cic6102_7101,
+    Cic6103_7103,
+    Cic6105_7105,
+    Cic6106_7106,
+    DdIplNdxj0,
+    DdIplNddj0,
+    DdIplNddj1,
+    DdIplNddj2,
+    DdIplNdde0,
+}
+
+/// iQue bootcode is intentionally absent, there are no verified checksums of its IPL3 variants.
+/// Such ROMs are reported as unknown IPL3 and get the seed matching their header checksum.
+const KNOWN_CIC_VARIANTS: [CicVariant; 12] = [
+    CicVariant::Cic5167,
+    CicVariant::Cic6101,
+    CicVariant::Cic7102,
+    CicVariant::Cic6102_7101,
+    CicVariant::Cic6103_7103,
+    CicVariant::Cic6105_7105,
+    CicVariant::Cic6106_7106,
+    CicVariant::DdIplNdxj0,
+    CicVariant::DdIplNddj0,
+    CicVariant::DdIplNddj1,
+    CicVariant::DdIplNddj2,
+    CicVariant::DdIplNdde0,
+];
+
+impl CicVariant {
+    pub fn seed(&self) -> u8 {
+        match self {
+            Self::Cic5167 => 0xDD,
+            Self::Cic6101 | Self::Cic7102 | Self::Cic6102_7101 => 0x3F,
+            Self::Cic6103_7103 => 0x78,
+            Self::Cic6105_7105 => 0x91,
+            Self::Cic6106_7106 => 0x85,
+            Self::DdIplNdxj0 | Self::DdIplNddj0 | Self::DdIplNddj1 | Self::DdIplNddj2 => 0xDD,
+            Self::DdIplNdde0 => 0xDE,
+        }
+    }
+
+    pub fn checksum(&self) -> u64 {
+        match self {
+            Self::Cic5167 => 0x083C6C77E0B1,
+            Self::Cic6101 => 0x45CC73EE317A,
+            Self::Cic7102 => 0x44160EC5D9AF,
+            Self::Cic6102_7101 => 0xA536C0F1D859,
+            Self::Cic6103_7103 => 0x586FD4709867,
+            Self::Cic6105_7105 => 0x8618A45BC2D3,
+            Self::Cic6106_7106 => 0x2BBAD4E6EB74,
+            Self::DdIplNdxj0 => 0x6EE8D9E84970,
+            Self::DdIplNddj0 => 0x6C216495C8B9,
+            Self::DdIplNddj1 => 0xE27F43BA93AC,
+            Self::DdIplNddj2 => 0x32B294E2AB90,
+            Self::DdIplNdde0 => 0x05BA2EF0A5F1,
+        }
+    }
+}
+
+impl std::fmt::Display for CicVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Cic5167 => "5167",
+            Self::Cic6101 => "6101",
+            Self::Cic7102 => "7102",
+            Self::Cic6102_7101 => "6102/7101",
+            Self::Cic6103_7103 => "6103/7103",
+            Self::Cic6105_7105 => "6105/7105",
+            Self::Cic6106_7106 => "6106/7106",
+            Self::DdIplNdxj0 => "64DD IPL (NDXJ0)",
+            Self::DdIplNddj0 => "64DD IPL (NDDJ0)",
+            Self::DdIplNddj1 => "64DD IPL (NDDJ1)",
+            Self::DdIplNddj2 => "64DD IPL (NDDJ2)",
+            Self::DdIplNdde0 => "64DD IPL (NDDE0)",
+        })
+    }
+}
+
+pub struct Ipl3Signature {
+    pub seed: u8,
+    pub checksum: u64,
+    pub variant: Option<CicVariant>,
+    /// Seed of the unknown IPL3 was found by matching the checksum stored in the ROM header
+    pub seed_found: bool,
+}
+
+pub fn identify_ipl3(ipl3: &[u8]) -> Result<Option<CicVariant>, Error> {
+    for variant in KNOWN_CIC_VARIANTS {
+        if calculate_ipl3_checksum(ipl3, variant.seed())? == variant.checksum() {
+            return Ok(Some(variant));
+        }
+    }
+    Ok(None)
+}
+
+pub fn sign_ipl3(ipl3: &[u8], custom_seed: Option<u8>) -> Result<Ipl3Signature, Error> {
+    let variant = identify_ipl3(ipl3)?;
+
+    // Unknown IPL3 detected, sign it with arbitrary seed (CIC6102/7101 value is used here)
+    const DEFAULT_SEED: u8 = 0x3F;
+
+    let seed = custom_seed.unwrap_or(variant.map_or(DEFAULT_SEED, |variant| variant.seed()));
+    let checksum = match variant {
+        Some(variant) if variant.seed() == seed => variant.checksum(),
+        _ => calculate_ipl3_checksum(ipl3, seed)?,
+    };
+
+    Ok(Ipl3Signature {
+        seed,
+        checksum,
+        variant,
+        seed_found: false,
+    })
+}
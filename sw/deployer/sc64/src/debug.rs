@@ -1,4 +1,4 @@
-use crate::sc64;
+use crate::{DebugPacket, SaveWriteback};
 use chrono::Local;
 use colored::Colorize;
 use encoding_rs::EUC_JP;
@@ -20,6 +20,7 @@ pub struct Handler {
     line_rx: Receiver<String>,
     external_line_tx: Sender<String>,
     encoding: Encoding,
+    commands_enabled: bool,
 }
 
 enum DataType {
@@ -139,7 +140,9 @@ impl TryFrom<&[u8]> for Heartbeat {
 }
 
 pub enum UserInput {
-    Packet(sc64::DebugPacket),
+    Packet(DebugPacket),
+    /// Line starting with `/`, returned without the prefix when commands are enabled
+    Command(String),
     EOF,
 }
 
@@ -177,6 +180,7 @@ impl Handler {
             line_rx,
             external_line_tx,
             encoding: Encoding::UTF8,
+            commands_enabled: false,
         }
     }
 
@@ -184,6 +188,10 @@ impl Handler {
         self.encoding = encoding;
     }
 
+    pub fn enable_commands(&mut self) {
+        self.commands_enabled = true;
+    }
+
     pub fn send_external_input(&self, input: &str) {
         self.external_line_tx.send(input.to_string()).unwrap();
     }
@@ -205,6 +213,12 @@ impl Handler {
             return None;
         }
 
+        if self.commands_enabled {
+            if let Some(command) = line.strip_prefix('/') {
+                return Some(UserInput::Command(command.trim().to_string()));
+            }
+        }
+
         let token_count = line.matches("@").count();
 
         if (token_count % 2) != 0 {
@@ -212,7 +226,7 @@ impl Handler {
         }
 
         let packet = if token_count == 2 && line.starts_with("@") && line.ends_with("@") {
-            sc64::DebugPacket {
+            DebugPacket {
                 datatype: DataType::RawBinary.into(),
                 data: match load_file(line.trim_matches('@')) {
                     Ok(data) => data,
@@ -249,7 +263,7 @@ impl Handler {
                 }
             }
             data.append(&mut b"\0".to_vec());
-            sc64::DebugPacket {
+            DebugPacket {
                 datatype: DataType::Text.into(),
                 data,
             }
@@ -267,8 +281,8 @@ impl Handler {
         Some(UserInput::Packet(packet))
     }
 
-    pub fn handle_debug_packet(&mut self, debug_packet: sc64::DebugPacket) {
-        let sc64::DebugPacket { datatype, data } = debug_packet;
+    pub fn handle_debug_packet(&mut self, debug_packet: DebugPacket) {
+        let DebugPacket { datatype, data } = debug_packet;
         match datatype.into() {
             DataType::Text => self.handle_datatype_text(&data),
             DataType::RawBinary => self.handle_datatype_raw_binary(&data),
@@ -283,11 +297,7 @@ impl Handler {
         self.print_text(data);
     }
 
-    pub fn handle_save_writeback(
-        &self,
-        save_writeback: sc64::SaveWriteback,
-        path: &Option<PathBuf>,
-    ) {
+    pub fn handle_save_writeback(&self, save_writeback: SaveWriteback, path: &Option<PathBuf>) {
         let filename = &if let Some(path) = path {
             path.to_string_lossy().to_string()
         } else {
@@ -0,0 +1,1222 @@
+use crate::Error;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::File,
+    io::{Cursor, Read, Seek, SeekFrom, Write},
+    ops::Range,
+};
+
+const BLOCKS_PER_TRACK: usize = 2;
+const SECTORS_PER_BLOCK: usize = 85;
+const SYSTEM_SECTOR_LENGTH: usize = 232;
+const BAD_TRACKS_PER_ZONE: usize = 12;
+const SYSTEM_AREA_LBAS: usize = 24;
+
+const D64_SYSTEM_DATA_OFFSET: usize = 0x000;
+const D64_ID_DATA_OFFSET: usize = 0x100;
+const D64_DATA_OFFSET: usize = 0x200;
+
+const RETAIL_COUNTRY_CODE: [u8; 4] = [0xE8, 0x48, 0xD3, 0x16];
+
+const OVERLAY_MAGIC: &[u8; 8] = b"SC64DDOV";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Retail,
+    Development,
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Retail => "Retail",
+            Self::Development => "Development",
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Ndd,
+    D64,
+}
+
+impl Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Ndd => "NDD",
+            Self::D64 => "D64",
+        })
+    }
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Ndd => "ndd",
+            Self::D64 => "d64",
+        }
+    }
+}
+
+struct SystemAreaInfo<'a> {
+    format: Format,
+    sector_length: usize,
+    sys_lba: &'a [usize],
+    bad_lba: &'a [usize],
+}
+
+const SYSTEM_AREA: [SystemAreaInfo; 2] = [
+    SystemAreaInfo {
+        format: Format::Retail,
+        sector_length: 232,
+        sys_lba: &[9, 8, 1, 0],
+        bad_lba: &[2, 3, 10, 11, 12, 16, 17, 18, 19, 20, 21, 22, 23],
+    },
+    SystemAreaInfo {
+        format: Format::Development,
+        sector_length: 192,
+        sys_lba: &[11, 10, 3, 2],
+        bad_lba: &[0, 1, 8, 9, 16, 17, 18, 19, 20, 21, 22, 23],
+    },
+];
+
+const ID_LBAS: [usize; 2] = [15, 14];
+
+/// Contents of system area LBAs synthesized from the .d64 header, indexed by LBA
+type SystemBlocks = HashMap<usize, Vec<u8>>;
+
+struct DiskZone {
+    head: usize,
+    sector_length: usize,
+    tracks: usize,
+    track_offset: usize,
+}
+
+macro_rules! zone {
+    ($h:expr, $l:expr, $t:expr, $o:expr) => {
+        DiskZone {
+            head: $h,
+            sector_length: $l,
+            tracks: $t,
+            track_offset: $o,
+        }
+    };
+}
+
+const ZONE_MAPPING: [DiskZone; 16] = [
+    zone!(0, 232, 158, 0),
+    zone!(0, 216, 158, 158),
+    zone!(0, 208, 149, 316),
+    zone!(0, 192, 149, 465),
+    zone!(0, 176, 149, 614),
+    zone!(0, 160, 149, 763),
+    zone!(0, 144, 149, 912),
+    zone!(0, 128, 114, 1061),
+    zone!(1, 216, 158, 0),
+    zone!(1, 208, 158, 158),
+    zone!(1, 192, 149, 316),
+    zone!(1, 176, 149, 465),
+    zone!(1, 160, 149, 614),
+    zone!(1, 144, 149, 763),
+    zone!(1, 128, 149, 912),
+    zone!(1, 112, 114, 1061),
+];
+
+const VZONE_TO_PZONE: [[usize; 16]; 7] = [
+    [0, 1, 2, 9, 8, 3, 4, 5, 6, 7, 15, 14, 13, 12, 11, 10],
+    [0, 1, 2, 3, 10, 9, 8, 4, 5, 6, 7, 15, 14, 13, 12, 11],
+    [0, 1, 2, 3, 4, 11, 10, 9, 8, 5, 6, 7, 15, 14, 13, 12],
+    [0, 1, 2, 3, 4, 5, 12, 11, 10, 9, 8, 6, 7, 15, 14, 13],
+    [0, 1, 2, 3, 4, 5, 6, 13, 12, 11, 10, 9, 8, 7, 15, 14],
+    [0, 1, 2, 3, 4, 5, 6, 7, 14, 13, 12, 11, 10, 9, 8, 15],
+    [0, 1, 2, 3, 4, 5, 6, 7, 15, 14, 13, 12, 11, 10, 9, 8],
+];
+
+const ROM_ZONES: [usize; 7] = [5, 7, 9, 11, 13, 15, 16];
+
+pub struct Zone {
+    pub vzone: usize,
+    pub pzone: usize,
+    pub head: usize,
+    pub tracks: Range<usize>,
+    pub lbas: Range<usize>,
+    pub block_length: usize,
+    pub writable: bool,
+    /// Defect tracks listed in the system area
+    pub bad_tracks: Vec<usize>,
+}
+
+pub struct Info {
+    pub format: Format,
+    pub image_format: ImageFormat,
+    pub disk_type: usize,
+    pub rom_lbas: Range<usize>,
+    pub ram_lbas: Range<usize>,
+    pub zones: Vec<Zone>,
+    pub id_data: Vec<u8>,
+}
+
+/// Outcome of the block write request
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum WriteResult {
+    Written,
+    /// Disk is write protected, block data was discarded
+    WriteProtected,
+    /// Block isn't present on the disk, belongs to the ROM area or has unexpected length
+    Rejected,
+}
+
+/// State of a single system LBA copy stored in the .ndd image
+pub struct SystemCopy {
+    pub lba: usize,
+    /// Sector repetitions different from the first sector of the block
+    pub mismatched_sectors: Vec<usize>,
+    /// Expected to be 0x10
+    pub format_byte: u8,
+    /// Upper nibble is expected to be 0x1, lower nibble holds the disk type
+    pub disk_type_byte: u8,
+    /// Problems found in the defect track table
+    pub defect_table_errors: Vec<String>,
+    /// Defect track table doesn't match the one in the copy used when loading the disk
+    pub defect_table_differs: bool,
+}
+
+impl SystemCopy {
+    pub fn is_valid(&self) -> bool {
+        self.mismatched_sectors.is_empty()
+            && self.format_byte == 0x10
+            && (self.disk_type_byte & 0xF0) == 0x10
+            && self.defect_table_errors.is_empty()
+    }
+
+    /// Copy has to be rebuilt from the one used when loading the disk
+    pub fn is_damaged(&self) -> bool {
+        !self.is_valid() || self.defect_table_differs
+    }
+}
+
+/// State of a single ID LBA copy stored in the .ndd image
+pub struct IdCopy {
+    pub lba: usize,
+    /// Sector repetitions different from the first sector of the block
+    pub mismatched_sectors: Vec<usize>,
+}
+
+impl IdCopy {
+    pub fn is_valid(&self) -> bool {
+        self.mismatched_sectors.is_empty()
+    }
+}
+
+pub struct Verification {
+    /// Format of the first system LBA set with a valid copy
+    pub format: Option<Format>,
+    /// Copies of the detected format, or copies of every format when none of them is valid
+    pub system_copies: Vec<SystemCopy>,
+    pub id_copies: Vec<IdCopy>,
+    /// LBA of the system copy used when loading the disk
+    pub system_source: Option<usize>,
+    /// LBA of the ID copy used when loading the disk
+    pub id_source: Option<usize>,
+}
+
+impl Verification {
+    pub fn is_damaged(&self) -> bool {
+        self.system_copies.iter().any(|copy| copy.is_damaged())
+            || self.id_copies.iter().any(|copy| !copy.is_valid())
+    }
+
+    pub fn is_repairable(&self) -> bool {
+        self.system_source.is_some() && self.id_source.is_some()
+    }
+}
+
+struct Mapping {
+    lba: usize,
+    offset: usize,
+    length: usize,
+    writable: bool,
+}
+
+pub trait DiskFile: Read + Write + Seek {}
+
+impl<T: Read + Write + Seek> DiskFile for T {}
+
+pub struct Disk {
+    file: Box<dyn DiskFile>,
+    system_area: SystemArea,
+    image_format: ImageFormat,
+    mapping: HashMap<usize, Mapping>,
+    system_blocks: SystemBlocks,
+    overlay: Option<Overlay>,
+    read_only: bool,
+}
+
+impl Disk {
+    pub fn get_format(&self) -> &Format {
+        &self.system_area.format
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Rejects all writes like on a write protected disk
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn get_image_format(&self) -> &ImageFormat {
+        &self.image_format
+    }
+
+    pub fn get_lba(&self, track: u32, head: u32, block: u32) -> Option<usize> {
+        if head == 0 && track < 12 {
+            return Some((track << 1 | block ^ (track % 2)) as usize);
+        }
+        let location = track << 2 | head << 1 | block;
+        self.mapping
+            .get(&(location as usize))
+            .map(|block| block.lba)
+    }
+
+    /// Returns virtual zone of the block
+    pub fn get_zone(&self, track: u32, head: u32, block: u32) -> Option<usize> {
+        let lba = self.get_lba(track, head, block)?;
+        zone_lbas(self.system_area.disk_type)
+            .iter()
+            .position(|(_, lbas)| lbas.contains(&lba))
+    }
+
+    pub fn read_block(
+        &mut self,
+        track: u32,
+        head: u32,
+        block: u32,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let location = track << 2 | head << 1 | block;
+        if let Some(block) = self.mapping.get(&(location as usize)) {
+            if let Some(data) = self
+                .overlay
+                .as_ref()
+                .and_then(|overlay| overlay.blocks.get(&(location as usize)))
+            {
+                return Ok(Some(data.clone()));
+            }
+            if let Some(data) = self.system_blocks.get(&block.lba) {
+                return Ok(Some(data.clone()));
+            }
+            let mut data = vec![0u8; block.length];
+            self.file.seek(SeekFrom::Start(block.offset as u64))?;
+            self.file.read_exact(&mut data)?;
+            return Ok(Some(data));
+        }
+        Ok(None)
+    }
+
+    pub fn write_block(
+        &mut self,
+        track: u32,
+        head: u32,
+        block: u32,
+        data: &[u8],
+    ) -> Result<WriteResult, Error> {
+        let location = track << 2 | head << 1 | block;
+        let Some(block) = self.mapping.get(&(location as usize)) else {
+            return Ok(WriteResult::Rejected);
+        };
+        if block.length != data.len() {
+            return Ok(WriteResult::Rejected);
+        }
+        if self.read_only {
+            return Ok(WriteResult::WriteProtected);
+        }
+        if !block.writable {
+            return Ok(WriteResult::Rejected);
+        }
+        if let Some(overlay) = self.overlay.as_mut() {
+            overlay.write(location as usize, data)?;
+        } else {
+            self.file.seek(SeekFrom::Start(block.offset as u64))?;
+            self.file.write_all(data)?;
+        }
+        Ok(WriteResult::Written)
+    }
+
+    pub fn get_info(&self) -> Info {
+        let SystemArea {
+            format,
+            disk_type,
+            sys_data,
+            id_data,
+            ..
+        } = &self.system_area;
+        let defect_tracks = defect_tracks(sys_data);
+        let zones: Vec<Zone> = zone_lbas(*disk_type)
+            .into_iter()
+            .enumerate()
+            .map(|(vzone, (pzone, lbas))| {
+                let zone = &ZONE_MAPPING[pzone];
+                Zone {
+                    vzone,
+                    pzone,
+                    head: zone.head,
+                    tracks: zone.track_offset..(zone.track_offset + zone.tracks),
+                    lbas,
+                    block_length: zone.sector_length * SECTORS_PER_BLOCK,
+                    writable: vzone >= ROM_ZONES[*disk_type],
+                    bad_tracks: defect_tracks[pzone]
+                        .iter()
+                        .map(|track| zone.track_offset + track)
+                        .collect(),
+                }
+            })
+            .collect();
+        let ram_start = zones
+            .iter()
+            .find(|zone| zone.writable)
+            .map(|zone| zone.lbas.start)
+            .unwrap_or(lba_count());
+        Info {
+            format: *format,
+            image_format: self.image_format,
+            disk_type: *disk_type,
+            rom_lbas: SYSTEM_AREA_LBAS..ram_start,
+            ram_lbas: ram_start..lba_count(),
+            zones,
+            id_data: id_data.clone(),
+        }
+    }
+
+    /// Writes disk contents in provided image format, LBAs not present in the source image are filled with zeros
+    pub fn write_image<W: Write>(
+        &mut self,
+        writer: &mut W,
+        image_format: ImageFormat,
+    ) -> Result<(), Error> {
+        let blocks: HashMap<usize, (usize, usize)> = self
+            .mapping
+            .values()
+            .map(|block| (block.lba, (block.offset, block.length)))
+            .collect();
+        let file = &mut self.file;
+        let system_blocks = &self.system_blocks;
+        write_image(writer, image_format, &self.system_area, |lba| {
+            if let Some(data) = system_blocks.get(&lba) {
+                return Ok(Some(data.clone()));
+            }
+            if let Some(&(offset, length)) = blocks.get(&lba) {
+                let mut data = vec![0u8; length];
+                file.seek(SeekFrom::Start(offset as u64))?;
+                file.read_exact(&mut data)?;
+                return Ok(Some(data));
+            }
+            Ok(None)
+        })
+    }
+
+    /// Returns block offsets in the .ndd copy of the disk for every track/head/block index, as expected by the SD mode
+    pub fn get_thb_table(&self) -> Vec<u32> {
+        const THB_UNMAPPED: u32 = 0xFFFF_FFFF;
+        const THB_WRITABLE_FLAG: u32 = 1 << 31;
+        let tracks = ZONE_MAPPING
+            .iter()
+            .map(|zone| zone.track_offset + zone.tracks)
+            .max()
+            .unwrap_or(0);
+        let mut thb_table = vec![THB_UNMAPPED; tracks << 2];
+        let mapping = build_mapping(&self.system_area, |_, offset, _| Some(offset));
+        for (location, block) in mapping {
+            let writable = block.writable && !self.read_only;
+            thb_table[location] =
+                block.offset as u32 | if writable { THB_WRITABLE_FLAG } else { 0 };
+        }
+        thb_table
+    }
+
+    /// Returns ranges of the .ndd copy of the disk that can't be modified by disk writes, adjacent blocks are merged
+    pub fn get_ndd_read_only_ranges(&self) -> Vec<Range<usize>> {
+        let mut blocks: Vec<Range<usize>> =
+            build_mapping(&self.system_area, |_, offset, _| Some(offset))
+                .into_values()
+                .filter(|block| !block.writable)
+                .map(|block| block.offset..(block.offset + block.length))
+                .collect();
+        blocks.sort_by_key(|block| block.start);
+        let mut ranges: Vec<Range<usize>> = vec![];
+        for block in blocks {
+            match ranges.last_mut() {
+                Some(range) if range.end == block.start => range.end = block.end,
+                _ => ranges.push(block),
+            }
+        }
+        ranges
+    }
+
+    /// Redirects all writes to the overlay, base disk image stays unmodified
+    pub fn set_overlay(&mut self, overlay: Overlay) -> Result<(), Error> {
+        for (location, data) in overlay.blocks.iter() {
+            match self.mapping.get(location) {
+                Some(block) if block.writable && block.length == data.len() => {}
+                _ => {
+                    return Err(Error::InvalidInput(
+                        "Disk overlay doesn't match the disk image".into(),
+                    ))
+                }
+            }
+        }
+        self.overlay = Some(overlay);
+        Ok(())
+    }
+
+    pub fn get_overlay(&self) -> Option<&Overlay> {
+        self.overlay.as_ref()
+    }
+
+    /// Writes blocks stored in the overlay to the disk image and detaches the overlay, returns number of written blocks
+    pub fn commit_overlay(&mut self) -> Result<usize, Error> {
+        let Some(overlay) = self.overlay.take() else {
+            return Ok(0);
+        };
+        for (location, data) in overlay.blocks.iter() {
+            let block = &self.mapping[location];
+            self.file.seek(SeekFrom::Start(block.offset as u64))?;
+            self.file.write_all(data)?;
+        }
+        self.file.flush()?;
+        Ok(overlay.blocks.len())
+    }
+}
+
+/// Blocks written to the disk, stored in memory and optionally in a delta file next to the disk image
+pub struct Overlay {
+    file: Option<File>,
+    blocks: HashMap<usize, Vec<u8>>,
+}
+
+impl Overlay {
+    /// Overlay kept only in memory, all writes are lost when disk is closed
+    pub fn volatile() -> Self {
+        Overlay {
+            file: None,
+            blocks: HashMap::new(),
+        }
+    }
+
+    /// Opens existing delta file or creates a new one
+    pub fn open(path: &str) -> Result<Self, Error> {
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let mut data = vec![];
+        file.read_to_end(&mut data)?;
+
+        let invalid = || Error::InvalidInput(format!("Disk overlay file [{path}] is not valid"));
+        let mut blocks = HashMap::new();
+        let mut records = 0;
+        if !data.is_empty() {
+            let mut records_data = data.strip_prefix(OVERLAY_MAGIC).ok_or_else(invalid)?;
+            while !records_data.is_empty() {
+                if records_data.len() < 8 {
+                    return Err(invalid());
+                }
+                let location = u32::from_be_bytes(records_data[0..4].try_into().unwrap()) as usize;
+                let length = u32::from_be_bytes(records_data[4..8].try_into().unwrap()) as usize;
+                let block = records_data.get(8..(8 + length)).ok_or_else(invalid)?;
+                blocks.insert(location, block.to_vec());
+                records_data = &records_data[(8 + length)..];
+                records += 1;
+            }
+        }
+
+        let mut overlay = Overlay {
+            file: Some(file),
+            blocks,
+        };
+        // Rewrite the file when it's new or when some blocks were written more than once
+        if data.is_empty() || records != overlay.blocks.len() {
+            overlay.rewrite()?;
+        }
+        Ok(overlay)
+    }
+
+    /// Number of blocks stored in the overlay
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn is_volatile(&self) -> bool {
+        self.file.is_none()
+    }
+
+    fn write(&mut self, location: usize, data: &[u8]) -> Result<(), Error> {
+        if let Some(file) = self.file.as_mut() {
+            file.seek(SeekFrom::End(0))?;
+            file.write_all(&overlay_record(location, data))?;
+        }
+        self.blocks.insert(location, data.to_vec());
+        Ok(())
+    }
+
+    fn rewrite(&mut self) -> Result<(), Error> {
+        if let Some(file) = self.file.as_mut() {
+            let mut data = OVERLAY_MAGIC.to_vec();
+            for (location, block) in self.blocks.iter() {
+                data.append(&mut overlay_record(*location, block));
+            }
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&data)?;
+        }
+        Ok(())
+    }
+}
+
+fn overlay_record(location: usize, data: &[u8]) -> Vec<u8> {
+    let mut record = vec![];
+    record.extend_from_slice(&(location as u32).to_be_bytes());
+    record.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    record.extend_from_slice(data);
+    record
+}
+
+/// Path of the delta file used by the overlay of provided disk image
+pub fn overlay_path(path: &str) -> String {
+    format!("{path}.overlay")
+}
+
+fn load(mut file: Box<dyn DiskFile>) -> Result<Disk, Error> {
+    let length = file.seek(SeekFrom::End(0))? as usize;
+    let (image_format, system_area, mapping, system_blocks) = if length == ndd_length() {
+        let (system_area, mapping) = load_ndd(&mut file, length)?;
+        (ImageFormat::Ndd, system_area, mapping, HashMap::new())
+    } else {
+        match d64_disk_type(&mut file, length)? {
+            Some(disk_type) if length == d64_length(disk_type) => {
+                let (system_area, mapping, system_blocks) = load_d64(&mut file, length)?;
+                (ImageFormat::D64, system_area, mapping, system_blocks)
+            }
+            Some(disk_type) => {
+                return Err(Error::InvalidInput(format!(
+                    "Provided .d64 disk file length [{length}] doesn't match expected length [{}] for disk type {disk_type}",
+                    d64_length(disk_type)
+                )))
+            }
+            None => {
+                return Err(Error::InvalidInput(format!(
+                    "Provided .ndd disk file length [{length}] doesn't match expected length [{}]",
+                    ndd_length()
+                )))
+            }
+        }
+    };
+    Ok(Disk {
+        file,
+        system_area,
+        image_format,
+        mapping,
+        system_blocks,
+        overlay: None,
+        read_only: false,
+    })
+}
+
+pub fn open(path: &str) -> Result<Disk, Error> {
+    load(Box::new(File::options().read(true).write(true).open(path)?))
+}
+
+/// Opens disk image without write access, disk is marked as read-only
+pub fn open_read_only(path: &str) -> Result<Disk, Error> {
+    let mut disk = load(Box::new(File::open(path)?))?;
+    disk.set_read_only(true);
+    Ok(disk)
+}
+
+pub fn open_memory(data: Vec<u8>) -> Result<Disk, Error> {
+    load(Box::new(Cursor::new(data)))
+}
+
+pub fn check_formats(disks: &[Disk]) -> Result<(), Error> {
+    if !disks
+        .windows(2)
+        .all(|d| d[0].get_format() == d[1].get_format())
+    {
+        return Err(Error::InvalidInput("Disk format mismatch".into()));
+    }
+    Ok(())
+}
+
+pub fn open_multiple(paths: &[String]) -> Result<Vec<Disk>, Error> {
+    let mut disks: Vec<Disk> = Vec::new();
+    for path in paths {
+        let disk = open(path)?;
+        disks.push(disk);
+    }
+    check_formats(&disks)?;
+    Ok(disks)
+}
+
+/// Creates blank disk image with system area describing disk without any defect tracks and empty ID
+pub fn create<W: Write>(
+    writer: &mut W,
+    image_format: ImageFormat,
+    format: Format,
+    disk_type: usize,
+) -> Result<(), Error> {
+    if disk_type >= VZONE_TO_PZONE.len() {
+        return Err(Error::InvalidInput("Unknown disk type".into()));
+    }
+    let mut sys_data = vec![0u8; SYSTEM_SECTOR_LENGTH];
+    if format == Format::Retail {
+        sys_data[0..4].copy_from_slice(&RETAIL_COUNTRY_CODE);
+    }
+    sys_data[4] = 0x10;
+    sys_data[5] = 0x10 | disk_type as u8;
+    let system_area = SystemArea {
+        format,
+        disk_type,
+        sys_data,
+        id_data: vec![0u8; SYSTEM_SECTOR_LENGTH],
+        bad_lbas: vec![],
+    };
+    let system_blocks = system_blocks(&system_area);
+    write_image(writer, image_format, &system_area, |lba| {
+        Ok(system_blocks.get(&lba).cloned())
+    })
+}
+
+/// Checks every system and ID LBA copy of the .ndd image, works also on images that fail to load
+pub fn verify<T: Read + Seek>(file: &mut T) -> Result<Verification, Error> {
+    if file.seek(SeekFrom::End(0))? as usize != ndd_length() {
+        return Err(Error::InvalidInput(
+            "System area copies can be verified only in .ndd disk images".into(),
+        ));
+    }
+
+    let mut format: Option<Format> = None;
+    let mut system_copies: Vec<SystemCopy> = Vec::new();
+    let mut system_source: Option<usize> = None;
+    for info in SYSTEM_AREA {
+        let mut copies: Vec<(SystemCopy, Vec<u8>)> = Vec::new();
+        for &lba in info.sys_lba {
+            let data = load_sys_lba(file, lba)?;
+            let copy = SystemCopy {
+                lba,
+                mismatched_sectors: mismatched_sectors(&data, info.sector_length),
+                format_byte: data[4],
+                disk_type_byte: data[5],
+                defect_table_errors: defect_table_errors(&data),
+                defect_table_differs: false,
+            };
+            copies.push((copy, data));
+        }
+        // Disk loader keeps the data of the last valid copy
+        let source = copies
+            .iter()
+            .rev()
+            .find(|(copy, _)| copy.is_valid())
+            .map(|(copy, data)| (copy.lba, defect_tracks(data)));
+        if let Some((lba, source_tracks)) = source {
+            system_copies = copies
+                .into_iter()
+                .map(|(mut copy, data)| {
+                    copy.defect_table_differs = copy.defect_table_errors.is_empty()
+                        && defect_tracks(&data) != source_tracks;
+                    copy
+                })
+                .collect();
+            format = Some(info.format);
+            system_source = Some(lba);
+            break;
+        }
+        system_copies.extend(copies.into_iter().map(|(copy, _)| copy));
+    }
+
+    let mut id_copies: Vec<IdCopy> = Vec::new();
+    for lba in ID_LBAS {
+        let data = load_sys_lba(file, lba)?;
+        id_copies.push(IdCopy {
+            lba,
+            mismatched_sectors: mismatched_sectors(&data, SYSTEM_SECTOR_LENGTH),
+        });
+    }
+    let id_source = id_copies
+        .iter()
+        .find(|copy| copy.is_valid())
+        .map(|copy| copy.lba);
+
+    Ok(Verification {
+        format,
+        system_copies,
+        id_copies,
+        system_source,
+        id_source,
+    })
+}
+
+/// Overwrites damaged system and ID LBA copies of the .ndd image with the copies used when loading the disk, returns rewritten LBAs
+///
+/// Valid system copies with the defect track table different from the used copy are rewritten as well
+pub fn repair<T: Read + Write + Seek>(file: &mut T) -> Result<Vec<usize>, Error> {
+    let verification = verify(file)?;
+    let Some(system_source) = verification.system_source else {
+        return Err(Error::InvalidInput(
+            "No valid system LBA copy found, disk can't be repaired".into(),
+        ));
+    };
+    let Some(id_source) = verification.id_source else {
+        return Err(Error::InvalidInput(
+            "No valid ID LBA copy found, disk can't be repaired".into(),
+        ));
+    };
+    let system_data = load_sys_lba(file, system_source)?;
+    let id_data = load_sys_lba(file, id_source)?;
+
+    let damaged = verification
+        .system_copies
+        .iter()
+        .filter(|copy| copy.is_damaged())
+        .map(|copy| (copy.lba, &system_data))
+        .chain(
+            verification
+                .id_copies
+                .iter()
+                .filter(|copy| !copy.is_valid())
+                .map(|copy| (copy.lba, &id_data)),
+        );
+    let mut repaired: Vec<usize> = Vec::new();
+    for (lba, data) in damaged {
+        file.seek(SeekFrom::Start((lba * data.len()) as u64))?;
+        file.write_all(data)?;
+        repaired.push(lba);
+    }
+    file.flush()?;
+
+    Ok(repaired)
+}
+
+struct SystemArea {
+    format: Format,
+    disk_type: usize,
+    sys_data: Vec<u8>,
+    id_data: Vec<u8>,
+    bad_lbas: Vec<usize>,
+}
+
+fn system_area_info(format: Format) -> &'static SystemAreaInfo<'static> {
+    SYSTEM_AREA
+        .iter()
+        .find(|info| info.format == format)
+        .unwrap()
+}
+
+fn lba_count() -> usize {
+    ZONE_MAPPING
+        .iter()
+        .map(|zone| (zone.tracks - BAD_TRACKS_PER_ZONE) * BLOCKS_PER_TRACK)
+        .sum()
+}
+
+/// Returns physical zone and LBA range for every virtual zone of the disk type
+fn zone_lbas(disk_type: usize) -> Vec<(usize, Range<usize>)> {
+    let mut lba = 0;
+    VZONE_TO_PZONE[disk_type]
+        .iter()
+        .map(|&pzone| {
+            let start = lba;
+            lba += (ZONE_MAPPING[pzone].tracks - BAD_TRACKS_PER_ZONE) * BLOCKS_PER_TRACK;
+            (pzone, start..lba)
+        })
+        .collect()
+}
+
+/// Length of the .ndd image, it contains every LBA including system area
+pub fn ndd_length() -> usize {
+    ZONE_MAPPING
+        .iter()
+        .map(|zone| {
+            (zone.tracks - BAD_TRACKS_PER_ZONE)
+                * BLOCKS_PER_TRACK
+                * zone.sector_length
+                * SECTORS_PER_BLOCK
+        })
+        .sum()
+}
+
+/// Length of the .d64 image, it contains the header followed by every user area LBA of the disk type
+fn d64_length(disk_type: usize) -> usize {
+    let user_area_length: usize = zone_lbas(disk_type)
+        .into_iter()
+        .map(|(pzone, lbas)| {
+            lbas.filter(|lba| *lba >= SYSTEM_AREA_LBAS).count()
+                * ZONE_MAPPING[pzone].sector_length
+                * SECTORS_PER_BLOCK
+        })
+        .sum();
+    D64_DATA_OFFSET + user_area_length
+}
+
+/// Disk type stored in the .d64 header, `None` when file doesn't start with the .d64 header
+fn d64_disk_type<T: Read + Seek>(file: &mut T, length: usize) -> Result<Option<usize>, Error> {
+    if length < D64_DATA_OFFSET {
+        return Ok(None);
+    }
+    let mut header = vec![0u8; D64_DATA_OFFSET];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)?;
+
+    let sys_data = &header[D64_SYSTEM_DATA_OFFSET..];
+    let disk_type = (sys_data[5] & 0x0F) as usize;
+    if (sys_data[4] != 0x10) || ((sys_data[5] & 0xF0) != 0x10) || disk_type >= VZONE_TO_PZONE.len()
+    {
+        return Ok(None);
+    }
+    // Header pads system data with zeroes up to the ID data, while .ndd repeats the system data sector here
+    let sector_length = system_area_info(d64_format(sys_data)).sector_length;
+    if header[(D64_SYSTEM_DATA_OFFSET + sector_length)..D64_ID_DATA_OFFSET]
+        .iter()
+        .any(|byte| *byte != 0)
+    {
+        return Ok(None);
+    }
+    Ok(Some(disk_type))
+}
+
+/// Development disks have no country code set
+fn d64_format(sys_data: &[u8]) -> Format {
+    if sys_data[0..4] == [0, 0, 0, 0] {
+        Format::Development
+    } else {
+        Format::Retail
+    }
+}
+
+/// System and ID LBAs contents as they are stored on the disk
+fn system_blocks(system_area: &SystemArea) -> SystemBlocks {
+    let info = system_area_info(system_area.format);
+    let block_length = SYSTEM_SECTOR_LENGTH * SECTORS_PER_BLOCK;
+    let repeat_sector = |sector: &[u8]| {
+        let mut data = sector.repeat(SECTORS_PER_BLOCK);
+        data.resize(block_length, 0);
+        data
+    };
+    let mut system_blocks = SystemBlocks::new();
+    for &lba in info.sys_lba {
+        system_blocks.insert(
+            lba,
+            repeat_sector(&system_area.sys_data[0..info.sector_length]),
+        );
+    }
+    for lba in ID_LBAS {
+        system_blocks.insert(lba, repeat_sector(&system_area.id_data));
+    }
+    system_blocks
+}
+
+fn write_image<W: Write>(
+    writer: &mut W,
+    image_format: ImageFormat,
+    system_area: &SystemArea,
+    mut read_lba: impl FnMut(usize) -> Result<Option<Vec<u8>>, Error>,
+) -> Result<(), Error> {
+    let first_lba = match image_format {
+        ImageFormat::Ndd => 0,
+        ImageFormat::D64 => {
+            let sector_length = system_area_info(system_area.format).sector_length;
+            let mut header = vec![0u8; D64_DATA_OFFSET];
+            header[D64_SYSTEM_DATA_OFFSET..(D64_SYSTEM_DATA_OFFSET + sector_length)]
+                .copy_from_slice(&system_area.sys_data[0..sector_length]);
+            header[D64_ID_DATA_OFFSET..(D64_ID_DATA_OFFSET + SYSTEM_SECTOR_LENGTH)]
+                .copy_from_slice(&system_area.id_data);
+            writer.write_all(&header)?;
+            SYSTEM_AREA_LBAS
+        }
+    };
+    for (pzone, lbas) in zone_lbas(system_area.disk_type) {
+        let block_length = ZONE_MAPPING[pzone].sector_length * SECTORS_PER_BLOCK;
+        for lba in lbas.filter(|lba| *lba >= first_lba) {
+            let mut data = read_lba(lba)?.unwrap_or_default();
+            data.resize(block_length, 0);
+            writer.write_all(&data)?;
+        }
+    }
+    Ok(())
+}
+
+fn load_ndd<T: Read + Seek>(
+    file: &mut T,
+    length: usize,
+) -> Result<(SystemArea, HashMap<usize, Mapping>), Error> {
+    let system_area = load_ndd_system_area(file)?;
+    let mapping = build_mapping(&system_area, |_, offset, block_length| {
+        (offset + block_length <= length).then_some(offset)
+    });
+    Ok((system_area, mapping))
+}
+
+fn load_ndd_system_area<T: Read + Seek>(file: &mut T) -> Result<SystemArea, Error> {
+    let mut disk_format: Option<Format> = None;
+    let mut disk_type: usize = 0;
+    let mut sys_data = vec![0u8; SYSTEM_SECTOR_LENGTH];
+    let mut bad_lbas: Vec<usize> = Vec::new();
+
+    for info in SYSTEM_AREA {
+        bad_lbas.clear();
+        for &lba in info.sys_lba {
+            let data = load_sys_lba(file, lba)?;
+            if verify_sys_lba(&data, info.sector_length) {
+                if (data[4] != 0x10)
+                    || ((data[5] & 0xF0) != 0x10)
+                    || !defect_table_errors(&data).is_empty()
+                {
+                    bad_lbas.push(lba);
+                } else {
+                    disk_format = Some(info.format);
+                    disk_type = (data[5] & 0x0F) as usize;
+                    sys_data = data[0..SYSTEM_SECTOR_LENGTH].to_vec();
+                }
+            } else {
+                bad_lbas.push(lba);
+            }
+        }
+        if disk_format.is_some() {
+            bad_lbas.append(&mut info.bad_lba.to_vec());
+            break;
+        }
+    }
+    if disk_format.is_none() {
+        return Err(Error::InvalidInput(
+            "Provided 64DD disk file is not valid".into(),
+        ));
+    }
+    if disk_type >= VZONE_TO_PZONE.len() {
+        return Err(Error::InvalidInput("Unknown disk type".into()));
+    }
+
+    let mut id_data: Option<Vec<u8>> = None;
+    for lba in ID_LBAS {
+        let data = load_sys_lba(file, lba)?;
+        if verify_sys_lba(&data, SYSTEM_SECTOR_LENGTH) {
+            id_data.get_or_insert(data[0..SYSTEM_SECTOR_LENGTH].to_vec());
+        } else {
+            bad_lbas.push(lba);
+        }
+    }
+    let Some(id_data) = id_data else {
+        return Err(Error::InvalidInput("No valid ID LBA found".into()));
+    };
+
+    Ok(SystemArea {
+        format: disk_format.unwrap(),
+        disk_type,
+        sys_data,
+        id_data,
+        bad_lbas,
+    })
+}
+
+/// Loads .d64 image, it contains only system and ID data followed by the user area LBAs
+fn load_d64<T: Read + Seek>(
+    file: &mut T,
+    length: usize,
+) -> Result<(SystemArea, HashMap<usize, Mapping>, SystemBlocks), Error> {
+    if length < D64_DATA_OFFSET {
+        return Err(Error::InvalidInput(
+            "Provided 64DD disk file is not valid".into(),
+        ));
+    }
+    let mut header = vec![0u8; D64_DATA_OFFSET];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)?;
+
+    let sys_data =
+        header[D64_SYSTEM_DATA_OFFSET..(D64_SYSTEM_DATA_OFFSET + SYSTEM_SECTOR_LENGTH)].to_vec();
+    let id_data = header[D64_ID_DATA_OFFSET..(D64_ID_DATA_OFFSET + SYSTEM_SECTOR_LENGTH)].to_vec();
+    if (sys_data[4] != 0x10)
+        || ((sys_data[5] & 0xF0) != 0x10)
+        || !defect_table_errors(&sys_data).is_empty()
+    {
+        return Err(Error::InvalidInput(
+            "Provided 64DD disk file is not valid".into(),
+        ));
+    }
+    let disk_type = (sys_data[5] & 0x0F) as usize;
+    if disk_type >= VZONE_TO_PZONE.len() {
+        return Err(Error::InvalidInput("Unknown disk type".into()));
+    }
+
+    let format = d64_format(&sys_data);
+    let info = system_area_info(format);
+
+    let system_area = SystemArea {
+        format,
+        disk_type,
+        sys_data,
+        id_data,
+        bad_lbas: (0..SYSTEM_AREA_LBAS)
+            .filter(|lba| !info.sys_lba.contains(lba) && !ID_LBAS.contains(lba))
+            .collect(),
+    };
+    let system_blocks = system_blocks(&system_area);
+    let user_area_offset = SYSTEM_AREA_LBAS * SYSTEM_SECTOR_LENGTH * SECTORS_PER_BLOCK;
+    let mapping = build_mapping(&system_area, |lba, offset, block_length| {
+        if lba < SYSTEM_AREA_LBAS {
+            return Some(0);
+        }
+        let offset = D64_DATA_OFFSET + offset - user_area_offset;
+        (offset + block_length <= length).then_some(offset)
+    });
+
+    Ok((system_area, mapping, system_blocks))
+}
+
+/// Builds block mapping, `place` returns offset of the LBA in the image file (offset argument is the .ndd offset)
+fn build_mapping(
+    system_area: &SystemArea,
+    place: impl Fn(usize, usize, usize) -> Option<usize>,
+) -> HashMap<usize, Mapping> {
+    let SystemArea {
+        disk_type,
+        sys_data,
+        bad_lbas,
+        ..
+    } = system_area;
+    let disk_type = *disk_type;
+
+    let mut zone_bad_tracks = defect_tracks(sys_data);
+
+    // Unused spare tracks at the end of the zone are skipped like the defect ones
+    for (bad_tracks, info) in zone_bad_tracks.iter_mut().zip(ZONE_MAPPING.iter()) {
+        for track in 0..(BAD_TRACKS_PER_ZONE - bad_tracks.len()) {
+            bad_tracks.push(info.tracks - track - 1);
+        }
+    }
+
+    let mut mapping = HashMap::new();
+
+    let mut lba: usize = 0;
+    let mut offset: usize = 0;
+    let mut starting_block: usize = 0;
+
+    for (vzone, &pzone) in VZONE_TO_PZONE[disk_type].iter().enumerate() {
+        let DiskZone {
+            head,
+            sector_length,
+            tracks,
+            track_offset,
+        } = ZONE_MAPPING[pzone];
+
+        let zone_tracks: Box<dyn Iterator<Item = usize>> = if head == 0 {
+            Box::new(0..tracks)
+        } else {
+            Box::new((0..tracks).rev())
+        };
+
+        for zone_track in zone_tracks {
+            if !zone_bad_tracks[pzone].contains(&zone_track) {
+                for block in 0..BLOCKS_PER_TRACK {
+                    let track = track_offset + zone_track;
+                    let location = (track << 2) | (head << 1) | (starting_block ^ block);
+                    let length = sector_length * SECTORS_PER_BLOCK;
+                    let file_offset = place(lba, offset, length);
+                    if let (false, Some(offset)) = (bad_lbas.contains(&lba), file_offset) {
+                        let writable = vzone >= ROM_ZONES[disk_type];
+                        mapping.insert(
+                            location,
+                            Mapping {
+                                lba,
+                                offset,
+                                length,
+                                writable,
+                            },
+                        );
+                    }
+                    lba += 1;
+                    offset += length;
+                }
+                starting_block ^= 1;
+            }
+        }
+    }
+
+    mapping
+}
+
+/// Returns defect tracks listed in the system area for every physical zone
+fn defect_tracks(sys_data: &[u8]) -> Vec<Vec<usize>> {
+    let mut zone_bad_tracks: Vec<Vec<usize>> = Vec::new();
+
+    for zone in 0..ZONE_MAPPING.len() {
+        let mut bad_tracks: Vec<usize> = Vec::new();
+        let start = if zone == 0 { 0 } else { sys_data[0x07 + zone] };
+        let stop = sys_data[0x07 + zone + 1];
+        for offset in start..stop {
+            bad_tracks.push(sys_data[0x20 + offset as usize] as usize);
+        }
+        zone_bad_tracks.push(bad_tracks);
+    }
+
+    zone_bad_tracks
+}
+
+/// Describes problems in the defect track table, table with invalid zone offsets would break the block mapping
+fn defect_table_errors(sys_data: &[u8]) -> Vec<String> {
+    let mut errors: Vec<String> = Vec::new();
+    let mut start = 0;
+    for (zone, info) in ZONE_MAPPING.iter().enumerate() {
+        let stop = sys_data[0x08 + zone] as usize;
+        if stop < start || (stop - start) > BAD_TRACKS_PER_ZONE {
+            errors.push(format!(
+                "Zone {zone} defect table offsets are invalid ({start} - {stop})"
+            ));
+            break;
+        }
+        let tracks = &sys_data[(0x20 + start)..(0x20 + stop)];
+        if tracks.iter().any(|&track| track as usize >= info.tracks) {
+            errors.push(format!(
+                "Zone {zone} lists defect track outside of the zone"
+            ));
+        }
+        if tracks
+            .iter()
+            .enumerate()
+            .any(|(i, track)| tracks[..i].contains(track))
+        {
+            errors.push(format!("Zone {zone} lists the same defect track twice"));
+        }
+        start = stop;
+    }
+    errors
+}
+
+fn load_sys_lba<T: Read + Seek>(file: &mut T, lba: usize) -> Result<Vec<u8>, Error> {
+    let length = SYSTEM_SECTOR_LENGTH * SECTORS_PER_BLOCK;
+    file.seek(SeekFrom::Start((lba * length) as u64))?;
+    let mut data = vec![0u8; length];
+    file.read_exact(&mut data)?;
+    Ok(data)
+}
+
+fn verify_sys_lba(data: &[u8], sector_length: usize) -> bool {
+    mismatched_sectors(data, sector_length).is_empty()
+}
+
+/// Returns sector repetitions of the system area block that differ from its first sector
+fn mismatched_sectors(data: &[u8], sector_length: usize) -> Vec<usize> {
+    let sys_data = &data[0..sector_length];
+    (1..SECTORS_PER_BLOCK)
+        .filter(|sector| {
+            let offset = sector * sector_length;
+            &data[offset..(offset + sector_length)] != sys_data
+        })
+        .collect()
+}
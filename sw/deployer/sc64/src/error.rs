@@ -0,0 +1,128 @@
+use super::{ff, SdCardResult};
+use std::fmt::{Display, Formatter, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    Connection,
+    Timeout,
+    Protocol,
+    Firmware,
+    SdCard,
+    FileSystem,
+    Io,
+    InvalidInput,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    DeviceNotFound,
+    InvalidPort(String),
+    ConnectionFailed {
+        address: String,
+        source: std::io::Error,
+    },
+    NotSc64Device,
+    UnsupportedFirmware {
+        major: u16,
+        minor: u16,
+    },
+    FirmwareUpdate(String),
+    CommandFailed {
+        id: u8,
+    },
+    Protocol(String),
+    Timeout(std::io::Error),
+    SdCard(SdCardResult),
+    FatFs(ff::Error),
+    Io(std::io::Error),
+    InvalidInput(String),
+    Context {
+        description: String,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::DeviceNotFound
+            | Self::InvalidPort(_)
+            | Self::ConnectionFailed { .. }
+            | Self::NotSc64Device => ErrorKind::Connection,
+            Self::UnsupportedFirmware { .. } | Self::FirmwareUpdate(_) => ErrorKind::Firmware,
+            Self::CommandFailed { .. } | Self::Protocol(_) => ErrorKind::Protocol,
+            Self::Timeout(_) => ErrorKind::Timeout,
+            Self::SdCard(_) => ErrorKind::SdCard,
+            Self::FatFs(_) => ErrorKind::FileSystem,
+            Self::Io(_) => ErrorKind::Io,
+            Self::InvalidInput(_) => ErrorKind::InvalidInput,
+            Self::Context { source, .. } => source.kind(),
+        }
+    }
+
+    pub fn context(self, description: &str) -> Self {
+        Self::Context {
+            description: description.to_string(),
+            source: Box::new(self),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ConnectionFailed { source, .. } => Some(source),
+            Self::Timeout(error) | Self::Io(error) => Some(error),
+            Self::FatFs(error) => Some(error),
+            Self::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            Self::DeviceNotFound => f.write_str("No SC64 devices found"),
+            Self::InvalidPort(port) => write!(f, "Invalid port prefix provided [{port}]"),
+            Self::ConnectionFailed { address, .. } => write!(f, "Couldn't connect to [{address}]"),
+            Self::NotSc64Device => f.write_str("Unknown identifier received, not a SC64 device"),
+            Self::UnsupportedFirmware { major, minor } => write!(
+                f,
+                "Unsupported SC64 firmware version, minimum supported version: {major}.{minor}.x"
+            ),
+            Self::FirmwareUpdate(description) => f.write_str(description),
+            Self::CommandFailed { id } => {
+                write!(f, "Command [{}] returned an error response", *id as char)
+            }
+            Self::Protocol(description) => f.write_str(description),
+            Self::Timeout(_) => f.write_str("Timed out while waiting for the device"),
+            Self::SdCard(result) => write!(f, "SD card error: {result}"),
+            Self::FatFs(_) => f.write_str("FatFs error"),
+            Self::Io(_) => f.write_str("IO error"),
+            Self::InvalidInput(description) => f.write_str(description),
+            Self::Context { description, .. } => f.write_str(description),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        match value.kind() {
+            std::io::ErrorKind::TimedOut => Self::Timeout(value),
+            _ => Self::Io(value),
+        }
+    }
+}
+
+impl From<ff::Error> for Error {
+    fn from(value: ff::Error) -> Self {
+        Self::FatFs(value)
+    }
+}
+
+impl From<SdCardResult> for Error {
+    fn from(value: SdCardResult) -> Self {
+        Self::SdCard(value)
+    }
+}
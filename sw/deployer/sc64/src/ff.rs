@@ -27,6 +27,7 @@ mod fatfs {
     pub const GET_BLOCK_SIZE: BYTE = 3;
     pub const CTRL_TRIM: BYTE = 4;
 
+    #[derive(Debug)]
     pub enum Error {
         DiskErr,
         IntErr,
@@ -81,6 +82,8 @@ mod fatfs {
         }
     }
 
+    impl std::error::Error for Error {}
+
     impl From<FRESULT> for Error {
         fn from(value: FRESULT) -> Self {
             match value {
@@ -567,6 +570,30 @@ impl File {
             error => Err(error.into()),
         }
     }
+
+    /// Returns SD card sector number of every sector in the file, file pointer is moved
+    pub fn sectors(&mut self) -> Result<Vec<u32>, Error> {
+        let fs = unsafe { &*self.fil.obj.fs };
+        let cluster_sectors = fs.csize as u64;
+        let cluster_length = cluster_sectors * SD_CARD_SECTOR_SIZE as u64;
+        let size = self.fil.obj.objsize;
+        let sector_count = size.div_ceil(SD_CARD_SECTOR_SIZE as u64);
+        let mut sectors = Vec::with_capacity(sector_count as usize);
+        let mut offset = 0;
+        while offset < size {
+            // Seeking to the end of the cluster leaves file object pointing at it without reading any data
+            let end = (offset + cluster_length).min(size);
+            match unsafe { fatfs::f_lseek(&mut self.fil, end) } {
+                fatfs::FRESULT_FR_OK => {}
+                error => return Err(error.into()),
+            }
+            let first_sector = fs.database as u64 + cluster_sectors * (self.fil.clust as u64 - 2);
+            let count = (end - offset).div_ceil(SD_CARD_SECTOR_SIZE as u64);
+            sectors.extend((0..count).map(|sector| (first_sector + sector) as u32));
+            offset = end;
+        }
+        Ok(sectors)
+    }
 }
 
 impl std::io::Read for File {
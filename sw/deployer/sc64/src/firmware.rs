@@ -21,7 +21,11 @@ impl TryFrom<u32> for ChunkId {
             3 => Self::FpgaData,
             4 => Self::BootloaderData,
             5 => Self::PrimerData,
-            _ => return Err(Error::new("Unknown chunk id inside firmware update file")),
+            _ => {
+                return Err(Error::InvalidInput(
+                    "Unknown chunk id inside firmware update file".into(),
+                ))
+            }
         })
     }
 }
@@ -89,6 +93,24 @@ impl Display for Firmware {
     }
 }
 
+impl Firmware {
+    pub fn update_info(&self) -> Option<&str> {
+        self.update_info.as_deref()
+    }
+
+    pub fn chunk_lengths(&self) -> [(&'static str, Option<usize>); 4] {
+        [
+            ("mcu", self.mcu_data.as_ref().map(|data| data.len())),
+            ("fpga", self.fpga_data.as_ref().map(|data| data.len())),
+            (
+                "bootloader",
+                self.bootloader_data.as_ref().map(|data| data.len()),
+            ),
+            ("primer", self.primer_data.as_ref().map(|data| data.len())),
+        ]
+    }
+}
+
 const SC64_FIRMWARE_UPDATE_TOKEN: &[u8; 16] = b"SC64 Update v2.0";
 
 pub fn verify(data: &[u8]) -> Result<Firmware, Error> {
@@ -98,7 +120,7 @@ pub fn verify(data: &[u8]) -> Result<Firmware, Error> {
 
     reader.read(&mut buffer)?;
     if buffer != SC64_FIRMWARE_UPDATE_TOKEN {
-        return Err(Error::new("Invalid firmware update header"));
+        return Err(Error::InvalidInput("Invalid firmware update header".into()));
     }
 
     let mut firmware = Firmware {
@@ -124,9 +146,9 @@ pub fn verify(data: &[u8]) -> Result<Firmware, Error> {
             reader.seek(SeekFrom::Current(align as i64))?;
 
             if crc32fast::hash(&data) != checksum {
-                return Err(Error::new(
-                    format!("Invalid checksum for chunk [{id}]").as_str(),
-                ));
+                return Err(Error::InvalidInput(format!(
+                    "Invalid checksum for chunk [{id}]"
+                )));
             }
 
             match id {
@@ -141,7 +163,9 @@ pub fn verify(data: &[u8]) -> Result<Firmware, Error> {
         } else if bytes == 0 {
             break;
         } else {
-            return Err(Error::new("Unexpected end of data in firmware update"));
+            return Err(Error::InvalidInput(
+                "Unexpected end of data in firmware update".into(),
+            ));
         }
     }
 
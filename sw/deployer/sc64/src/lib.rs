@@ -1,35 +1,41 @@
-mod cic;
+pub mod cic;
+pub mod debug;
+pub mod disk;
 mod error;
 pub mod ff;
 pub mod firmware;
 mod ftdi;
 mod link;
+pub mod n64;
+pub mod patch;
+pub mod rom;
+pub mod save;
 mod serial;
 pub mod server;
+mod sim;
 mod time;
 mod types;
 
 pub use self::{
-    error::Error,
-    link::list_local_devices,
+    error::{Error, ErrorKind},
+    link::{list_local_devices, BackendType, DeviceInfo},
     server::ServerEvent,
     types::{
         AuxMessage, BootMode, ButtonMode, ButtonState, CicSeed, CicStep, DataPacket, DdDiskState,
-        DdDriveType, DdMode, DebugPacket, DiagnosticData, DiskPacket, DiskPacketKind,
-        FpgaDebugData, ISViewer, MemoryTestPattern, MemoryTestPatternResult, SaveType,
-        SaveWriteback, SdCardInfo, SdCardOpPacket, SdCardResult, SdCardStatus, SpeedTestDirection,
-        Switch, TvType,
+        DdDriveType, DdMode, DdSdDisk, DebugPacket, DiagnosticData, DiagnosticDataV0,
+        DiagnosticDataV1, DiskBlock, DiskPacket, DiskPacketKind, FirmwareStatus, FpgaDebugData,
+        ISViewer, MemoryTestPattern, MemoryTestPatternResult, PiFifoFlags, PiIOAccess,
+        PiIODirection, SaveType, SaveWriteback, SdCardInfo, SdCardOpPacket, SdCardResult,
+        SdCardStatus, SpeedTestDirection, Switch, TvType, UpdateStatus,
     },
 };
 
 use self::{
-    cic::{sign_ipl3, IPL3_LENGTH, IPL3_OFFSET},
+    cic::{identify_ipl3, sign_ipl3, Ipl3Signature, IPL3_LENGTH, IPL3_OFFSET},
     link::Link,
+    rom::{find_checksum_seed, CHECKSUM_DATA_LENGTH},
     time::{convert_from_datetime, convert_to_datetime},
-    types::{
-        get_config, get_setting, Config, ConfigId, FirmwareStatus, SdCardOp, Setting, SettingId,
-        UpdateStatus,
-    },
+    types::{get_config, get_setting, Config, ConfigId, SdCardOp, Setting, SettingId},
 };
 use chrono::NaiveDateTime;
 use rand::Rng;
@@ -98,6 +104,9 @@ const SRAM_1M_LENGTH: usize = 128 * 1024;
 
 const BOOTLOADER_ADDRESS: u32 = 0x04E0_0000;
 
+const DD_SD_MAPPING_ADDRESS: u32 = 0x0200_0000; // Arbitrary offset in SDRAM memory, after the ROM area used in 64DD mode
+pub const DD_SD_MAX_DISKS: usize = 4;
+
 const SD_CARD_BUFFER_ADDRESS: u32 = 0x03FE_0000; // Arbitrary offset in SDRAM memory
 const SD_CARD_BUFFER_LENGTH: usize = 128 * 1024; // Arbitrary length in SDRAM memory
 
@@ -117,8 +126,8 @@ impl SC64 {
     fn command_identifier_get(&mut self) -> Result<[u8; 4], Error> {
         let data = self.link.execute_command(b'v', [0, 0], &[])?;
         if data.len() != 4 {
-            return Err(Error::new(
-                "Invalid data length received for identifier get command",
+            return Err(Error::Protocol(
+                "Invalid data length received for identifier get command".into(),
             ));
         }
         Ok(data[0..4].try_into().unwrap())
@@ -127,8 +136,8 @@ impl SC64 {
     fn command_version_get(&mut self) -> Result<(u16, u16, u32), Error> {
         let data = self.link.execute_command(b'V', [0, 0], &[])?;
         if data.len() != 8 {
-            return Err(Error::new(
-                "Invalid data length received for version get command",
+            return Err(Error::Protocol(
+                "Invalid data length received for version get command".into(),
             ));
         }
         let major = u16::from_be_bytes(data[0..2].try_into().unwrap());
@@ -163,8 +172,8 @@ impl SC64 {
             .link
             .execute_command(b'c', [config_id.into(), 0], &[])?;
         if data.len() != 4 {
-            return Err(Error::new(
-                "Invalid data length received for config get command",
+            return Err(Error::Protocol(
+                "Invalid data length received for config get command".into(),
             ));
         }
         let value = u32::from_be_bytes(data[0..4].try_into().unwrap());
@@ -181,8 +190,8 @@ impl SC64 {
             .link
             .execute_command(b'a', [setting_id.into(), 0], &[])?;
         if data.len() != 4 {
-            return Err(Error::new(
-                "Invalid data length received for setting get command",
+            return Err(Error::Protocol(
+                "Invalid data length received for setting get command".into(),
             ));
         }
         let value = u32::from_be_bytes(data[0..4].try_into().unwrap());
@@ -197,8 +206,8 @@ impl SC64 {
     fn command_time_get(&mut self) -> Result<NaiveDateTime, Error> {
         let data = self.link.execute_command(b't', [0, 0], &[])?;
         if data.len() != 8 {
-            return Err(Error::new(
-                "Invalid data length received for time get command",
+            return Err(Error::Protocol(
+                "Invalid data length received for time get command".into(),
             ));
         }
         Ok(convert_to_datetime(&data[0..8].try_into().unwrap())?)
@@ -215,8 +224,8 @@ impl SC64 {
             .link
             .execute_command(b'm', [address, length as u32], &[])?;
         if data.len() != length {
-            return Err(Error::new(
-                "Invalid data length received for memory read command",
+            return Err(Error::Protocol(
+                "Invalid data length received for memory read command".into(),
             ));
         }
         Ok(data)
@@ -249,8 +258,8 @@ impl SC64 {
             .link
             .execute_command_raw(b'i', op.into(), &[], false, true)?;
         if data.len() != 8 {
-            return Err(Error::new(
-                "Invalid data length received for SD card operation command",
+            return Err(Error::Protocol(
+                "Invalid data length received for SD card operation command".into(),
             ));
         }
         Ok(SdCardOpPacket {
@@ -296,6 +305,12 @@ impl SC64 {
         Ok(())
     }
 
+    fn command_dd_disk_mapping_set(&mut self, address: u32, length: usize) -> Result<(), Error> {
+        self.link
+            .execute_command(b'd', [address, length as u32], &[])?;
+        Ok(())
+    }
+
     fn command_writeback_enable(&mut self) -> Result<(), Error> {
         self.link.execute_command(b'W', [0, 0], &[])?;
         Ok(())
@@ -304,8 +319,8 @@ impl SC64 {
     fn command_flash_wait_busy(&mut self, wait: bool) -> Result<u32, Error> {
         let data = self.link.execute_command(b'p', [wait as u32, 0], &[])?;
         if data.len() != 4 {
-            return Err(Error::new(
-                "Invalid data length received for flash wait busy command",
+            return Err(Error::Protocol(
+                "Invalid data length received for flash wait busy command".into(),
             ));
         }
         let erase_block_size = u32::from_be_bytes(data[0..4].try_into().unwrap());
@@ -322,8 +337,8 @@ impl SC64 {
             .link
             .execute_command_raw(b'f', [address, 0], &[], false, true)?;
         if data.len() != 8 {
-            return Err(Error::new(
-                "Invalid data length received for firmware backup command",
+            return Err(Error::Protocol(
+                "Invalid data length received for firmware backup command".into(),
             ));
         }
         let status = u32::from_be_bytes(data[0..4].try_into().unwrap());
@@ -340,8 +355,8 @@ impl SC64 {
             self.link
                 .execute_command_raw(b'F', [address, length as u32], &[], false, true)?;
         if data.len() != 4 {
-            return Err(Error::new(
-                "Invalid data length received for firmware update command",
+            return Err(Error::Protocol(
+                "Invalid data length received for firmware update command".into(),
             ));
         }
         Ok(u32::from_be_bytes(data[0..4].try_into().unwrap()).try_into()?)
@@ -366,7 +381,7 @@ impl SC64 {
         no_shadow: bool,
     ) -> Result<(), Error> {
         if length > MAX_ROM_LENGTH {
-            return Err(Error::new("ROM length too big"));
+            return Err(Error::InvalidInput("ROM length too big".into()));
         }
 
         let mut pi_config = vec![0u8; 4];
@@ -426,30 +441,18 @@ impl SC64 {
 
     pub fn upload_ddipl<T: Read>(&mut self, reader: &mut T, length: usize) -> Result<(), Error> {
         if length > DDIPL_LENGTH {
-            return Err(Error::new("DDIPL length too big"));
+            return Err(Error::InvalidInput("DDIPL length too big".into()));
         }
 
         self.memory_write_chunked(reader, DDIPL_ADDRESS, length, None)
     }
 
     pub fn upload_save<T: Read>(&mut self, reader: &mut T, length: usize) -> Result<(), Error> {
-        let save_type = get_config!(self, SaveType)?;
-
-        let (address, save_length) = match save_type {
-            SaveType::None => {
-                return Err(Error::new("No save type is enabled"));
-            }
-            SaveType::Eeprom4k => (EEPROM_ADDRESS, EEPROM_4K_LENGTH),
-            SaveType::Eeprom16k => (EEPROM_ADDRESS, EEPROM_16K_LENGTH),
-            SaveType::Sram => (SAVE_ADDRESS, SRAM_LENGTH),
-            SaveType::Flashram => (SAVE_ADDRESS, FLASHRAM_LENGTH),
-            SaveType::SramBanked => (SAVE_ADDRESS, SRAM_BANKED_LENGTH),
-            SaveType::Sram1m => (SAVE_ADDRESS, SRAM_1M_LENGTH),
-        };
+        let (address, save_length) = self.get_save_region()?;
 
         if length != save_length {
-            return Err(Error::new(
-                "Save file size did not match currently enabled save type",
+            return Err(Error::InvalidInput(
+                "Save file size did not match currently enabled save type".into(),
             ));
         }
 
@@ -457,23 +460,23 @@ impl SC64 {
     }
 
     pub fn download_save<T: Write>(&mut self, writer: &mut T) -> Result<(), Error> {
-        let save_type = get_config!(self, SaveType)?;
-
-        let (address, save_length) = match save_type {
-            SaveType::None => {
-                return Err(Error::new("No save type is enabled"));
-            }
-            SaveType::Eeprom4k => (EEPROM_ADDRESS, EEPROM_4K_LENGTH),
-            SaveType::Eeprom16k => (EEPROM_ADDRESS, EEPROM_16K_LENGTH),
-            SaveType::Sram => (SAVE_ADDRESS, SRAM_LENGTH),
-            SaveType::Flashram => (SAVE_ADDRESS, FLASHRAM_LENGTH),
-            SaveType::SramBanked => (SAVE_ADDRESS, SRAM_BANKED_LENGTH),
-            SaveType::Sram1m => (SAVE_ADDRESS, SRAM_1M_LENGTH),
-        };
+        let (address, save_length) = self.get_save_region()?;
 
         self.memory_read_chunked(writer, address, save_length)
     }
 
+    fn get_save_region(&mut self) -> Result<(u32, usize), Error> {
+        let save_type = get_config!(self, SaveType)?;
+        let address = match save_type {
+            SaveType::Eeprom4k | SaveType::Eeprom16k => EEPROM_ADDRESS,
+            _ => SAVE_ADDRESS,
+        };
+        match save::length(save_type) {
+            Some(length) => Ok((address, length)),
+            None => Err(Error::InvalidInput("No save type is enabled".into())),
+        }
+    }
+
     pub fn dump_memory<T: Write>(
         &mut self,
         writer: &mut T,
@@ -481,32 +484,71 @@ impl SC64 {
         length: usize,
     ) -> Result<(), Error> {
         if address + length as u32 > MEMORY_LENGTH as u32 {
-            return Err(Error::new("Invalid dump address or length"));
+            return Err(Error::InvalidInput("Invalid dump address or length".into()));
         }
         self.memory_read_chunked(writer, address, length)
     }
 
-    pub fn calculate_cic_parameters(&mut self, custom_seed: Option<u8>) -> Result<(), Error> {
-        let (address, cic_seed, boot_seed) = match get_config!(self, BootMode)? {
-            BootMode::Menu => (BOOTLOADER_ADDRESS, None, None),
-            BootMode::Rom => (BOOTLOADER_ADDRESS, None, custom_seed),
-            BootMode::DdIpl => (BOOTLOADER_ADDRESS, None, custom_seed),
-            BootMode::DirectRom => (SDRAM_ADDRESS, custom_seed, None),
-            BootMode::DirectDdIpl => (DDIPL_ADDRESS, custom_seed, None),
+    pub fn calculate_cic_parameters(
+        &mut self,
+        custom_seed: Option<u8>,
+    ) -> Result<Option<Ipl3Signature>, Error> {
+        let boot_mode = get_config!(self, BootMode)?;
+        let found_seed = match (&boot_mode, custom_seed) {
+            (BootMode::Rom | BootMode::DirectRom, None) => {
+                self.find_unknown_ipl3_seed(SDRAM_ADDRESS)?
+            }
+            (BootMode::DdIpl | BootMode::DirectDdIpl, None) => {
+                self.find_unknown_ipl3_seed(DDIPL_ADDRESS)?
+            }
+            _ => None,
+        };
+        let custom_seed = custom_seed.or(found_seed);
+        let (address, cic_seed, boot_seed, game_address) = match boot_mode {
+            BootMode::Menu => (BOOTLOADER_ADDRESS, None, None, None),
+            BootMode::Rom => (BOOTLOADER_ADDRESS, None, custom_seed, Some(SDRAM_ADDRESS)),
+            BootMode::DdIpl => (BOOTLOADER_ADDRESS, None, custom_seed, Some(DDIPL_ADDRESS)),
+            BootMode::DirectRom => (SDRAM_ADDRESS, custom_seed, None, Some(SDRAM_ADDRESS)),
+            BootMode::DirectDdIpl => (DDIPL_ADDRESS, custom_seed, None, Some(DDIPL_ADDRESS)),
         };
         let ipl3 = self.command_memory_read(address + IPL3_OFFSET, IPL3_LENGTH)?;
-        let (seed, checksum) = sign_ipl3(&ipl3, cic_seed)?;
-        self.command_cic_params_set(false, seed, checksum)?;
+        let signature = sign_ipl3(&ipl3, cic_seed)?;
+        self.command_cic_params_set(false, signature.seed, signature.checksum)?;
         if let Some(seed) = boot_seed {
             self.command_config_set(Config::CicSeed(CicSeed::Seed(seed)))?;
         }
-        Ok(())
+        let signature = match game_address {
+            Some(game_address) if game_address == address => signature,
+            Some(game_address) => {
+                let ipl3 = self.command_memory_read(game_address + IPL3_OFFSET, IPL3_LENGTH)?;
+                sign_ipl3(&ipl3, custom_seed)?
+            }
+            None => return Ok(None),
+        };
+        Ok(Some(Ipl3Signature {
+            seed_found: found_seed.is_some(),
+            ..signature
+        }))
+    }
+
+    /// Unknown IPL3 gets the seed matching the checksum in the ROM header, if there is one
+    fn find_unknown_ipl3_seed(&mut self, address: u32) -> Result<Option<u8>, Error> {
+        let ipl3 = self.command_memory_read(address + IPL3_OFFSET, IPL3_LENGTH)?;
+        if identify_ipl3(&ipl3)?.is_some() {
+            return Ok(None);
+        }
+        let data = self.command_memory_read(address, CHECKSUM_DATA_LENGTH)?;
+        Ok(find_checksum_seed(&data).unwrap_or(None))
     }
 
     pub fn set_boot_mode(&mut self, boot_mode: BootMode) -> Result<(), Error> {
         self.command_config_set(Config::BootMode(boot_mode))
     }
 
+    pub fn get_save_type(&mut self) -> Result<SaveType, Error> {
+        get_config!(self, SaveType)
+    }
+
     pub fn set_save_type(&mut self, save_type: SaveType) -> Result<(), Error> {
         self.command_config_set(Config::SaveType(save_type))
     }
@@ -567,6 +609,69 @@ impl SC64 {
         Ok(())
     }
 
+    /// Empty mapping is rejected without changing anything, unknown commands additionally return the 0xFFFFFFFF value
+    pub fn check_64dd_sd_support(&mut self) -> Result<(), Error> {
+        let data =
+            self.link
+                .execute_command_raw(b'd', [DD_SD_MAPPING_ADDRESS, 0], &[], false, true)?;
+        if data == [0xFF; 4] {
+            return Err(Error::CommandFailed { id: b'd' }
+                .context("SC64 firmware doesn't support serving 64DD disks from the SD card"));
+        }
+        Ok(())
+    }
+
+    /// Loads disk mapping and passes 64DD block requests to the SD card, works without PC connected afterwards
+    pub fn configure_64dd_sd(
+        &mut self,
+        drive_type: DdDriveType,
+        disks: &[DdSdDisk],
+    ) -> Result<(), Error> {
+        self.check_64dd_sd_support()?;
+        if disks.is_empty() || disks.len() > DD_SD_MAX_DISKS {
+            return Err(Error::InvalidInput(format!(
+                "Up to {DD_SD_MAX_DISKS} disks can be served from the SD card"
+            )));
+        }
+
+        let mut mapping = vec![];
+        let mut address = DD_SD_MAPPING_ADDRESS + (disks.len() * 8) as u32;
+        for disk in disks {
+            let thb_table: Vec<u8> = disk
+                .thb_table
+                .iter()
+                .flat_map(|v| v.to_be_bytes())
+                .collect();
+            let sector_table: Vec<u8> = disk
+                .sector_table
+                .iter()
+                .flat_map(|v| v.to_be_bytes())
+                .collect();
+            let thb_table_address = address;
+            let sector_table_address = thb_table_address + thb_table.len() as u32;
+            address = sector_table_address + sector_table.len() as u32;
+            if address > DDIPL_ADDRESS {
+                return Err(Error::InvalidInput(
+                    "64DD disk mapping doesn't fit in the SDRAM".into(),
+                ));
+            }
+            self.command_memory_write(thb_table_address, &thb_table)?;
+            self.command_memory_write(sector_table_address, &sector_table)?;
+
+            mapping.extend(thb_table_address.to_be_bytes());
+            mapping.extend(sector_table_address.to_be_bytes());
+        }
+        self.command_memory_write(DD_SD_MAPPING_ADDRESS, &mapping)?;
+        self.command_dd_disk_mapping_set(DD_SD_MAPPING_ADDRESS, mapping.len())?;
+
+        self.command_config_set(Config::DdMode(DdMode::Full))?;
+        self.command_config_set(Config::DdDriveType(drive_type))?;
+        self.command_config_set(Config::DdSdEnable(Switch::On))?;
+        self.command_config_set(Config::DdDiskState(DdDiskState::Inserted))?;
+        self.command_config_set(Config::ButtonMode(ButtonMode::DdDiskSwap))?;
+        Ok(())
+    }
+
     pub fn set_64dd_disk_state(&mut self, disk_state: DdDiskState) -> Result<(), Error> {
         self.command_config_set(Config::DdDiskState(disk_state))
     }
@@ -575,12 +680,9 @@ impl SC64 {
         if let Some(offset) = offset {
             if get_config!(self, RomShadowEnable)?.into() {
                 if offset > (SAVE_ADDRESS - ISV_BUFFER_LENGTH as u32) {
-                    return Err(Error::new(
-                        format!(
-                            "ROM shadow is enabled, IS-Viewer 64 at offset 0x{offset:08X} won't work"
-                        )
-                        .as_str(),
-                    ));
+                    return Err(Error::InvalidInput(format!(
+                        "ROM shadow is enabled, IS-Viewer 64 at offset 0x{offset:08X} won't work"
+                    )));
                 }
             }
             self.command_config_set(Config::RomWriteEnable(Switch::On))?;
@@ -687,9 +789,9 @@ impl SC64 {
                     status: _,
                 } => self.command_memory_read(SD_CARD_INFO_BUFFER_ADDRESS, 32)?,
                 packet => {
-                    return Err(Error::new(
-                        format!("Couldn't get SD card info registers: {}", packet.result).as_str(),
-                    ))
+                    return Err(
+                        Error::SdCard(packet.result).context("Couldn't get SD card info registers")
+                    )
                 }
             };
         Ok(info.try_into()?)
@@ -697,8 +799,8 @@ impl SC64 {
 
     pub fn read_sd_card(&mut self, data: &mut [u8], sector: u32) -> Result<SdCardResult, Error> {
         if data.len() % SD_CARD_SECTOR_SIZE != 0 {
-            return Err(Error::new(
-                "SD card read length not aligned to the sector size",
+            return Err(Error::InvalidInput(
+                "SD card read length not aligned to the sector size".into(),
             ));
         }
 
@@ -720,8 +822,8 @@ impl SC64 {
 
     pub fn write_sd_card(&mut self, data: &[u8], sector: u32) -> Result<SdCardResult, Error> {
         if data.len() % SD_CARD_SECTOR_SIZE != 0 {
-            return Err(Error::new(
-                "SD card write length not aligned to the sector size",
+            return Err(Error::InvalidInput(
+                "SD card write length not aligned to the sector size".into(),
             ));
         }
 
@@ -741,25 +843,23 @@ impl SC64 {
     }
 
     pub fn check_device(&mut self) -> Result<(), Error> {
-        let identifier = self.command_identifier_get().map_err(|e| {
-            Error::new(format!("Couldn't get SC64 device identifier: {e}").as_str())
-        })?;
+        let identifier = self
+            .command_identifier_get()
+            .map_err(|e| e.context("Couldn't get SC64 device identifier"))?;
         if &identifier != SC64_V2_IDENTIFIER {
-            return Err(Error::new("Unknown identifier received, not a SC64 device"));
+            return Err(Error::NotSc64Device);
         }
         Ok(())
     }
 
     pub fn check_firmware_version(&mut self) -> Result<(u16, u16, u32), Error> {
-        let unsupported_version_message = format!(
-            "Unsupported SC64 firmware version, minimum supported version: {}.{}.x",
-            SUPPORTED_MAJOR_VERSION, SUPPORTED_MINOR_VERSION
-        );
-        let (major, minor, revision) = self
-            .command_version_get()
-            .map_err(|_| Error::new(unsupported_version_message.as_str()))?;
+        let unsupported_version_error = || Error::UnsupportedFirmware {
+            major: SUPPORTED_MAJOR_VERSION,
+            minor: SUPPORTED_MINOR_VERSION,
+        };
+        let (major, minor, revision) = self.command_version_get()?;
         if major != SUPPORTED_MAJOR_VERSION || minor < SUPPORTED_MINOR_VERSION {
-            return Err(Error::new(unsupported_version_message.as_str()));
+            return Err(unsupported_version_error());
         }
         Ok((major, minor, revision))
     }
@@ -780,9 +880,10 @@ impl SC64 {
         self.command_state_reset()?;
         let (status, length) = self.command_firmware_backup(FIRMWARE_ADDRESS_SDRAM)?;
         if !matches!(status, FirmwareStatus::Ok) {
-            return Err(Error::new(
-                format!("Firmware backup error: {}", status).as_str(),
-            ));
+            return Err(Error::FirmwareUpdate(format!(
+                "Firmware backup error: {}",
+                status
+            )));
         }
         self.command_memory_read(FIRMWARE_ADDRESS_SDRAM, length as usize)
     }
@@ -790,21 +891,14 @@ impl SC64 {
     pub fn update_firmware(&mut self, data: &[u8], use_flash_memory: bool) -> Result<(), Error> {
         const FLASH_UPDATE_SUPPORTED_MINOR_VERSION: u16 = 19;
         let status = if use_flash_memory {
-            let unsupported_version_error = Error::new(format!(
-                "Your firmware doesn't support updating from Flash memory, minimum required version: {}.{}.x",
-                SUPPORTED_MAJOR_VERSION, FLASH_UPDATE_SUPPORTED_MINOR_VERSION
-            ).as_str());
-            self.command_version_get()
-                .and_then(|(major, minor, revision)| {
-                    if major != SUPPORTED_MAJOR_VERSION
-                        || minor < FLASH_UPDATE_SUPPORTED_MINOR_VERSION
-                    {
-                        return Err(unsupported_version_error.clone());
-                    } else {
-                        Ok((major, minor, revision))
-                    }
-                })
-                .map_err(|_| unsupported_version_error.clone())?;
+            let unsupported_version_error = || Error::UnsupportedFirmware {
+                major: SUPPORTED_MAJOR_VERSION,
+                minor: FLASH_UPDATE_SUPPORTED_MINOR_VERSION,
+            };
+            let (major, minor, _) = self.command_version_get()?;
+            if major != SUPPORTED_MAJOR_VERSION || minor < FLASH_UPDATE_SUPPORTED_MINOR_VERSION {
+                return Err(unsupported_version_error());
+            }
             self.command_state_reset()?;
             self.flash_erase(FIRMWARE_ADDRESS_FLASH, data.len())?;
             self.command_memory_write(FIRMWARE_ADDRESS_FLASH, data)?;
@@ -816,9 +910,10 @@ impl SC64 {
             self.command_firmware_update(FIRMWARE_ADDRESS_SDRAM, data.len())?
         };
         if !matches!(status, FirmwareStatus::Ok) {
-            return Err(Error::new(
-                format!("Firmware update verify error: {}", status).as_str(),
-            ));
+            return Err(Error::FirmwareUpdate(format!(
+                "Firmware update verify error: {}",
+                status
+            )));
         }
         let timeout = Instant::now();
         let mut last_update_status = UpdateStatus::Err;
@@ -831,27 +926,21 @@ impl SC64 {
                             return Ok(());
                         }
                         UpdateStatus::Err => {
-                            return Err(Error::new(
-                                format!(
+                            return Err(Error::FirmwareUpdate(format!(
                                 "Firmware update error on step {}, device is, most likely, bricked",
                                 last_update_status
-                            )
-                                .as_str(),
-                            ))
+                            )))
                         }
                         current_update_status => last_update_status = current_update_status,
                     }
                 }
             }
             if timeout.elapsed() > FIRMWARE_UPDATE_TIMEOUT {
-                return Err(Error::new(
-                    format!(
+                return Err(Error::FirmwareUpdate(format!(
                         "Firmware update timeout, SC64 did not finish update in {} seconds, last step: {}",
                         FIRMWARE_UPDATE_TIMEOUT.as_secs(),
                         last_update_status
-                    )
-                    .as_str(),
-                ));
+                    )));
             }
             std::thread::sleep(Duration::from_millis(1));
         }
@@ -888,33 +977,21 @@ impl SC64 {
 
         match self.init_sd_card()? {
             SdCardResult::OK => {}
-            result => {
-                return Err(Error::new(
-                    format!("Init SD card failed: {result}").as_str(),
-                ))
-            }
+            result => return Err(Error::SdCard(result).context("Init SD card failed")),
         }
 
         let time = std::time::Instant::now();
 
         match self.read_sd_card(&mut data, 0)? {
             SdCardResult::OK => {}
-            result => {
-                return Err(Error::new(
-                    format!("Read SD card failed: {result}").as_str(),
-                ))
-            }
+            result => return Err(Error::SdCard(result).context("Read SD card failed")),
         }
 
         let elapsed = time.elapsed();
 
         match self.deinit_sd_card()? {
             SdCardResult::OK => {}
-            result => {
-                return Err(Error::new(
-                    format!("Deinit SD card failed: {result}").as_str(),
-                ))
-            }
+            result => return Err(Error::SdCard(result).context("Deinit SD card failed")),
         }
 
         Ok((TEST_LENGTH as f64 / MIB_DIVIDER) / elapsed.as_secs_f64())
@@ -1045,8 +1122,12 @@ impl SC64 {
 
 impl SC64 {
     pub fn open_local(port: Option<String>) -> Result<Self, Error> {
+        let port = match port {
+            Some(port) => port,
+            None => list_local_devices()?[0].port.clone(),
+        };
         let mut sc64 = SC64 {
-            link: link::new_local(&port.unwrap_or(list_local_devices()?[0].port.clone()))?,
+            link: link::new_local(&port)?,
         };
         sc64.check_device()?;
         Ok(sc64)
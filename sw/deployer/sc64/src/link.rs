@@ -1,4 +1,4 @@
-use super::{error::Error, ftdi::FtdiDevice, serial::SerialDevice};
+use super::{error::Error, ftdi::FtdiDevice, serial::SerialDevice, sim::SimDevice};
 use std::{
     collections::VecDeque,
     fmt::Display,
@@ -33,7 +33,7 @@ impl TryFrom<u32> for DataType {
             2 => Self::Response,
             3 => Self::Packet,
             0xCAFEBEEF => Self::KeepAlive,
-            _ => return Err(Error::new("Unknown data type")),
+            _ => return Err(Error::Protocol("Unknown data type".into())),
         })
     }
 }
@@ -56,6 +56,7 @@ pub enum UsbPacket {
 
 const SERIAL_PREFIX: &str = "serial://";
 const FTDI_PREFIX: &str = "ftdi://";
+const SIM_PREFIX: &str = "sim://";
 
 const RESET_TIMEOUT: Duration = Duration::from_secs(1);
 const POLL_TIMEOUT: Duration = Duration::from_millis(5);
@@ -292,6 +293,46 @@ fn new_ftdi_backend(port: &str) -> std::io::Result<FtdiBackend> {
     })
 }
 
+struct SimBackend {
+    device: SimDevice,
+}
+
+impl Backend for SimBackend {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        self.device.read(buffer)
+    }
+
+    fn write_all(&mut self, buffer: &[u8]) -> std::io::Result<()> {
+        self.device.write_all(buffer)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.device.flush()
+    }
+
+    fn discard_input(&mut self) -> std::io::Result<()> {
+        self.device.discard_input()
+    }
+
+    fn discard_output(&mut self) -> std::io::Result<()> {
+        self.device.discard_output()
+    }
+
+    fn set_dtr(&mut self, value: bool) -> std::io::Result<()> {
+        self.device.set_dtr(value)
+    }
+
+    fn read_dsr(&mut self) -> std::io::Result<bool> {
+        self.device.read_dsr()
+    }
+}
+
+fn new_sim_backend(sd_card_image: &str) -> std::io::Result<SimBackend> {
+    Ok(SimBackend {
+        device: SimDevice::new(sd_card_image, Some(POLL_TIMEOUT))?,
+    })
+}
+
 struct TcpBackend {
     stream: TcpStream,
     reader: BufReader<TcpStream>,
@@ -388,8 +429,9 @@ impl Backend for TcpBackend {
 }
 
 fn new_tcp_backend(address: &str) -> Result<TcpBackend, Error> {
-    let stream = TcpStream::connect(address).map_err(|error| {
-        Error::new(format!("Couldn't connect to [{address}]: {error}").as_str())
+    let stream = TcpStream::connect(address).map_err(|source| Error::ConnectionFailed {
+        address: address.to_string(),
+        source,
     })?;
     stream.set_read_timeout(Some(POLL_TIMEOUT))?;
     stream.set_write_timeout(Some(IO_TIMEOUT))?;
@@ -411,8 +453,12 @@ fn new_local_backend(port: &str) -> Result<Box<dyn Backend>, Error> {
         Box::new(new_ftdi_backend(
             port.strip_prefix(FTDI_PREFIX).unwrap_or_default(),
         )?)
+    } else if port.starts_with(SIM_PREFIX) {
+        Box::new(new_sim_backend(
+            port.strip_prefix(SIM_PREFIX).unwrap_or_default(),
+        )?)
     } else {
-        return Err(Error::new("Invalid port prefix provided"));
+        return Err(Error::InvalidPort(port.to_string()));
     };
     backend.reset()?;
     Ok(backend)
@@ -451,10 +497,10 @@ impl Link {
         }
         let response = self.receive_response()?;
         if id != response.id {
-            return Err(Error::new("Command response ID didn't match"));
+            return Err(Error::Protocol("Command response ID didn't match".into()));
         }
         if !ignore_error && response.error {
-            return Err(Error::new("Command response error"));
+            return Err(Error::CommandFailed { id });
         }
         Ok(response.data)
     }
@@ -466,11 +512,9 @@ impl Link {
         {
             Ok(response) => match response {
                 Some(response) => Ok(response),
-                None => Err(Error::new("No response was received")),
+                None => Err(Error::Protocol("No response was received".into())),
             },
-            Err(error) => Err(Error::new(
-                format!("Command response error: {error}").as_str(),
-            )),
+            Err(error) => Err(Error::from(error).context("Command response error")),
         }
     }
 
@@ -480,7 +524,9 @@ impl Link {
                 .backend
                 .process_incoming_data(DataType::Packet, &mut self.packets)?;
             if response.is_some() {
-                return Err(Error::new("Unexpected command response in data stream"));
+                return Err(Error::Protocol(
+                    "Unexpected command response in data stream".into(),
+                ));
             }
         }
         Ok(self.packets.pop_front())
@@ -575,7 +621,7 @@ pub fn list_local_devices() -> Result<Vec<DeviceInfo>, Error> {
     }
 
     if devices.len() == 0 {
-        return Err(Error::new("No SC64 devices found"));
+        return Err(Error::DeviceNotFound);
     }
 
     return Ok(devices);
@@ -0,0 +1,559 @@
+use super::{
+    rom::{Header, HEADER_LENGTH},
+    Error, TvType,
+};
+use include_flate::flate;
+use serde::Deserialize;
+use std::{
+    fmt::Display,
+    io::{Read, Seek},
+    path::{Path, PathBuf},
+};
+
+flate!(static MUPEN64PLUS_INI: str from "data/mupen64plus.ini");
+
+#[derive(Clone, Copy)]
+pub enum SaveType {
+    None,
+    Eeprom4k,
+    Eeprom16k,
+    Sram,
+    SramBanked,
+    Flashram,
+    Sram1m,
+}
+
+impl SaveType {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "none" => Self::None,
+            "eeprom 4kb" | "eeprom4k" => Self::Eeprom4k,
+            "eeprom 16kb" | "eeprom16k" => Self::Eeprom16k,
+            "sram" | "sram 256kbit" => Self::Sram,
+            "sram banked" | "sram 768kbit" | "sram-banked" => Self::SramBanked,
+            "sram 1mbit" | "sram1m" => Self::Sram1m,
+            "flash ram" | "flashram" => Self::Flashram,
+            _ => return None,
+        })
+    }
+}
+
+const HASH_CHUNK_LENGTH: usize = 1 * 1024 * 1024;
+
+// Retail titles keyed by media type and cartridge ID, shared by all regions and revisions
+// (region or revision specific settings can be provided with the user database)
+const GAME_CODE_SAVE_TYPES: &[(SaveType, &[&str])] = &[
+    (
+        SaveType::Eeprom4k,
+        &[
+            "NTW", "NHF", "NOS", "NTC", "NER", "NAG", "NAB", "NS3", "NTN", "NBN", "NBK", "NFH",
+            "NMU", "NBC", "NBH", "NHA", "NBM", "NBV", "NBD", "NCT", "NCH", "NCG", "NP2", "NXO",
+            "NCU", "NCX", "NDY", "NDQ", "NDR", "NN6", "NDU", "NJM", "NFW", "NF2", "NKA", "NFG",
+            "NGL", "NGV", "NGE", "NHP", "NPG", "NIJ", "NIC", "NFY", "NKI", "NLL", "NLR", "NKT",
+            "CLB", "NLB", "NMW", "NML", "NTM", "NMI", "NMG", "NMO", "NMS", "NMR", "NCR", "NEA",
+            "NPW", "NPY", "NPT", "NRA", "NWQ", "NSU", "NSN", "NK2", "NSV", "NFX", "NS6", "NNA",
+            "NRS", "NSC", "NSA", "NB6", "NSM", "NSS", "NTX", "NT6", "NTP", "NTJ", "NRC", "NTR",
+            "NTB", "NGU", "NIR", "NVL", "NVY", "NWC", "NAD", "NWU", "NYK", "NMZ",
+        ],
+    ),
+    (
+        SaveType::Eeprom16k,
+        &[
+            "NB7", "NGT", "NFU", "NCW", "NCZ", "ND6", "NDO", "ND2", "N3D", "NMX", "NGC", "NIM",
+            "NNB", "NMV", "NM8", "NEV", "NPP", "NUB", "NPD", "NRZ", "NR7", "NEP", "NYS",
+        ],
+    ),
+    (
+        SaveType::Sram,
+        &[
+            "NTE", "NVB", "CZL", "NZL", "NKG", "NMF", "NRI", "NUT", "NUM", "NOB", "CPS", "NAL",
+            "NYW", "NWL", "NW2", "NWX", "NFZ", "NA2", "NVP", "NRE", "NB5", "NSI",
+        ],
+    ),
+    (SaveType::SramBanked, &["CDZ"]),
+    (
+        SaveType::Flashram,
+        &[
+            "NCC", "NDA", "NAF", "NJF", "NKJ", "NZS", "NM6", "NCK", "NMQ", "NPN", "NPF", "NPO",
+            "CP2", "NP3", "NRH", "NSQ", "NT9", "NW4",
+        ],
+    ),
+];
+
+#[derive(Clone, Default)]
+pub struct GameSettings {
+    pub title: Option<String>,
+    pub save_type: Option<SaveType>,
+    pub tv_type: Option<TvType>,
+    pub cic_seed: Option<u8>,
+    pub direct_boot: Option<bool>,
+}
+
+impl GameSettings {
+    fn or(self, other: GameSettings) -> GameSettings {
+        GameSettings {
+            title: self.title.or(other.title),
+            save_type: self.save_type.or(other.save_type),
+            tv_type: self.tv_type.or(other.tv_type),
+            cic_seed: self.cic_seed.or(other.cic_seed),
+            direct_boot: self.direct_boot.or(other.direct_boot),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+enum DatabaseKey {
+    Md5(String),
+    GameCode(String, Option<u8>),
+    InternalName(String),
+}
+
+impl Display for DatabaseKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Md5(_) => f.write_str("MD5"),
+            Self::GameCode(code, Some(revision)) => write!(f, "game code {code} rev {revision}"),
+            Self::GameCode(code, None) => write!(f, "game code {code}"),
+            Self::InternalName(name) => write!(f, "internal name \"{name}\""),
+        }
+    }
+}
+
+struct DatabaseEntry {
+    key: DatabaseKey,
+    settings: GameSettings,
+    path: PathBuf,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlDatabase {
+    #[serde(default)]
+    game: Vec<TomlEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct TomlEntry {
+    md5: Option<String>,
+    game_code: Option<String>,
+    revision: Option<u8>,
+    internal_name: Option<String>,
+    title: Option<String>,
+    save_type: Option<String>,
+    tv: Option<String>,
+    cic_seed: Option<u8>,
+    direct: Option<bool>,
+}
+
+fn invalid_entry(path: &Path, description: &str) -> Error {
+    Error::InvalidInput(format!(
+        "{description} in database file [{}]",
+        path.display()
+    ))
+}
+
+fn parse_save_type(path: &Path, value: &str) -> Result<SaveType, Error> {
+    SaveType::from_name(value)
+        .ok_or_else(|| invalid_entry(path, &format!("Invalid save type [{value}]")))
+}
+
+fn parse_tv_type(path: &Path, value: &str) -> Result<TvType, Error> {
+    Ok(match value.to_ascii_lowercase().as_str() {
+        "pal" => TvType::PAL,
+        "ntsc" => TvType::NTSC,
+        "mpal" => TvType::MPAL,
+        _ => return Err(invalid_entry(path, &format!("Invalid TV type [{value}]"))),
+    })
+}
+
+fn parse_key(
+    path: &Path,
+    md5: Option<&str>,
+    game_code: Option<&str>,
+    revision: Option<u8>,
+    internal_name: Option<&str>,
+) -> Result<DatabaseKey, Error> {
+    Ok(match (md5, game_code, internal_name) {
+        (Some(md5), None, None) => DatabaseKey::Md5(md5.to_ascii_uppercase()),
+        (None, Some(game_code), None) if game_code.len() == 4 => {
+            DatabaseKey::GameCode(game_code.to_string(), revision)
+        }
+        (None, None, Some(name)) => DatabaseKey::InternalName(name.trim().to_string()),
+        _ => {
+            return Err(invalid_entry(
+                path,
+                "Entry must be keyed by exactly one of MD5, 4 character game code or internal name",
+            ))
+        }
+    })
+}
+
+#[derive(Default)]
+pub struct Database {
+    entries: Vec<DatabaseEntry>,
+}
+
+impl Database {
+    /// Loads overlay entries from provided files or directories (*.ini and *.toml files)
+    pub fn load(paths: &[PathBuf]) -> Result<Self, Error> {
+        let mut database = Database::default();
+        for path in paths {
+            if path.is_dir() {
+                let mut files: Vec<PathBuf> = std::fs::read_dir(path)?
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| {
+                        path.extension()
+                            .is_some_and(|extension| extension == "ini" || extension == "toml")
+                    })
+                    .collect();
+                files.sort();
+                for file in files {
+                    database.load_file(&file)?;
+                }
+            } else {
+                database.load_file(path)?;
+            }
+        }
+        Ok(database)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<(), Error> {
+        let contents = std::fs::read_to_string(path)?;
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            self.load_toml(path, &contents)
+        } else {
+            self.load_ini(path, &contents)
+        }
+    }
+
+    fn load_toml(&mut self, path: &Path, contents: &str) -> Result<(), Error> {
+        let database: TomlDatabase = toml::from_str(contents)
+            .map_err(|error| invalid_entry(path, &format!("Parse error ({})", error.message())))?;
+        for entry in database.game {
+            let key = parse_key(
+                path,
+                entry.md5.as_deref(),
+                entry.game_code.as_deref(),
+                entry.revision,
+                entry.internal_name.as_deref(),
+            )?;
+            let settings = GameSettings {
+                title: entry.title,
+                save_type: entry
+                    .save_type
+                    .map(|value| parse_save_type(path, &value))
+                    .transpose()?,
+                tv_type: entry
+                    .tv
+                    .map(|value| parse_tv_type(path, &value))
+                    .transpose()?,
+                cic_seed: entry.cic_seed,
+                direct_boot: entry.direct,
+            };
+            self.entries.push(DatabaseEntry {
+                key,
+                settings,
+                path: path.to_path_buf(),
+            });
+        }
+        Ok(())
+    }
+
+    fn load_ini(&mut self, path: &Path, contents: &str) -> Result<(), Error> {
+        let ini = ini::Ini::load_from_str(contents)
+            .map_err(|error| invalid_entry(path, &format!("Parse error ({error})")))?;
+        for (section, properties) in ini.iter() {
+            let Some(section) = section else {
+                continue;
+            };
+            let is_md5 = section.len() == 32 && section.chars().all(|c| c.is_ascii_hexdigit());
+            let revision = properties
+                .get("Revision")
+                .map(|value| {
+                    value
+                        .parse::<u8>()
+                        .map_err(|_| invalid_entry(path, &format!("Invalid revision [{value}]")))
+                })
+                .transpose()?;
+            let key = parse_key(
+                path,
+                is_md5.then_some(section),
+                properties.get("GameCode"),
+                revision,
+                properties.get("InternalName"),
+            )?;
+            let cic_seed = properties
+                .get("CicSeed")
+                .map(|value| {
+                    let parsed = match value.strip_prefix("0x").or(value.strip_prefix("0X")) {
+                        Some(hex) => u8::from_str_radix(hex, 16),
+                        None => value.parse::<u8>(),
+                    };
+                    parsed.map_err(|_| invalid_entry(path, &format!("Invalid CIC seed [{value}]")))
+                })
+                .transpose()?;
+            let direct_boot = properties
+                .get("DirectBoot")
+                .map(|value| match value.to_ascii_lowercase().as_str() {
+                    "yes" | "true" | "1" => Ok(true),
+                    "no" | "false" | "0" => Ok(false),
+                    _ => Err(invalid_entry(
+                        path,
+                        &format!("Invalid direct boot value [{value}]"),
+                    )),
+                })
+                .transpose()?;
+            let settings = GameSettings {
+                title: properties.get("GoodName").map(|title| title.to_string()),
+                save_type: properties
+                    .get("SaveType")
+                    .map(|value| parse_save_type(path, value))
+                    .transpose()?,
+                tv_type: properties
+                    .get("TvType")
+                    .map(|value| parse_tv_type(path, value))
+                    .transpose()?,
+                cic_seed,
+                direct_boot,
+            };
+            self.entries.push(DatabaseEntry {
+                key,
+                settings,
+                path: path.to_path_buf(),
+            });
+        }
+        Ok(())
+    }
+
+    fn find(&self, hash: &str, header: Option<&Header>) -> Option<&DatabaseEntry> {
+        let md5 = DatabaseKey::Md5(hash.to_string());
+        let mut keys = vec![md5];
+        if let Some(header) = header {
+            keys.push(DatabaseKey::GameCode(
+                header.game_code(),
+                Some(header.version),
+            ));
+            keys.push(DatabaseKey::GameCode(header.game_code(), None));
+        }
+        keys.iter()
+            .find_map(|key| self.entries.iter().find(|entry| &entry.key == key))
+            .or_else(|| {
+                let name = header?.name.trim();
+                self.entries.iter().find(|entry| {
+                    matches!(&entry.key, DatabaseKey::InternalName(entry_name) if !name.is_empty() && entry_name.eq_ignore_ascii_case(name))
+                })
+            })
+    }
+}
+
+pub enum DetectionSource {
+    Database { path: PathBuf, key: String },
+    HomebrewHeader,
+    Mupen64Plus,
+    GameCode(String),
+    None,
+}
+
+impl Display for DetectionSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Database { path, key } => {
+                write!(f, "user database [{}] ({key})", path.display())
+            }
+            Self::HomebrewHeader => f.write_str("homebrew ROM header"),
+            Self::Mupen64Plus => f.write_str("embedded mupen64plus.ini"),
+            Self::GameCode(code) => write!(f, "built-in game code table ({code})"),
+            Self::None => f.write_str("none"),
+        }
+    }
+}
+
+pub struct Detection {
+    pub md5: String,
+    pub settings: GameSettings,
+    pub source: DetectionSource,
+    pub homebrew: Option<HomebrewHeader>,
+}
+
+impl Detection {
+    pub fn save_type(&self) -> SaveType {
+        self.settings.save_type.unwrap_or(SaveType::None)
+    }
+}
+
+fn calculate_md5<T: Read + Seek>(reader: &mut T) -> Result<String, Error> {
+    let mut pi_config = vec![0u8; 4];
+
+    reader.rewind()?;
+    reader.read_exact(&mut pi_config)?;
+
+    let endian_swapper = match &pi_config[0..4] {
+        [0x37, 0x80, 0x40, 0x12] => |b: &mut [u8]| b.chunks_exact_mut(2).for_each(|c| c.swap(0, 1)),
+        [0x40, 0x12, 0x37, 0x80] => |b: &mut [u8]| {
+            b.chunks_exact_mut(4).for_each(|c| {
+                c.swap(0, 3);
+                c.swap(1, 2)
+            })
+        },
+        _ => |_: &mut [u8]| {},
+    };
+
+    let mut hasher = md5::Context::new();
+    let mut buffer = vec![0u8; HASH_CHUNK_LENGTH];
+
+    reader.rewind()?;
+    loop {
+        let chunk = reader.read(&mut buffer)?;
+        if chunk > 0 {
+            endian_swapper(&mut buffer[0..chunk]);
+            hasher.consume(&buffer[0..chunk]);
+        } else {
+            break;
+        }
+    }
+
+    Ok(hex::encode_upper(hasher.compute().0))
+}
+
+/// Advanced homebrew ROM header (ED64 / libdragon `n64tool --savetype --rtc --regionfree`)
+#[derive(Clone, Copy)]
+pub struct HomebrewHeader {
+    /// Unknown save type values are reported as `None` and don't stop other detection methods
+    pub save_type: Option<SaveType>,
+    pub rtc: bool,
+    pub region_free: bool,
+}
+
+impl HomebrewHeader {
+    const MAGIC: &'static [u8; 2] = b"ED";
+    const MAGIC_OFFSET: usize = 0x3C;
+    const FLAGS_OFFSET: usize = 0x3F;
+    const FLAG_RTC: u8 = 1 << 0;
+    const FLAG_REGION_FREE: u8 = 1 << 1;
+
+    /// Header data must be in big endian byte order
+    fn parse(header: &[u8]) -> Option<Self> {
+        if &header[Self::MAGIC_OFFSET..(Self::MAGIC_OFFSET + Self::MAGIC.len())] != Self::MAGIC {
+            return None;
+        }
+        let flags = header[Self::FLAGS_OFFSET];
+        Some(HomebrewHeader {
+            save_type: match flags >> 4 {
+                0 => Some(SaveType::None),
+                1 => Some(SaveType::Eeprom4k),
+                2 => Some(SaveType::Eeprom16k),
+                3 => Some(SaveType::Sram),
+                4 => Some(SaveType::SramBanked),
+                5 => Some(SaveType::Flashram),
+                6 => Some(SaveType::Sram1m),
+                _ => None,
+            },
+            rtc: (flags & Self::FLAG_RTC) != 0,
+            region_free: (flags & Self::FLAG_REGION_FREE) != 0,
+        })
+    }
+
+    fn settings(&self) -> Option<GameSettings> {
+        Some(GameSettings {
+            save_type: Some(self.save_type?),
+            ..Default::default()
+        })
+    }
+}
+
+fn game_code_settings(header: &Header) -> Option<(String, GameSettings)> {
+    let game_code = header.game_code();
+    let (save_type, code) = GAME_CODE_SAVE_TYPES
+        .iter()
+        .flat_map(|(save_type, codes)| codes.iter().map(move |code| (*save_type, *code)))
+        .filter(|(_, code)| game_code.starts_with(code))
+        .max_by_key(|(_, code)| code.len())?;
+    Some((
+        code.to_string(),
+        GameSettings {
+            save_type: Some(save_type),
+            ..Default::default()
+        },
+    ))
+}
+
+fn mupen64plus_settings(hash: &str) -> Option<GameSettings> {
+    let database = ini::Ini::load_from_str(MUPEN64PLUS_INI.as_str())
+        .expect("Error during mupen64plus.ini parse operation");
+    let mut section = database.section(Some(hash))?;
+    let title = section.get("GoodName").map(|s| s.to_string());
+    // Bad dumps and hacks reference settings of the original ROM
+    if let Some(reference) = section.get("RefMD5") {
+        section = database.section(Some(reference)).unwrap_or(section);
+    }
+    Some(GameSettings {
+        title,
+        save_type: Some(
+            section
+                .get("SaveType")
+                .and_then(SaveType::from_name)
+                .unwrap_or(SaveType::None),
+        ),
+        ..Default::default()
+    })
+}
+
+/// Detects game settings, user database entries take precedence over ED64 header and embedded database
+pub fn identify<T: Read + Seek>(reader: &mut T, database: &Database) -> Result<Detection, Error> {
+    let hash = calculate_md5(reader)?;
+
+    let mut header_data = vec![0u8; HEADER_LENGTH];
+    reader.rewind()?;
+    reader.read_exact(&mut header_data)?;
+    let header = Header::parse(&header_data).ok();
+    if let Some(header) = &header {
+        header.byte_order.to_big_endian(&mut header_data);
+    }
+
+    let mut settings = GameSettings::default();
+    let mut source = DetectionSource::None;
+
+    if let Some(entry) = database.find(&hash, header.as_ref()) {
+        settings = entry.settings.clone();
+        source = DetectionSource::Database {
+            path: entry.path.clone(),
+            key: entry.key.to_string(),
+        };
+    }
+
+    let homebrew = HomebrewHeader::parse(&header_data);
+
+    let fallback = if let Some(homebrew_settings) = homebrew.and_then(|h| h.settings()) {
+        Some((DetectionSource::HomebrewHeader, homebrew_settings))
+    } else if let Some(mupen64plus_settings) = mupen64plus_settings(&hash) {
+        Some((DetectionSource::Mupen64Plus, mupen64plus_settings))
+    } else {
+        // Hacks, translations and overdumps won't match any MD5 but usually keep the game code
+        header
+            .as_ref()
+            .and_then(game_code_settings)
+            .map(|(code, settings)| (DetectionSource::GameCode(code), settings))
+    };
+
+    if let Some((fallback_source, fallback_settings)) = fallback {
+        settings = settings.or(fallback_settings);
+        if matches!(source, DetectionSource::None) {
+            source = fallback_source;
+        }
+    }
+
+    Ok(Detection {
+        md5: hash,
+        settings,
+        source,
+        homebrew,
+    })
+}
@@ -0,0 +1,794 @@
+use super::{rom::ByteOrder, Error, MAX_ROM_LENGTH};
+use std::fmt::Display;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+const BPS_MAGIC: &[u8] = b"BPS1";
+const BPS_FOOTER_LENGTH: usize = 12;
+const APS_MAGIC: &[u8] = b"APS10";
+const APS_DESCRIPTION_LENGTH: usize = 50;
+const VCDIFF_MAGIC: &[u8] = &[0xD6, 0xC3, 0xC4, 0x00];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    Ips,
+    Bps,
+    Aps,
+    Vcdiff,
+}
+
+impl PatchFormat {
+    pub fn detect(patch: &[u8]) -> Result<Self, Error> {
+        Ok(if patch.starts_with(IPS_MAGIC) {
+            Self::Ips
+        } else if patch.starts_with(BPS_MAGIC) {
+            Self::Bps
+        } else if patch.starts_with(APS_MAGIC) {
+            Self::Aps
+        } else if patch.starts_with(VCDIFF_MAGIC) {
+            Self::Vcdiff
+        } else {
+            return Err(Error::InvalidInput("Unknown patch file format".into()));
+        })
+    }
+}
+
+impl Display for PatchFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Ips => "IPS",
+            Self::Bps => "BPS",
+            Self::Aps => "APS",
+            Self::Vcdiff => "xdelta",
+        })
+    }
+}
+
+struct PatchReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        if length > self.remaining() {
+            return Err(Error::InvalidInput("Unexpected end of patch data".into()));
+        }
+        let bytes = &self.data[self.position..(self.position + length)];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16_be(&mut self) -> Result<u16, Error> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u24_be(&mut self) -> Result<usize, Error> {
+        let bytes = self.bytes(3)?;
+        Ok(((bytes[0] as usize) << 16) | ((bytes[1] as usize) << 8) | (bytes[2] as usize))
+    }
+
+    fn u32_be(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u32_le(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn bps_number(&mut self) -> Result<usize, Error> {
+        let mut value = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let byte = self.byte()?;
+            value = (byte as usize & 0x7F)
+                .checked_mul(shift)
+                .and_then(|data| value.checked_add(data))
+                .ok_or(Error::InvalidInput("Invalid number in BPS patch".into()))?;
+            if (byte & 0x80) != 0 {
+                return Ok(value);
+            }
+            shift = shift
+                .checked_shl(7)
+                .filter(|shift| *shift != 0)
+                .ok_or(Error::InvalidInput("Invalid number in BPS patch".into()))?;
+            value = value
+                .checked_add(shift)
+                .ok_or(Error::InvalidInput("Invalid number in BPS patch".into()))?;
+        }
+    }
+
+    fn bps_offset(&mut self) -> Result<isize, Error> {
+        let data = self.bps_number()?;
+        let offset = (data >> 1) as isize;
+        Ok(if (data & 1) != 0 { -offset } else { offset })
+    }
+
+    fn vcdiff_integer(&mut self) -> Result<usize, Error> {
+        let mut value = 0usize;
+        loop {
+            let byte = self.byte()?;
+            if value > (usize::MAX >> 7) {
+                return Err(Error::InvalidInput(
+                    "Invalid integer in VCDIFF patch".into(),
+                ));
+            }
+            value = (value << 7) | (byte as usize & 0x7F);
+            if (byte & 0x80) == 0 {
+                return Ok(value);
+            }
+        }
+    }
+}
+
+fn too_large() -> Error {
+    Error::InvalidInput(format!(
+        "Patch produces ROM larger than {} MiB",
+        MAX_ROM_LENGTH / (1024 * 1024)
+    ))
+}
+
+/// Patch data isn't trusted, lengths are checked before any allocation
+fn check_length(length: usize) -> Result<usize, Error> {
+    if length > MAX_ROM_LENGTH {
+        return Err(too_large());
+    }
+    Ok(length)
+}
+
+fn end_offset(offset: usize, length: usize) -> Result<usize, Error> {
+    check_length(offset.checked_add(length).ok_or_else(too_large)?)
+}
+
+fn write_at(target: &mut Vec<u8>, offset: usize, data: &[u8]) -> Result<(), Error> {
+    let end = end_offset(offset, data.len())?;
+    if target.len() < end {
+        target.resize(end, 0);
+    }
+    target[offset..end].copy_from_slice(data);
+    Ok(())
+}
+
+fn fill_at(target: &mut Vec<u8>, offset: usize, length: usize, value: u8) -> Result<(), Error> {
+    let end = end_offset(offset, length)?;
+    if target.len() < end {
+        target.resize(end, 0);
+    }
+    target[offset..end].fill(value);
+    Ok(())
+}
+
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut reader = PatchReader::new(&patch[IPS_MAGIC.len()..]);
+    let mut target = rom.to_vec();
+
+    loop {
+        let offset = reader.bytes(3)?;
+        if offset == IPS_EOF {
+            if reader.remaining() >= 3 {
+                target.resize(reader.u24_be()?, 0);
+            }
+            return Ok(target);
+        }
+        let offset =
+            ((offset[0] as usize) << 16) | ((offset[1] as usize) << 8) | (offset[2] as usize);
+        let length = reader.u16_be()? as usize;
+        if length == 0 {
+            let length = reader.u16_be()? as usize;
+            let value = reader.byte()?;
+            fill_at(&mut target, offset, length, value)?;
+        } else {
+            write_at(&mut target, offset, reader.bytes(length)?)?;
+        }
+    }
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    if patch.len() < BPS_MAGIC.len() + BPS_FOOTER_LENGTH {
+        return Err(Error::InvalidInput("BPS patch is too short".into()));
+    }
+
+    let (data, footer) = patch.split_at(patch.len() - BPS_FOOTER_LENGTH);
+    let mut footer = PatchReader::new(footer);
+    let source_checksum = footer.u32_le()?;
+    let target_checksum = footer.u32_le()?;
+    let patch_checksum = footer.u32_le()?;
+
+    if crc32fast::hash(&patch[..(patch.len() - 4)]) != patch_checksum {
+        return Err(Error::InvalidInput("BPS patch file is corrupted".into()));
+    }
+    if crc32fast::hash(rom) != source_checksum {
+        return Err(Error::InvalidInput(
+            "BPS patch source checksum mismatch, patch was made for a different ROM".into(),
+        ));
+    }
+
+    let mut reader = PatchReader::new(&data[BPS_MAGIC.len()..]);
+    let source_length = reader.bps_number()?;
+    let target_length = reader.bps_number()?;
+    let metadata_length = reader.bps_number()?;
+    reader.bytes(metadata_length)?;
+
+    if source_length != rom.len() {
+        return Err(Error::InvalidInput(
+            "BPS patch source length mismatch, patch was made for a different ROM".into(),
+        ));
+    }
+
+    let invalid_action = || Error::InvalidInput("Invalid action in BPS patch".into());
+
+    check_length(target_length)?;
+
+    let mut target: Vec<u8> = Vec::with_capacity(target_length);
+    let mut source_offset = 0isize;
+    let mut target_offset = 0isize;
+
+    while reader.remaining() > 0 {
+        let data = reader.bps_number()?;
+        let length = (data >> 2) + 1;
+        if target_length - target.len() < length {
+            return Err(invalid_action());
+        }
+        match data & 0x03 {
+            0 => {
+                let start = target.len();
+                let bytes = rom
+                    .get(start..(start.checked_add(length).ok_or_else(invalid_action)?))
+                    .ok_or_else(invalid_action)?;
+                target.extend_from_slice(bytes);
+            }
+            1 => target.extend_from_slice(reader.bytes(length)?),
+            2 => {
+                source_offset = source_offset
+                    .checked_add(reader.bps_offset()?)
+                    .ok_or_else(invalid_action)?;
+                let start = usize::try_from(source_offset).map_err(|_| invalid_action())?;
+                let bytes = rom
+                    .get(start..(start.checked_add(length).ok_or_else(invalid_action)?))
+                    .ok_or_else(invalid_action)?;
+                target.extend_from_slice(bytes);
+                source_offset += length as isize;
+            }
+            _ => {
+                target_offset = target_offset
+                    .checked_add(reader.bps_offset()?)
+                    .ok_or_else(invalid_action)?;
+                let start = usize::try_from(target_offset).map_err(|_| invalid_action())?;
+                if start >= target.len() {
+                    return Err(invalid_action());
+                }
+                // Source and destination can overlap, copy has to be done byte by byte
+                for index in start..(start + length) {
+                    target.push(target[index]);
+                }
+                target_offset += length as isize;
+            }
+        }
+    }
+
+    if target.len() != target_length || crc32fast::hash(&target) != target_checksum {
+        return Err(Error::InvalidInput(
+            "BPS patch target checksum mismatch".into(),
+        ));
+    }
+
+    Ok(target)
+}
+
+fn apply_aps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut reader = PatchReader::new(&patch[APS_MAGIC.len()..]);
+    let patch_type = reader.byte()?;
+    let encoding = reader.byte()?;
+    reader.bytes(APS_DESCRIPTION_LENGTH)?;
+
+    if encoding != 0 {
+        return Err(Error::InvalidInput(format!(
+            "Unsupported APS patch encoding method [{encoding}]"
+        )));
+    }
+
+    let mut target = rom.to_vec();
+    let mut byte_order = ByteOrder::BigEndian;
+
+    match patch_type {
+        0 => {}
+        1 => {
+            byte_order = match reader.byte()? {
+                0 => ByteOrder::ByteSwapped,
+                _ => ByteOrder::BigEndian,
+            };
+            let cartridge_id = reader.bytes(3)?;
+            let crc = reader.bytes(8)?;
+            reader.bytes(5)?;
+
+            // Patch data refers to the ROM stored in the byte order used when creating the patch
+            byte_order.to_big_endian(&mut target);
+            if target.get(0x3C..0x3F) != Some(cartridge_id) || target.get(0x10..0x18) != Some(crc) {
+                return Err(Error::InvalidInput(
+                    "APS patch was made for a different ROM".into(),
+                ));
+            }
+        }
+        _ => {
+            return Err(Error::InvalidInput(format!(
+                "Unsupported APS patch type [{patch_type}]"
+            )))
+        }
+    }
+
+    let target_length = check_length(reader.u32_le()? as usize)?;
+    target.resize(target_length, 0);
+
+    while reader.remaining() > 0 {
+        let offset = reader.u32_le()? as usize;
+        let length = reader.byte()? as usize;
+        if length == 0 {
+            let value = reader.byte()?;
+            let length = reader.byte()? as usize;
+            fill_at(&mut target, offset, length, value)?;
+        } else {
+            write_at(&mut target, offset, reader.bytes(length)?)?;
+        }
+    }
+
+    byte_order.to_big_endian(&mut target);
+
+    Ok(target)
+}
+
+#[derive(Clone, Copy)]
+enum VcdiffInstruction {
+    Noop,
+    Add(usize),
+    Run(usize),
+    Copy(usize, usize),
+}
+
+fn vcdiff_default_code_table() -> Vec<(VcdiffInstruction, VcdiffInstruction)> {
+    use VcdiffInstruction::{Add, Copy, Noop, Run};
+
+    let mut table = vec![(Run(0), Noop)];
+    table.extend((0..=17).map(|size| (Add(size), Noop)));
+    for mode in 0..9 {
+        table.push((Copy(0, mode), Noop));
+        table.extend((4..=18).map(|size| (Copy(size, mode), Noop)));
+    }
+    for mode in 0..6 {
+        for add_size in 1..=4 {
+            table.extend((4..=6).map(|copy_size| (Add(add_size), Copy(copy_size, mode))));
+        }
+    }
+    for mode in 6..9 {
+        table.extend((1..=4).map(|add_size| (Add(add_size), Copy(4, mode))));
+    }
+    table.extend((0..9).map(|mode| (Copy(4, mode), Add(1))));
+    table
+}
+
+const VCDIFF_NEAR_CACHE_SIZE: usize = 4;
+const VCDIFF_SAME_CACHE_SIZE: usize = 3;
+
+struct VcdiffAddressCache {
+    near: [usize; VCDIFF_NEAR_CACHE_SIZE],
+    next_slot: usize,
+    same: [usize; VCDIFF_SAME_CACHE_SIZE * 256],
+}
+
+impl VcdiffAddressCache {
+    fn new() -> Self {
+        Self {
+            near: [0; VCDIFF_NEAR_CACHE_SIZE],
+            next_slot: 0,
+            same: [0; VCDIFF_SAME_CACHE_SIZE * 256],
+        }
+    }
+
+    fn decode(
+        &mut self,
+        reader: &mut PatchReader,
+        mode: usize,
+        here: usize,
+    ) -> Result<usize, Error> {
+        let invalid_address = || Error::InvalidInput("Invalid address in VCDIFF patch".into());
+        let address = match mode {
+            0 => reader.vcdiff_integer()?,
+            1 => here
+                .checked_sub(reader.vcdiff_integer()?)
+                .ok_or_else(invalid_address)?,
+            2..=5 => self.near[mode - 2]
+                .checked_add(reader.vcdiff_integer()?)
+                .ok_or_else(invalid_address)?,
+            _ => self.same[(mode - 6) * 256 + reader.byte()? as usize],
+        };
+        self.near[self.next_slot] = address;
+        self.next_slot = (self.next_slot + 1) % VCDIFF_NEAR_CACHE_SIZE;
+        self.same[address % (VCDIFF_SAME_CACHE_SIZE * 256)] = address;
+        Ok(address)
+    }
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+fn apply_vcdiff(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    const VCD_DECOMPRESS: u8 = 0x01;
+    const VCD_CODETABLE: u8 = 0x02;
+    const VCD_APPHEADER: u8 = 0x04;
+    const VCD_SOURCE: u8 = 0x01;
+    const VCD_TARGET: u8 = 0x02;
+    const VCD_ADLER32: u8 = 0x04;
+
+    let mut reader = PatchReader::new(&patch[VCDIFF_MAGIC.len()..]);
+
+    let header_indicator = reader.byte()?;
+    if (header_indicator & (VCD_DECOMPRESS | VCD_CODETABLE)) != 0 {
+        return Err(Error::InvalidInput(
+            "Unsupported xdelta patch, secondary compression and custom code tables are not supported (create patch with -S none)".into(),
+        ));
+    }
+    if (header_indicator & VCD_APPHEADER) != 0 {
+        let length = reader.vcdiff_integer()?;
+        reader.bytes(length)?;
+    }
+
+    let code_table = vcdiff_default_code_table();
+    let mut target: Vec<u8> = vec![];
+
+    while reader.remaining() > 0 {
+        let window_indicator = reader.byte()?;
+        let source = if (window_indicator & (VCD_SOURCE | VCD_TARGET)) != 0 {
+            let length = reader.vcdiff_integer()?;
+            let position = reader.vcdiff_integer()?;
+            let data = if (window_indicator & VCD_SOURCE) != 0 {
+                rom
+            } else {
+                &target
+            };
+            let invalid_segment = || {
+                Error::InvalidInput(
+                    "Invalid source segment in xdelta patch, patch was made for a different ROM"
+                        .into(),
+                )
+            };
+            let end = position.checked_add(length).ok_or_else(invalid_segment)?;
+            data.get(position..end)
+                .ok_or_else(invalid_segment)?
+                .to_vec()
+        } else {
+            vec![]
+        };
+
+        reader.vcdiff_integer()?;
+        let window_length = reader.vcdiff_integer()?;
+        end_offset(target.len(), window_length)?;
+        if reader.byte()? != 0 {
+            return Err(Error::InvalidInput(
+                "Unsupported xdelta patch, compressed sections are not supported".into(),
+            ));
+        }
+        let data_length = reader.vcdiff_integer()?;
+        let instructions_length = reader.vcdiff_integer()?;
+        let addresses_length = reader.vcdiff_integer()?;
+        let checksum = if (window_indicator & VCD_ADLER32) != 0 {
+            Some(reader.u32_be()?)
+        } else {
+            None
+        };
+
+        let mut data = PatchReader::new(reader.bytes(data_length)?);
+        let mut instructions = PatchReader::new(reader.bytes(instructions_length)?);
+        let mut addresses = PatchReader::new(reader.bytes(addresses_length)?);
+        let mut cache = VcdiffAddressCache::new();
+        let mut window: Vec<u8> = Vec::with_capacity(window_length);
+        let invalid_length = || Error::InvalidInput("Invalid window length in xdelta patch".into());
+
+        while instructions.remaining() > 0 {
+            let (first, second) = code_table[instructions.byte()? as usize];
+            for instruction in [first, second] {
+                let size = |size: usize, instructions: &mut PatchReader, window: &[u8]| {
+                    let size = if size == 0 {
+                        instructions.vcdiff_integer()?
+                    } else {
+                        size
+                    };
+                    if window_length - window.len() < size {
+                        return Err(invalid_length());
+                    }
+                    Ok(size)
+                };
+                match instruction {
+                    VcdiffInstruction::Noop => {}
+                    VcdiffInstruction::Add(length) => {
+                        let length = size(length, &mut instructions, &window)?;
+                        window.extend_from_slice(data.bytes(length)?);
+                    }
+                    VcdiffInstruction::Run(length) => {
+                        let length = size(length, &mut instructions, &window)?;
+                        let value = data.byte()?;
+                        window.resize(window.len() + length, value);
+                    }
+                    VcdiffInstruction::Copy(length, mode) => {
+                        let length = size(length, &mut instructions, &window)?;
+                        let here = source.len() + window.len();
+                        let address = cache.decode(&mut addresses, mode, here)?;
+                        if address >= here {
+                            return Err(Error::InvalidInput(
+                                "Invalid address in VCDIFF patch".into(),
+                            ));
+                        }
+                        // Copy can overlap with the data being produced, it has to be done byte by byte
+                        for position in address..(address + length) {
+                            let byte = if position < source.len() {
+                                source[position]
+                            } else {
+                                window[position - source.len()]
+                            };
+                            window.push(byte);
+                        }
+                    }
+                }
+            }
+        }
+
+        if window.len() != window_length {
+            return Err(invalid_length());
+        }
+        if checksum.is_some_and(|checksum| checksum != adler32(&window)) {
+            return Err(Error::InvalidInput(
+                "xdelta patch target checksum mismatch".into(),
+            ));
+        }
+
+        target.extend_from_slice(&window);
+    }
+
+    Ok(target)
+}
+
+/// Applies a patch to the ROM, data is expected to be in the big endian (z64) byte order
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    match PatchFormat::detect(patch)? {
+        PatchFormat::Ips => apply_ips(rom, patch),
+        PatchFormat::Bps => apply_bps(rom, patch),
+        PatchFormat::Aps => apply_aps(rom, patch),
+        PatchFormat::Vcdiff => apply_vcdiff(rom, patch),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bps_number(mut value: usize, patch: &mut Vec<u8>) {
+        loop {
+            let data = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                patch.push(0x80 | data);
+                return;
+            }
+            patch.push(data);
+            value -= 1;
+        }
+    }
+
+    fn bps_patch(rom: &[u8], target_length: usize, actions: &[u8], target: &[u8]) -> Vec<u8> {
+        let mut patch = BPS_MAGIC.to_vec();
+        bps_number(rom.len(), &mut patch);
+        bps_number(target_length, &mut patch);
+        bps_number(0, &mut patch);
+        patch.extend_from_slice(actions);
+        patch.extend_from_slice(&crc32fast::hash(rom).to_le_bytes());
+        patch.extend_from_slice(&crc32fast::hash(target).to_le_bytes());
+        patch.extend_from_slice(&crc32fast::hash(&patch).to_le_bytes());
+        patch
+    }
+
+    fn aps_patch(target_length: u32, records: &[u8]) -> Vec<u8> {
+        let mut patch = APS_MAGIC.to_vec();
+        patch.extend_from_slice(&[0, 0]);
+        patch.extend_from_slice(&[b' '; APS_DESCRIPTION_LENGTH]);
+        patch.extend_from_slice(&target_length.to_le_bytes());
+        patch.extend_from_slice(records);
+        patch
+    }
+
+    fn vcdiff_patch(
+        window_length: u8,
+        data: &[u8],
+        instructions: &[u8],
+        addresses: &[u8],
+    ) -> Vec<u8> {
+        let mut patch = VCDIFF_MAGIC.to_vec();
+        patch.push(0);
+        let window = [
+            &[
+                window_length,
+                0,
+                data.len() as u8,
+                instructions.len() as u8,
+                addresses.len() as u8,
+            ],
+            data,
+            instructions,
+            addresses,
+        ]
+        .concat();
+        patch.push(0);
+        patch.push(window.len() as u8);
+        patch.extend_from_slice(&window);
+        patch
+    }
+
+    fn ips() -> Vec<u8> {
+        [
+            IPS_MAGIC,
+            &[0x00, 0x00, 0x02, 0x00, 0x03, 0x01, 0x02, 0x03],
+            &[0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x04, 0xAA],
+            IPS_EOF,
+            &[0x00, 0x00, 0x0E],
+        ]
+        .concat()
+    }
+
+    fn bps() -> Vec<u8> {
+        let actions = [
+            // Source read, 4 bytes
+            0x80 | 0x0C,
+            // Target read, 2 bytes
+            0x80 | 0x05,
+            b'x',
+            b'y',
+            // Target copy, 4 bytes from offset 4, overlapping with the produced data
+            0x80 | 0x0F,
+            0x88,
+            // Source copy, 2 bytes from offset 6
+            0x80 | 0x06,
+            0x8C,
+        ];
+        bps_patch(b"ABCDEFGH", 12, &actions, b"ABCDxyxyxyGH")
+    }
+
+    fn aps() -> Vec<u8> {
+        aps_patch(10, &[1, 0, 0, 0, 2, 0x11, 0x22, 5, 0, 0, 0, 0, 0xEE, 3])
+    }
+
+    fn vcdiff() -> Vec<u8> {
+        // ADD 2 bytes, then COPY 6 bytes from address 0 overlapping with the produced data
+        vcdiff_patch(8, b"AB", &[3, 22], &[0])
+    }
+
+    #[test]
+    fn detect_format() {
+        assert!(PatchFormat::detect(&ips()).is_ok_and(|format| format == PatchFormat::Ips));
+        assert!(PatchFormat::detect(&bps()).is_ok_and(|format| format == PatchFormat::Bps));
+        assert!(PatchFormat::detect(&aps()).is_ok_and(|format| format == PatchFormat::Aps));
+        assert!(PatchFormat::detect(&vcdiff()).is_ok_and(|format| format == PatchFormat::Vcdiff));
+        assert!(PatchFormat::detect(b"PATC").is_err());
+    }
+
+    #[test]
+    fn ips_rle_and_truncation() {
+        assert_eq!(
+            apply_patch(&[0; 16], &ips()).unwrap(),
+            [0, 0, 1, 2, 3, 0, 0, 0, 0xAA, 0xAA, 0xAA, 0xAA, 0, 0]
+        );
+    }
+
+    #[test]
+    fn bps_actions() {
+        assert_eq!(apply_patch(b"ABCDEFGH", &bps()).unwrap(), b"ABCDxyxyxyGH");
+    }
+
+    #[test]
+    fn bps_bad_checksum() {
+        let mut patch = bps();
+        let last = patch.len() - 1;
+        patch[last] ^= 0xFF;
+        assert!(apply_patch(b"ABCDEFGH", &patch).is_err());
+        assert!(apply_patch(b"ABCDEFGX", &bps()).is_err());
+    }
+
+    #[test]
+    fn bps_oversized_target() {
+        let patch = bps_patch(b"ABCDEFGH", usize::MAX >> 8, &[], b"");
+        assert!(apply_patch(b"ABCDEFGH", &patch).is_err());
+
+        // Target copy longer than the declared target length
+        let patch = bps_patch(b"ABCDEFGH", 2, &[0x84, 0x7F, 0x80, 0x80], b"AB");
+        assert!(apply_patch(b"ABCDEFGH", &patch).is_err());
+    }
+
+    #[test]
+    fn aps_records() {
+        assert_eq!(
+            apply_patch(&[0; 8], &aps()).unwrap(),
+            [0, 0x11, 0x22, 0, 0, 0xEE, 0xEE, 0xEE, 0, 0]
+        );
+    }
+
+    #[test]
+    fn aps_oversized_target() {
+        assert!(apply_patch(&[0; 8], &aps_patch(u32::MAX, &[])).is_err());
+        assert!(apply_patch(&[0; 8], &aps_patch(8, &[0xF0, 0xFF, 0xFF, 0xFF, 1, 0])).is_err());
+    }
+
+    #[test]
+    fn vcdiff_overlapping_copy() {
+        assert_eq!(apply_patch(&[], &vcdiff()).unwrap(), b"ABABABAB");
+    }
+
+    #[test]
+    fn vcdiff_source_segment() {
+        let mut patch = VCDIFF_MAGIC.to_vec();
+        // Window copying 3 bytes of the source segment starting at ROM offset 1
+        patch.extend_from_slice(&[0, 0x01, 3, 1, 8, 3, 0, 0, 2, 1, 19, 3, 0]);
+        assert_eq!(apply_patch(b"ABCDEFGH", &patch).unwrap(), b"BCD");
+
+        patch[7] = 6;
+        assert!(apply_patch(b"ABCDEFGH", &patch).is_err());
+
+        patch.splice(7..8, [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F]);
+        assert!(apply_patch(b"ABCDEFGH", &patch).is_err());
+    }
+
+    #[test]
+    fn vcdiff_invalid_window_length() {
+        // Instructions producing more data than the declared window length
+        assert!(apply_patch(&[], &vcdiff_patch(4, b"AB", &[3, 22], &[0])).is_err());
+        assert!(apply_patch(&[], &vcdiff_patch(2, b"A", &[0, 8], &[])).is_err());
+
+        // Window length above the ROM length limit
+        let mut patch = VCDIFF_MAGIC.to_vec();
+        patch.extend_from_slice(&[0, 0, 8, 0xFF, 0xFF, 0xFF, 0x7F, 0, 0, 1, 0, 1]);
+        assert!(apply_patch(&[], &patch).is_err());
+    }
+
+    #[test]
+    fn truncated_patches() {
+        let ips = ips();
+        let aps = aps();
+        let vcdiff = vcdiff();
+        let bps = bps();
+        // Prefixes ending after the EOF marker, on the APS record boundary or after the xdelta header are complete patches
+        for (rom, patch, complete) in [
+            (
+                &[0u8; 16][..],
+                &ips,
+                &[ips.len() - 3, ips.len() - 2, ips.len() - 1][..],
+            ),
+            (b"ABCDEFGH", &bps, &[]),
+            (&[0; 8], &aps, &[61, 68]),
+            (&[], &vcdiff, &[VCDIFF_MAGIC.len() + 1]),
+        ] {
+            for length in 0..patch.len() {
+                if !complete.contains(&length) {
+                    assert!(apply_patch(rom, &patch[..length]).is_err(), "{length}");
+                }
+            }
+        }
+    }
+}
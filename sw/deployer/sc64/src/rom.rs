@@ -0,0 +1,489 @@
+use super::{
+    cic::{identify_ipl3, CicVariant, IPL3_LENGTH, IPL3_OFFSET},
+    Error, TvType,
+};
+use encoding_rs::SHIFT_JIS;
+use std::{
+    fmt::Display,
+    io::{Read, Seek, SeekFrom},
+};
+
+pub const HEADER_LENGTH: usize = 0x40;
+pub const BOOT_LENGTH: usize = IPL3_OFFSET as usize + IPL3_LENGTH;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    BigEndian,
+    ByteSwapped,
+    LittleEndian,
+}
+
+impl ByteOrder {
+    pub fn detect(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < 4 {
+            return Err(Error::InvalidInput("ROM file is too short".into()));
+        }
+        Ok(match &data[0..4] {
+            [0x80, _, _, _] => Self::BigEndian,
+            [_, 0x80, _, _] => Self::ByteSwapped,
+            [_, _, _, 0x80] => Self::LittleEndian,
+            _ => {
+                return Err(Error::InvalidInput(
+                    "Unknown ROM byte order, PI config word doesn't look valid".into(),
+                ))
+            }
+        })
+    }
+
+    pub fn to_big_endian(&self, data: &mut [u8]) {
+        match self {
+            Self::BigEndian => {}
+            Self::ByteSwapped => data.chunks_exact_mut(2).for_each(|c| c.swap(0, 1)),
+            Self::LittleEndian => data.chunks_exact_mut(4).for_each(|c| c.reverse()),
+        }
+    }
+}
+
+impl Display for ByteOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::BigEndian => "Big endian (z64)",
+            Self::ByteSwapped => "Byte swapped (v64)",
+            Self::LittleEndian => "Little endian (n64)",
+        })
+    }
+}
+
+pub struct LibultraVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub revision: u8,
+}
+
+impl Display for LibultraVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.revision.is_ascii_alphabetic() {
+            f.write_fmt(format_args!(
+                "{}.{}{}",
+                self.major, self.minor, self.revision as char
+            ))
+        } else {
+            f.write_fmt(format_args!("{}.{}", self.major, self.minor))
+        }
+    }
+}
+
+pub struct Header {
+    pub byte_order: ByteOrder,
+    pub pi_config: u32,
+    pub clock_rate: u32,
+    pub entry_point: u32,
+    pub libultra_version: LibultraVersion,
+    pub crc1: u32,
+    pub crc2: u32,
+    pub name: String,
+    pub media_type: u8,
+    pub cartridge_id: [u8; 2],
+    pub region_code: u8,
+    pub version: u8,
+}
+
+impl Header {
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < HEADER_LENGTH {
+            return Err(Error::InvalidInput("ROM file is too short".into()));
+        }
+
+        let byte_order = ByteOrder::detect(data)?;
+        let mut header = data[0..HEADER_LENGTH].to_vec();
+        byte_order.to_big_endian(&mut header);
+
+        let word =
+            |offset: usize| u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap());
+
+        let (name, _, _) = SHIFT_JIS.decode(&header[0x20..0x34]);
+
+        Ok(Header {
+            byte_order,
+            pi_config: word(0x00),
+            clock_rate: word(0x04),
+            entry_point: word(0x08),
+            libultra_version: LibultraVersion {
+                major: header[0x0E] / 10,
+                minor: header[0x0E] % 10,
+                revision: header[0x0F],
+            },
+            crc1: word(0x10),
+            crc2: word(0x14),
+            name: name.trim_end_matches(['\0', ' ']).to_string(),
+            media_type: header[0x3B],
+            cartridge_id: [header[0x3C], header[0x3D]],
+            region_code: header[0x3E],
+            version: header[0x3F],
+        })
+    }
+
+    pub fn game_code(&self) -> String {
+        [
+            self.media_type,
+            self.cartridge_id[0],
+            self.cartridge_id[1],
+            self.region_code,
+        ]
+        .iter()
+        .map(|&c| if c.is_ascii_graphic() { c as char } else { '?' })
+        .collect()
+    }
+
+    pub fn media_type_name(&self) -> &'static str {
+        match self.media_type {
+            b'N' => "Cartridge",
+            b'D' => "64DD disk",
+            b'C' => "Cartridge (expandable with 64DD)",
+            b'E' => "64DD expansion",
+            b'Z' => "Aleck64",
+            _ => "Unknown",
+        }
+    }
+
+    pub fn region_name(&self) -> &'static str {
+        match self.region_code {
+            b'7' => "Beta",
+            b'A' => "Asia (NTSC)",
+            b'B' => "Brazil",
+            b'C' => "China",
+            b'D' => "Germany",
+            b'E' => "North America",
+            b'F' => "France",
+            b'G' => "Gateway 64 (NTSC)",
+            b'H' => "Netherlands",
+            b'I' => "Italy",
+            b'J' => "Japan",
+            b'K' => "Korea",
+            b'L' => "Gateway 64 (PAL)",
+            b'N' => "Canada",
+            b'P' | b'X' | b'Y' | b'Z' => "Europe",
+            b'S' => "Spain",
+            b'U' => "Australia",
+            b'W' => "Scandinavia",
+            _ => "Unknown",
+        }
+    }
+
+    pub fn tv_type(&self) -> TvType {
+        match self.region_code {
+            b'D' | b'F' | b'H' | b'I' | b'L' | b'P' | b'S' | b'U' | b'W' | b'X' | b'Y' | b'Z' => {
+                TvType::PAL
+            }
+            b'B' => TvType::MPAL,
+            _ => TvType::NTSC,
+        }
+    }
+}
+
+pub fn read_boot<T: Read + Seek>(reader: &mut T) -> Result<(Header, Vec<u8>), Error> {
+    let mut boot = vec![0u8; BOOT_LENGTH];
+    reader.seek(SeekFrom::Start(0))?;
+    reader
+        .read_exact(&mut boot)
+        .map_err(|error| match error.kind() {
+            std::io::ErrorKind::UnexpectedEof => {
+                Error::InvalidInput("ROM file is too short".into())
+            }
+            _ => error.into(),
+        })?;
+    let header = Header::parse(&boot)?;
+    header.byte_order.to_big_endian(&mut boot);
+    let ipl3 = boot.split_off(IPL3_OFFSET as usize);
+    Ok((header, ipl3))
+}
+
+const CRC_START: usize = 0x1000;
+const CRC_LENGTH: usize = 0x100000;
+pub const CHECKSUM_DATA_LENGTH: usize = CRC_START + CRC_LENGTH;
+const CRC_6105_TABLE_OFFSET: usize = 0x750;
+const CRC_SUPPORTED_SEEDS: &str = "0x3F, 0x78, 0x91, 0x85, 0xDD";
+
+#[derive(Clone, Copy)]
+enum CrcVariant {
+    Cic6102,
+    Cic6103,
+    Cic6105,
+    Cic6106,
+    Cic8303,
+}
+
+impl TryFrom<u8> for CrcVariant {
+    type Error = Error;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0x3F => Self::Cic6102,
+            0x78 => Self::Cic6103,
+            0x91 => Self::Cic6105,
+            0x85 => Self::Cic6106,
+            0xDD => Self::Cic8303,
+            _ => {
+                return Err(Error::InvalidInput(format!(
+                    "ROM checksum calculation is not supported for CIC seed 0x{value:02X} (supported seeds: {})",
+                    CRC_SUPPORTED_SEEDS
+                )))
+            }
+        })
+    }
+}
+
+impl CrcVariant {
+    fn initial_value(&self) -> u32 {
+        match self {
+            Self::Cic6102 => 0xF8CA4DDC,
+            Self::Cic6103 => 0xA3886759,
+            Self::Cic6105 => 0xDF26F436,
+            Self::Cic6106 => 0x1FEA617A,
+            Self::Cic8303 => 0x42819C32,
+        }
+    }
+}
+
+/// Checksum state after processing the checksummed ROM area, 6105 variant mixes in words of its IPL3
+fn crc_state(rom: &[u8], initial_value: u32, variant: CrcVariant) -> [u32; 6] {
+    let word = |offset: usize| u32::from_be_bytes(rom[offset..offset + 4].try_into().unwrap());
+
+    let seed = initial_value;
+    let (mut t1, mut t2, mut t3, mut t4, mut t5, mut t6) = (seed, seed, seed, seed, seed, seed);
+
+    for offset in (CRC_START..(CRC_START + CRC_LENGTH)).step_by(4) {
+        let data = word(offset);
+
+        let (sum, overflow) = t6.overflowing_add(data);
+        if overflow {
+            t4 = t4.wrapping_add(1);
+        }
+        t6 = sum;
+        t3 ^= data;
+        let rotated = data.rotate_left(data & 0x1F);
+        t5 = t5.wrapping_add(rotated);
+        if t2 > data {
+            t2 ^= rotated;
+        } else {
+            t2 ^= t6 ^ data;
+        }
+        t1 = t1.wrapping_add(match variant {
+            CrcVariant::Cic6105 => word(CRC_6105_TABLE_OFFSET + (offset & 0xFF)) ^ data,
+            _ => t5 ^ data,
+        });
+    }
+
+    [t1, t2, t3, t4, t5, t6]
+}
+
+fn finish_crc(state: [u32; 6], variant: CrcVariant) -> (u32, u32) {
+    let [t1, t2, t3, t4, t5, t6] = state;
+    match variant {
+        CrcVariant::Cic6103 => ((t6 ^ t4).wrapping_add(t3), (t5 ^ t2).wrapping_add(t1)),
+        CrcVariant::Cic6106 => (
+            t6.wrapping_mul(t4).wrapping_add(t3),
+            t5.wrapping_mul(t2).wrapping_add(t1),
+        ),
+        _ => (t6 ^ t4 ^ t3, t5 ^ t2 ^ t1),
+    }
+}
+
+fn calculate_crc(rom: &[u8], variant: CrcVariant) -> (u32, u32) {
+    finish_crc(crc_state(rom, variant.initial_value(), variant), variant)
+}
+
+pub struct Checksum {
+    pub seed: u8,
+    pub header: (u32, u32),
+    pub calculated: (u32, u32),
+}
+
+impl Checksum {
+    pub fn is_valid(&self) -> bool {
+        self.header == self.calculated
+    }
+}
+
+/// Returns checksummed part of the ROM in big endian byte order
+fn checksum_data(data: &[u8]) -> Result<Vec<u8>, Error> {
+    if data.len() < CHECKSUM_DATA_LENGTH {
+        return Err(Error::InvalidInput(format!(
+            "ROM is too short for checksum calculation (minimum length is 0x{CHECKSUM_DATA_LENGTH:X} bytes)"
+        )));
+    }
+    let byte_order = ByteOrder::detect(data)?;
+    let mut rom = data[0..CHECKSUM_DATA_LENGTH].to_vec();
+    byte_order.to_big_endian(&mut rom);
+    Ok(rom)
+}
+
+pub fn calculate_checksum(data: &[u8], custom_seed: Option<u8>) -> Result<Checksum, Error> {
+    let rom = checksum_data(data)?;
+    let header = Header::parse(&rom)?;
+
+    let seed = match custom_seed {
+        Some(seed) => seed,
+        None => match identify_ipl3(&rom[(IPL3_OFFSET as usize)..BOOT_LENGTH])? {
+            // Shares the seed with 8303, checksum algorithm of its IPL3 is not implemented
+            Some(CicVariant::Cic5167) => {
+                return Err(Error::InvalidInput(
+                    "ROM checksum calculation is not supported for CIC 5167".into(),
+                ))
+            }
+            Some(variant) => variant.seed(),
+            None => {
+                return Err(Error::InvalidInput(
+                    "Unknown IPL3 detected, CIC seed must be provided manually".into(),
+                ))
+            }
+        },
+    };
+
+    Ok(Checksum {
+        seed,
+        header: (header.crc1, header.crc2),
+        calculated: calculate_crc(&rom, seed.try_into()?),
+    })
+}
+
+/// Searches CIC seed for which the checksum matches the one stored in the ROM header, meant for unknown IPL3
+pub fn find_checksum_seed(data: &[u8]) -> Result<Option<u8>, Error> {
+    const MAGICS: [u32; 2] = [0x5D588B65, 0x6C078965];
+
+    let rom = checksum_data(data)?;
+    let header = Header::parse(&rom)?;
+
+    for seed in 0..=u8::MAX {
+        for magic in MAGICS {
+            let initial_value = (seed as u32).wrapping_mul(magic).wrapping_add(1);
+            let state = crc_state(&rom, initial_value, CrcVariant::Cic6102);
+            for variant in [
+                CrcVariant::Cic6102,
+                CrcVariant::Cic6103,
+                CrcVariant::Cic6106,
+            ] {
+                if finish_crc(state, variant) == (header.crc1, header.crc2) {
+                    return Ok(Some(seed));
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+pub fn fix_checksum(data: &mut [u8], custom_seed: Option<u8>) -> Result<Checksum, Error> {
+    let checksum = calculate_checksum(data, custom_seed)?;
+
+    let byte_order = ByteOrder::detect(data)?;
+    let (crc1, crc2) = checksum.calculated;
+    let crc = &mut data[0x10..0x18];
+    crc[0..4].copy_from_slice(&crc1.to_be_bytes());
+    crc[4..8].copy_from_slice(&crc2.to_be_bytes());
+    // Swapping is its own inverse, this restores the original byte order
+    byte_order.to_big_endian(crc);
+
+    Ok(checksum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pseudo random ROM with zeroed CRC1/CRC2, expected checksums come from the n64crc reference implementation
+    fn rom() -> Vec<u8> {
+        let mut state = 0x12345678u32;
+        let mut rom: Vec<u8> = (0..(CHECKSUM_DATA_LENGTH / 4))
+            .flat_map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state.to_be_bytes()
+            })
+            .collect();
+        rom[0x00..0x04].copy_from_slice(&[0x80, 0x37, 0x12, 0x40]);
+        rom[0x10..0x18].fill(0);
+        rom
+    }
+
+    const CHECKSUMS: [(u8, (u32, u32)); 5] = [
+        (0x3F, (0x50F87305, 0xC42613B2)),
+        (0x78, (0x45CB6248, 0x9CA3D7E4)),
+        (0x91, (0x791D1537, 0x35E5B421)),
+        (0x85, (0x63AED81B, 0xE276EA71)),
+        (0xDD, (0x86ABAD2A, 0x907AC763)),
+    ];
+
+    fn with_header_checksum(mut rom: Vec<u8>, (crc1, crc2): (u32, u32)) -> Vec<u8> {
+        rom[0x10..0x14].copy_from_slice(&crc1.to_be_bytes());
+        rom[0x14..0x18].copy_from_slice(&crc2.to_be_bytes());
+        rom
+    }
+
+    #[test]
+    fn calculate_checksum_seeds() {
+        for (seed, crc) in CHECKSUMS {
+            let checksum = calculate_checksum(&rom(), Some(seed)).unwrap();
+            assert_eq!(checksum.calculated, crc, "seed 0x{seed:02X}");
+            assert!(!checksum.is_valid());
+
+            let checksum =
+                calculate_checksum(&with_header_checksum(rom(), crc), Some(seed)).unwrap();
+            assert_eq!(checksum.header, crc);
+            assert!(checksum.is_valid(), "seed 0x{seed:02X}");
+        }
+    }
+
+    #[test]
+    fn european_regions_are_pal() {
+        for region_code in [b'P', b'X', b'Y', b'Z'] {
+            let mut rom = rom();
+            rom[0x3E] = region_code;
+            let header = Header::parse(&rom).unwrap();
+            assert_eq!(header.region_name(), "Europe");
+            assert!(matches!(header.tv_type(), TvType::PAL));
+        }
+    }
+
+    #[test]
+    fn calculate_checksum_errors() {
+        assert!(calculate_checksum(&rom(), Some(0x00)).is_err());
+        assert!(calculate_checksum(&rom()[..(CHECKSUM_DATA_LENGTH - 1)], Some(0x3F)).is_err());
+        // Synthetic IPL3 isn't known, seed has to be provided
+        assert!(calculate_checksum(&rom(), None).is_err());
+    }
+
+    #[test]
+    fn find_checksum_seed_matches_header() {
+        let (seed, crc) = CHECKSUMS[0];
+        assert_eq!(
+            find_checksum_seed(&with_header_checksum(rom(), crc)).unwrap(),
+            Some(seed)
+        );
+    }
+
+    #[test]
+    fn fix_checksum_byte_orders() {
+        for byte_order in [
+            ByteOrder::BigEndian,
+            ByteOrder::ByteSwapped,
+            ByteOrder::LittleEndian,
+        ] {
+            for (seed, crc) in CHECKSUMS {
+                let expected = with_header_checksum(rom(), crc);
+
+                // Converting from big endian is the same operation as converting to it
+                let mut data = rom();
+                byte_order.to_big_endian(&mut data);
+                assert!(ByteOrder::detect(&data).is_ok_and(|detected| detected == byte_order));
+
+                let checksum = fix_checksum(&mut data, Some(seed)).unwrap();
+                assert_eq!(checksum.calculated, crc);
+                assert!(calculate_checksum(&data, Some(seed)).unwrap().is_valid());
+
+                byte_order.to_big_endian(&mut data);
+                assert!(data == expected, "{byte_order}, seed 0x{seed:02X}");
+            }
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use super::{
+    Error, SaveType, EEPROM_16K_LENGTH, EEPROM_4K_LENGTH, FLASHRAM_LENGTH, SRAM_1M_LENGTH,
+    SRAM_BANKED_LENGTH, SRAM_LENGTH,
+};
+use std::fmt::Display;
+
+// mupen64plus combined save layout: EEPROM, 4 controller paks, SRAM and FlashRAM
+const SRM_EEPROM_OFFSET: usize = 0;
+const SRM_EEPROM_LENGTH: usize = 0x800;
+const SRM_MEMPAK_OFFSET: usize = SRM_EEPROM_OFFSET + SRM_EEPROM_LENGTH;
+const SRM_MEMPAK_LENGTH: usize = 4 * 0x8000;
+const SRM_SRAM_OFFSET: usize = SRM_MEMPAK_OFFSET + SRM_MEMPAK_LENGTH;
+const SRM_SRAM_LENGTH: usize = 0x8000;
+const SRM_FLASHRAM_OFFSET: usize = SRM_SRAM_OFFSET + SRM_SRAM_LENGTH;
+const SRM_FLASHRAM_LENGTH: usize = 0x20000;
+pub const SRM_LENGTH: usize = SRM_FLASHRAM_OFFSET + SRM_FLASHRAM_LENGTH;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SaveFormat {
+    Raw,
+    Swapped,
+    Project64,
+    Mupen64Plus,
+    EverDrive,
+}
+
+impl Display for SaveFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Raw => "SC64 raw",
+            Self::Swapped => "Byte swapped",
+            Self::Project64 => "Project64",
+            Self::Mupen64Plus => "mupen64plus SRM",
+            Self::EverDrive => "EverDrive",
+        })
+    }
+}
+
+impl SaveFormat {
+    pub fn detect(data: &[u8]) -> Self {
+        if data.len() == SRM_LENGTH {
+            Self::Mupen64Plus
+        } else {
+            Self::Raw
+        }
+    }
+
+    /// File extension commonly used by the format for provided save type
+    pub fn extension(&self, save_type: SaveType) -> &'static str {
+        match (self, save_type) {
+            (Self::Mupen64Plus, _) => "srm",
+            (Self::Raw, _) => "sav",
+            (_, SaveType::Eeprom4k | SaveType::Eeprom16k) => "eep",
+            (_, SaveType::Flashram) => "fla",
+            (Self::EverDrive, _) => "srm",
+            _ => "sra",
+        }
+    }
+
+    // SRAM and FlashRAM contents are stored in host (little endian) 32-bit words by emulators
+    fn is_swapped(&self) -> bool {
+        matches!(self, Self::Swapped | Self::Project64 | Self::Mupen64Plus)
+    }
+}
+
+pub fn length(save_type: SaveType) -> Option<usize> {
+    match save_type {
+        SaveType::None => None,
+        SaveType::Eeprom4k => Some(EEPROM_4K_LENGTH),
+        SaveType::Eeprom16k => Some(EEPROM_16K_LENGTH),
+        SaveType::Sram => Some(SRAM_LENGTH),
+        SaveType::Flashram => Some(FLASHRAM_LENGTH),
+        SaveType::SramBanked => Some(SRAM_BANKED_LENGTH),
+        SaveType::Sram1m => Some(SRAM_1M_LENGTH),
+    }
+}
+
+/// Returns name and length of the unit the save is accessed in (EEPROM block, SRAM bank or FlashRAM page)
+pub fn layout(save_type: SaveType) -> Option<(&'static str, usize)> {
+    match save_type {
+        SaveType::None => None,
+        SaveType::Eeprom4k | SaveType::Eeprom16k => Some(("Block", 8)),
+        SaveType::Sram | SaveType::SramBanked | SaveType::Sram1m => Some(("Bank", SRAM_LENGTH)),
+        SaveType::Flashram => Some(("Page", 128)),
+    }
+}
+
+/// Guesses save type from the file length, FlashRAM and SRAM 1M can't be told apart
+pub fn detect_save_type(length: usize) -> Option<SaveType> {
+    match length {
+        EEPROM_4K_LENGTH => Some(SaveType::Eeprom4k),
+        EEPROM_16K_LENGTH => Some(SaveType::Eeprom16k),
+        SRAM_LENGTH => Some(SaveType::Sram),
+        SRAM_BANKED_LENGTH => Some(SaveType::SramBanked),
+        _ => None,
+    }
+}
+
+fn is_eeprom(save_type: SaveType) -> bool {
+    matches!(save_type, SaveType::Eeprom4k | SaveType::Eeprom16k)
+}
+
+fn swap_words(data: &mut [u8]) {
+    data.chunks_exact_mut(4).for_each(|c| c.reverse());
+}
+
+fn resize_eeprom(mut data: Vec<u8>, length: usize) -> Result<Vec<u8>, Error> {
+    if data.len() > length {
+        let unused = &data[length..];
+        if !(unused.iter().all(|b| *b == 0x00) || unused.iter().all(|b| *b == 0xFF)) {
+            return Err(Error::InvalidInput(format!(
+                "EEPROM data beyond 0x{length:X} bytes isn't empty, save can't be trimmed to the selected save type"
+            )));
+        }
+    }
+    data.resize(length, 0xFF);
+    Ok(data)
+}
+
+fn srm_region(save_type: SaveType) -> Result<(usize, usize), Error> {
+    match save_type {
+        SaveType::Eeprom4k | SaveType::Eeprom16k => Ok((SRM_EEPROM_OFFSET, SRM_EEPROM_LENGTH)),
+        SaveType::Sram => Ok((SRM_SRAM_OFFSET, SRM_SRAM_LENGTH)),
+        SaveType::Flashram => Ok((SRM_FLASHRAM_OFFSET, SRM_FLASHRAM_LENGTH)),
+        _ => Err(Error::InvalidInput(format!(
+            "Save type [{save_type}] is not supported by the mupen64plus SRM format"
+        ))),
+    }
+}
+
+fn save_length(save_type: SaveType) -> Result<usize, Error> {
+    length(save_type).ok_or(Error::InvalidInput("No save type is selected".into()))
+}
+
+/// Converts save data stored in provided format to the layout used by SC64
+pub fn import(data: &[u8], format: SaveFormat, save_type: SaveType) -> Result<Vec<u8>, Error> {
+    let length = save_length(save_type)?;
+
+    let mut save = if format == SaveFormat::Mupen64Plus {
+        if data.len() != SRM_LENGTH {
+            return Err(Error::InvalidInput(format!(
+                "mupen64plus SRM file must be 0x{SRM_LENGTH:X} bytes long"
+            )));
+        }
+        let (offset, region_length) = srm_region(save_type)?;
+        data[offset..(offset + region_length)].to_vec()
+    } else {
+        data.to_vec()
+    };
+
+    if is_eeprom(save_type) {
+        save = resize_eeprom(save, length)?;
+    } else if save.len() != length {
+        return Err(Error::InvalidInput(format!(
+            "Save file size (0x{:X} bytes) did not match selected save type [{save_type}] (0x{length:X} bytes)",
+            save.len()
+        )));
+    } else if format.is_swapped() {
+        swap_words(&mut save);
+    }
+
+    Ok(save)
+}
+
+/// Converts save data in the layout used by SC64 to provided format
+pub fn export(save: &[u8], format: SaveFormat, save_type: SaveType) -> Result<Vec<u8>, Error> {
+    let length = save_length(save_type)?;
+    if save.len() != length {
+        return Err(Error::InvalidInput(format!(
+            "Save data size (0x{:X} bytes) did not match selected save type [{save_type}] (0x{length:X} bytes)",
+            save.len()
+        )));
+    }
+
+    let mut data = save.to_vec();
+    if format.is_swapped() && !is_eeprom(save_type) {
+        swap_words(&mut data);
+    }
+
+    Ok(match format {
+        // Project64 always keeps whole 16k EEPROM in the file
+        SaveFormat::Project64 if is_eeprom(save_type) => resize_eeprom(data, EEPROM_16K_LENGTH)?,
+        SaveFormat::Mupen64Plus => {
+            let (offset, region_length) = srm_region(save_type)?;
+            let mut srm = vec![0xFF; SRM_LENGTH];
+            srm[SRM_MEMPAK_OFFSET..(SRM_SRAM_OFFSET + SRM_SRAM_LENGTH)].fill(0);
+            data.resize(region_length, 0xFF);
+            srm[offset..(offset + region_length)].copy_from_slice(&data);
+            srm
+        }
+        _ => data,
+    })
+}
@@ -0,0 +1,622 @@
+use super::{
+    time::convert_from_datetime, BOOTLOADER_ADDRESS, MEMORY_LENGTH, SDRAM_LENGTH,
+    SD_CARD_SECTOR_SIZE,
+};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+const IDENTIFIER: &[u8; 4] = b"SCv2";
+const VERSION: (u16, u16, u32) = (2, 20, 2);
+
+const FLASH_ADDRESS: u32 = 0x0400_0000;
+const FLASH_LENGTH: u32 = 16 * 1024 * 1024;
+const FLASH_ERASE_BLOCK_SIZE: u32 = 128 * 1024;
+const FLASH_USABLE_LENGTH: u32 = 14 * 1024 * 1024;
+
+const BOOTLOADER_LENGTH: u32 = 1920 * 1024;
+
+const UPDATE_TOKEN: &[u8; 16] = b"SC64 Update v2.0";
+const UPDATE_ADDRESS_END: u32 = SDRAM_LENGTH as u32 + FLASH_USABLE_LENGTH;
+
+const SD_CARD_INFO_LENGTH: u32 = 32;
+const SD_CARD_MAX_SECTORS: u32 = 0x800000;
+
+const CONFIG_ITEMS: usize = 15;
+
+const CONFIG_BOOTLOADER_SWITCH: usize = 0;
+const CONFIG_ROM_WRITE_ENABLE: usize = 1;
+const CONFIG_ROM_SHADOW_ENABLE: usize = 2;
+const CONFIG_DD_MODE: usize = 3;
+const CONFIG_ISV_ADDRESS: usize = 4;
+const CONFIG_BOOT_MODE: usize = 5;
+const CONFIG_SAVE_TYPE: usize = 6;
+const CONFIG_CIC_SEED: usize = 7;
+const CONFIG_TV_TYPE: usize = 8;
+const CONFIG_DD_SD_ENABLE: usize = 9;
+const CONFIG_DD_DRIVE_TYPE: usize = 10;
+const CONFIG_DD_DISK_STATE: usize = 11;
+const CONFIG_BUTTON_STATE: usize = 12;
+const CONFIG_BUTTON_MODE: usize = 13;
+const CONFIG_ROM_EXTENDED_ENABLE: usize = 14;
+
+const SD_OK: u32 = 0;
+const SD_ERROR_NO_CARD_IN_SLOT: u32 = 1;
+const SD_ERROR_NOT_INITIALIZED: u32 = 2;
+const SD_ERROR_INVALID_ARGUMENT: u32 = 3;
+const SD_ERROR_INVALID_ADDRESS: u32 = 4;
+const SD_ERROR_INVALID_OPERATION: u32 = 5;
+const SD_ERROR_CMD18_IO: u32 = 20;
+const SD_ERROR_CMD25_IO: u32 = 23;
+
+const UPDATE_OK: u32 = 0;
+const UPDATE_ERROR_TOKEN: u32 = 1;
+const UPDATE_ERROR_CHECKSUM: u32 = 2;
+const UPDATE_ERROR_UNKNOWN_CHUNK: u32 = 4;
+const UPDATE_ERROR_ADDRESS: u32 = 6;
+
+const CIC_STEP_POWER_OFF: u32 = 1;
+
+struct SdCard {
+    image: File,
+    sectors: u32,
+    initialized: bool,
+    byte_swap: bool,
+}
+
+struct Firmware {
+    mcu_data: Vec<u8>,
+    fpga_data: Vec<u8>,
+}
+
+pub struct SimDevice {
+    memory: Vec<u8>,
+    config: [u32; CONFIG_ITEMS],
+    led_enable: u32,
+    time: [u32; 2],
+    sd_card: Option<SdCard>,
+    firmware: Firmware,
+    dtr: bool,
+    rx_buffer: Vec<u8>,
+    tx_buffer: VecDeque<u8>,
+    poll_timeout: std::time::Duration,
+}
+
+impl SimDevice {
+    const DEFAULT_POLL_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(16);
+
+    pub fn new(
+        sd_card_image: &str,
+        poll_timeout: Option<std::time::Duration>,
+    ) -> std::io::Result<Self> {
+        let sd_card = if sd_card_image.is_empty() {
+            None
+        } else {
+            let image = File::options().read(true).write(true).open(sd_card_image)?;
+            let sectors = (image.metadata()?.len() / SD_CARD_SECTOR_SIZE as u64) as u32;
+            Some(SdCard {
+                image,
+                sectors,
+                initialized: false,
+                byte_swap: false,
+            })
+        };
+        let mut device = Self {
+            memory: vec![0u8; MEMORY_LENGTH],
+            config: [0; CONFIG_ITEMS],
+            led_enable: 1,
+            time: convert_from_datetime(chrono::Local::now().naive_local()),
+            sd_card,
+            firmware: Firmware {
+                mcu_data: b"SC64 simulated MCU firmware".to_vec(),
+                fpga_data: b"SC64 simulated FPGA bitstream".to_vec(),
+            },
+            dtr: false,
+            rx_buffer: Vec::new(),
+            tx_buffer: VecDeque::new(),
+            poll_timeout: poll_timeout.unwrap_or(Self::DEFAULT_POLL_TIMEOUT),
+        };
+        device.memory[FLASH_ADDRESS as usize..(FLASH_ADDRESS + FLASH_LENGTH) as usize].fill(0xFF);
+        device.config[CONFIG_BOOTLOADER_SWITCH] = 1;
+        device.reset_config();
+        Ok(device)
+    }
+
+    pub fn set_dtr(&mut self, value: bool) -> std::io::Result<()> {
+        if value != self.dtr {
+            self.rx_buffer.clear();
+            self.tx_buffer.clear();
+        }
+        self.dtr = value;
+        Ok(())
+    }
+
+    pub fn read_dsr(&mut self) -> std::io::Result<bool> {
+        Ok(self.dtr)
+    }
+
+    pub fn discard_input(&mut self) -> std::io::Result<()> {
+        self.tx_buffer.clear();
+        Ok(())
+    }
+
+    pub fn discard_output(&mut self) -> std::io::Result<()> {
+        self.rx_buffer.clear();
+        Ok(())
+    }
+
+    fn reset_config(&mut self) {
+        self.config[CONFIG_ROM_WRITE_ENABLE] = 0;
+        self.config[CONFIG_ROM_SHADOW_ENABLE] = 0;
+        self.config[CONFIG_DD_MODE] = 0;
+        self.config[CONFIG_ISV_ADDRESS] = 0;
+        self.config[CONFIG_BOOT_MODE] = 0;
+        self.config[CONFIG_SAVE_TYPE] = 0;
+        self.config[CONFIG_CIC_SEED] = 0xFFFF;
+        self.config[CONFIG_TV_TYPE] = 3;
+        self.config[CONFIG_DD_SD_ENABLE] = 0;
+        self.config[CONFIG_DD_DRIVE_TYPE] = 0;
+        self.config[CONFIG_DD_DISK_STATE] = 0;
+        self.config[CONFIG_BUTTON_STATE] = 0;
+        self.config[CONFIG_BUTTON_MODE] = 0;
+        self.config[CONFIG_ROM_EXTENDED_ENABLE] = 0;
+    }
+
+    fn is_invalid_range(address: u32, length: u32, exclude_bootloader: bool) -> bool {
+        let end = address as u64 + length as u64;
+        if length == 0 || end > MEMORY_LENGTH as u64 {
+            return true;
+        }
+        exclude_bootloader
+            && end > BOOTLOADER_ADDRESS as u64
+            && address < BOOTLOADER_ADDRESS + BOOTLOADER_LENGTH
+    }
+
+    fn is_flash_address(address: usize) -> bool {
+        (FLASH_ADDRESS as usize..(FLASH_ADDRESS + FLASH_LENGTH) as usize).contains(&address)
+    }
+
+    fn memory_write(&mut self, address: u32, data: &[u8]) {
+        for (offset, byte) in data.iter().enumerate() {
+            let address = address as usize + offset;
+            if Self::is_flash_address(address) {
+                self.memory[address] &= *byte;
+            } else {
+                self.memory[address] = *byte;
+            }
+        }
+    }
+
+    fn memory_read(&self, address: u32, length: u32) -> &[u8] {
+        &self.memory[address as usize..(address + length) as usize]
+    }
+
+    fn push_response(&mut self, id: u8, error: bool, data: &[u8]) {
+        self.tx_buffer
+            .extend(if error { b"ERR" } else { b"CMP" }.iter());
+        self.push_payload(id, data);
+    }
+
+    fn push_packet(&mut self, id: u8, data: &[u8]) {
+        self.tx_buffer.extend(b"PKT".iter());
+        self.push_payload(id, data);
+    }
+
+    fn push_payload(&mut self, id: u8, data: &[u8]) {
+        self.tx_buffer.push_back(id);
+        self.tx_buffer.extend((data.len() as u32).to_be_bytes());
+        self.tx_buffer.extend(data.iter());
+    }
+
+    fn command_data_length(id: u8, args: [u32; 2]) -> usize {
+        match id {
+            b'M' | b'U' => args[1] as usize,
+            b's' | b'S' => 4,
+            _ => 0,
+        }
+    }
+
+    fn process_commands(&mut self) {
+        const HEADER_LENGTH: usize = 12;
+        while self.rx_buffer.len() >= HEADER_LENGTH {
+            let id = self.rx_buffer[3];
+            let args = [
+                u32::from_be_bytes(self.rx_buffer[4..8].try_into().unwrap()),
+                u32::from_be_bytes(self.rx_buffer[8..12].try_into().unwrap()),
+            ];
+            let length = HEADER_LENGTH + Self::command_data_length(id, args);
+            if self.rx_buffer.len() < length {
+                break;
+            }
+            let command: Vec<u8> = self.rx_buffer.drain(0..length).collect();
+            if &command[0..3] != b"CMD" {
+                self.rx_buffer.clear();
+                break;
+            }
+            self.execute_command(id, args, &command[HEADER_LENGTH..]);
+        }
+    }
+
+    fn execute_command(&mut self, id: u8, args: [u32; 2], data: &[u8]) {
+        let mut error = false;
+        let mut response: Vec<u8> = vec![];
+
+        match id {
+            b'v' => response.extend(IDENTIFIER),
+            b'V' => {
+                response.extend(VERSION.0.to_be_bytes());
+                response.extend(VERSION.1.to_be_bytes());
+                response.extend(VERSION.2.to_be_bytes());
+            }
+            b'R' => {
+                self.reset_config();
+                if let Some(sd_card) = self.sd_card.as_mut() {
+                    sd_card.byte_swap = false;
+                }
+            }
+            b'c' => match self.config_query(args[0]) {
+                Some(value) => response.extend(value.to_be_bytes()),
+                None => {
+                    error = true;
+                    response.extend(args[1].to_be_bytes());
+                }
+            },
+            b'C' => error = self.config_update(args[0], args[1]),
+            b'a' => {
+                error = args[0] != 0;
+                response.extend(if error { args[1] } else { self.led_enable }.to_be_bytes());
+            }
+            b'A' => {
+                error = args[0] != 0;
+                if !error {
+                    self.led_enable = args[1];
+                }
+            }
+            b't' => {
+                response.extend(self.time[0].to_be_bytes());
+                response.extend(self.time[1].to_be_bytes());
+            }
+            b'T' => self.time = args,
+            b'm' => {
+                if Self::is_invalid_range(args[0], args[1], false) {
+                    error = true;
+                } else {
+                    response.extend(self.memory_read(args[0], args[1]));
+                }
+            }
+            b'M' => {
+                if Self::is_invalid_range(args[0], args[1], true) {
+                    error = true;
+                } else {
+                    self.memory_write(args[0], data);
+                }
+            }
+            b'U' => {
+                if args[1] > 0 {
+                    self.push_packet(b'G', &[]);
+                }
+                return;
+            }
+            b'X' => {
+                if (args[0] & 0xFF00_0000) == 0xFF00_0000 {
+                    self.push_response(id, false, &[]);
+                    self.push_packet(b'X', &args[0].to_be_bytes());
+                    return;
+                }
+            }
+            b'i' => {
+                let result = self.sd_card_operation(args[0], args[1]);
+                error = result != SD_OK;
+                response.extend(result.to_be_bytes());
+                response.extend(self.sd_card_status().to_be_bytes());
+            }
+            b's' | b'S' => {
+                let sector = u32::from_be_bytes(data[0..4].try_into().unwrap());
+                let result = self.sd_card_transfer(id == b'S', args[0], sector, args[1]);
+                error = result != SD_OK;
+                response.extend(result.to_be_bytes());
+            }
+            b'd' => error = Self::is_invalid_range(args[0], args[1], true),
+            b'B' | b'D' | b'W' => {}
+            b'p' => response.extend(FLASH_ERASE_BLOCK_SIZE.to_be_bytes()),
+            b'P' => {
+                let address = args[0];
+                error = Self::is_invalid_range(address, FLASH_ERASE_BLOCK_SIZE, true)
+                    || !address.is_multiple_of(FLASH_ERASE_BLOCK_SIZE)
+                    || !Self::is_flash_address(address as usize);
+                if !error {
+                    let start = address as usize;
+                    self.memory[start..start + FLASH_ERASE_BLOCK_SIZE as usize].fill(0xFF);
+                }
+            }
+            b'f' => {
+                self.config[CONFIG_ROM_WRITE_ENABLE] = 0;
+                let (status, length) = self.firmware_backup(args[0]);
+                error = status != UPDATE_OK;
+                response.extend(status.to_be_bytes());
+                response.extend(length.to_be_bytes());
+            }
+            b'F' => {
+                self.config[CONFIG_ROM_WRITE_ENABLE] = 0;
+                let (status, firmware) = self.firmware_prepare(args[0], args[1]);
+                error = status != UPDATE_OK;
+                self.push_response(id, error, &status.to_be_bytes());
+                if let Some((firmware, steps)) = firmware {
+                    for step in steps {
+                        self.push_packet(b'F', &step.to_be_bytes());
+                    }
+                    self.push_packet(b'F', &0x80u32.to_be_bytes());
+                    self.firmware = firmware;
+                }
+                return;
+            }
+            b'?' => {
+                response.extend(0u32.to_be_bytes());
+                response.extend((CIC_STEP_POWER_OFF << 4).to_be_bytes());
+            }
+            b'%' => {
+                const VOLTAGE_MV: u32 = 3300;
+                const TEMPERATURE: u32 = 250;
+                response.extend(0x8000_0001u32.to_be_bytes());
+                response.extend(VOLTAGE_MV.to_be_bytes());
+                response.extend(TEMPERATURE.to_be_bytes());
+                response.extend(0u32.to_be_bytes());
+            }
+            _ => {
+                error = true;
+                response.extend(0xFFFF_FFFFu32.to_be_bytes());
+            }
+        }
+
+        self.push_response(id, error, &response);
+    }
+
+    fn config_query(&self, id: u32) -> Option<u32> {
+        self.config.get(id as usize).copied()
+    }
+
+    fn config_update(&mut self, id: u32, value: u32) -> bool {
+        let index = id as usize;
+        let valid = match index {
+            CONFIG_BOOTLOADER_SWITCH
+            | CONFIG_ROM_WRITE_ENABLE
+            | CONFIG_ROM_SHADOW_ENABLE
+            | CONFIG_DD_SD_ENABLE
+            | CONFIG_ROM_EXTENDED_ENABLE => {
+                self.config[index] = (value != 0) as u32;
+                return false;
+            }
+            CONFIG_ISV_ADDRESS => true,
+            CONFIG_DD_MODE => value <= 3,
+            CONFIG_BOOT_MODE => value <= 4,
+            CONFIG_SAVE_TYPE => value <= 6,
+            CONFIG_CIC_SEED => value == 0xFFFF || value <= 0xFF,
+            CONFIG_TV_TYPE => value <= 3,
+            CONFIG_DD_DRIVE_TYPE => value <= 1,
+            CONFIG_DD_DISK_STATE => value <= 2,
+            CONFIG_BUTTON_MODE => value <= 3,
+            CONFIG_BUTTON_STATE => false,
+            _ => false,
+        };
+        if valid {
+            self.config[index] = value;
+        }
+        !valid
+    }
+
+    fn sd_card_status(&self) -> u32 {
+        match &self.sd_card {
+            Some(sd_card) => {
+                let mut status = 1 << 0;
+                if sd_card.initialized {
+                    status |= (1 << 1) | (1 << 2) | (1 << 3);
+                }
+                if sd_card.byte_swap {
+                    status |= 1 << 4;
+                }
+                status
+            }
+            None => 0,
+        }
+    }
+
+    fn sd_card_operation(&mut self, address: u32, op: u32) -> u32 {
+        let Some(sd_card) = self.sd_card.as_mut() else {
+            return match op {
+                0 | 2 => SD_OK,
+                1 | 3 | 4 | 5 => SD_ERROR_NO_CARD_IN_SLOT,
+                _ => SD_ERROR_INVALID_OPERATION,
+            };
+        };
+        match op {
+            0 => {
+                sd_card.initialized = false;
+                sd_card.byte_swap = false;
+            }
+            1 => sd_card.initialized = true,
+            2 => {}
+            3 => {
+                if Self::is_invalid_range(address, SD_CARD_INFO_LENGTH, true) {
+                    return SD_ERROR_INVALID_ADDRESS;
+                }
+                if !sd_card.initialized {
+                    return SD_ERROR_NOT_INITIALIZED;
+                }
+                let c_size = ((sd_card.sectors / 1024).max(1) - 1) as u128;
+                let csd: u128 = (1 << 126) | (c_size << 48);
+                let mut info = csd.to_be_bytes().to_vec();
+                info.resize(SD_CARD_INFO_LENGTH as usize, 0);
+                self.memory_write(address, &info);
+            }
+            4 | 5 => {
+                if !sd_card.initialized {
+                    return SD_ERROR_NOT_INITIALIZED;
+                }
+                sd_card.byte_swap = op == 4;
+            }
+            _ => return SD_ERROR_INVALID_OPERATION,
+        }
+        SD_OK
+    }
+
+    fn sd_card_transfer(&mut self, write: bool, address: u32, sector: u32, count: u32) -> u32 {
+        if count >= SD_CARD_MAX_SECTORS {
+            return SD_ERROR_INVALID_ARGUMENT;
+        }
+        let length = count * SD_CARD_SECTOR_SIZE as u32;
+        if Self::is_invalid_range(address, length, true) {
+            return SD_ERROR_INVALID_ADDRESS;
+        }
+        let Some(sd_card) = self.sd_card.as_mut() else {
+            return SD_ERROR_NO_CARD_IN_SLOT;
+        };
+        if !sd_card.initialized {
+            return SD_ERROR_NOT_INITIALIZED;
+        }
+        let io_error = if write {
+            SD_ERROR_CMD25_IO
+        } else {
+            SD_ERROR_CMD18_IO
+        };
+        if (sector as u64 + count as u64) > sd_card.sectors as u64 {
+            return io_error;
+        }
+        let byte_swap = sd_card.byte_swap;
+        let offset = sector as u64 * SD_CARD_SECTOR_SIZE as u64;
+        if sd_card.image.seek(SeekFrom::Start(offset)).is_err() {
+            return io_error;
+        }
+        if write {
+            let mut data = self.memory_read(address, length).to_vec();
+            if byte_swap {
+                data.chunks_exact_mut(2).for_each(|c| c.swap(0, 1));
+            }
+            let Some(sd_card) = self.sd_card.as_mut() else {
+                return SD_ERROR_NO_CARD_IN_SLOT;
+            };
+            if sd_card.image.write_all(&data).is_err() {
+                return io_error;
+            }
+        } else {
+            let mut data = vec![0u8; length as usize];
+            if sd_card.image.read_exact(&mut data).is_err() {
+                return io_error;
+            }
+            if byte_swap {
+                data.chunks_exact_mut(2).for_each(|c| c.swap(0, 1));
+            }
+            self.memory_write(address, &data);
+        }
+        SD_OK
+    }
+
+    fn firmware_backup(&mut self, address: u32) -> (u32, u32) {
+        let bootloader = self
+            .memory_read(BOOTLOADER_ADDRESS, BOOTLOADER_LENGTH)
+            .to_vec();
+        let mut image = UPDATE_TOKEN.to_vec();
+        for (id, data) in [
+            (2u32, &self.firmware.mcu_data),
+            (3u32, &self.firmware.fpga_data),
+            (4u32, &bootloader),
+        ] {
+            let chunk_length = 16 + data.len();
+            let aligned_chunk_length = chunk_length.next_multiple_of(16);
+            image.extend(id.to_le_bytes());
+            image.extend(((aligned_chunk_length - 8) as u32).to_le_bytes());
+            image.extend(crc32fast::hash(data).to_le_bytes());
+            image.extend((data.len() as u32).to_le_bytes());
+            image.extend(data.iter());
+            image.resize(image.len() + (aligned_chunk_length - chunk_length), 0);
+        }
+        let length = image.len() as u32;
+        if (address as u64 + length as u64) > SDRAM_LENGTH as u64 {
+            return (UPDATE_ERROR_ADDRESS, 0);
+        }
+        self.memory_write(address, &image);
+        (UPDATE_OK, length)
+    }
+
+    fn firmware_prepare(&self, address: u32, length: u32) -> (u32, Option<(Firmware, Vec<u32>)>) {
+        let end = address as u64 + length as u64;
+        if address >= UPDATE_ADDRESS_END || end > UPDATE_ADDRESS_END as u64 || length < 16 {
+            return (UPDATE_ERROR_ADDRESS, None);
+        }
+        let image = self.memory_read(address, length);
+        if &image[0..16] != UPDATE_TOKEN {
+            return (UPDATE_ERROR_TOKEN, None);
+        }
+
+        let mut firmware = Firmware {
+            mcu_data: self.firmware.mcu_data.clone(),
+            fpga_data: self.firmware.fpga_data.clone(),
+        };
+        let mut steps = vec![];
+        let mut offset = 16;
+
+        while offset + 16 <= image.len() {
+            let field = |index: usize| {
+                let start = offset + (index * 4);
+                u32::from_le_bytes(image[start..start + 4].try_into().unwrap()) as usize
+            };
+            let (id, aligned_length, checksum, data_length) =
+                (field(0), field(1), field(2), field(3));
+            let data_start = offset + 16;
+            if data_length > aligned_length || data_start + data_length > image.len() {
+                return (UPDATE_ERROR_CHECKSUM, None);
+            }
+            let data = &image[data_start..data_start + data_length];
+            if crc32fast::hash(data) as usize != checksum {
+                return (UPDATE_ERROR_CHECKSUM, None);
+            }
+            match id {
+                1 | 5 => {}
+                2 => {
+                    firmware.mcu_data = data.to_vec();
+                    steps.push(1);
+                }
+                3 => {
+                    firmware.fpga_data = data.to_vec();
+                    steps.push(2);
+                }
+                4 => steps.push(3),
+                _ => return (UPDATE_ERROR_UNKNOWN_CHUNK, None),
+            }
+            offset += 8 + aligned_length;
+        }
+
+        steps.sort();
+        (UPDATE_OK, Some((firmware, steps)))
+    }
+}
+
+impl std::io::Read for SimDevice {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        if buffer.is_empty() {
+            return Err(std::io::ErrorKind::InvalidInput.into());
+        }
+        if self.tx_buffer.is_empty() {
+            std::thread::sleep(self.poll_timeout);
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+        let length = buffer.len().min(self.tx_buffer.len());
+        for (item, byte) in buffer.iter_mut().zip(self.tx_buffer.drain(0..length)) {
+            *item = byte;
+        }
+        Ok(length)
+    }
+}
+
+impl std::io::Write for SimDevice {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        self.rx_buffer.extend_from_slice(buffer);
+        self.process_commands();
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -21,7 +21,9 @@ pub fn convert_to_datetime(data: &[u8; 8]) -> Result<NaiveDateTime, Error> {
         "%Y-%m-%dT%H:%M:%S",
     )
     .map_err(|e| {
-        Error::new(format!("Couldn't convert time from bytes to NaiveDateTime: {e}").as_str())
+        Error::Protocol(format!(
+            "Couldn't convert time from bytes to NaiveDateTime: {e}"
+        ))
     })
 }
 
@@ -183,7 +183,7 @@ impl TryFrom<u32> for DdMode {
             1 => Self::Regs,
             2 => Self::DdIpl,
             3 => Self::Full,
-            _ => return Err(Error::new("Unknown 64DD mode code")),
+            _ => return Err(Error::Protocol("Unknown 64DD mode code".into())),
         })
     }
 }
@@ -263,7 +263,7 @@ impl TryFrom<u32> for BootMode {
             2 => Self::DdIpl,
             3 => Self::DirectRom,
             4 => Self::DirectDdIpl,
-            _ => return Err(Error::new("Unknown boot mode code")),
+            _ => return Err(Error::Protocol("Unknown boot mode code".into())),
         })
     }
 }
@@ -280,6 +280,7 @@ impl From<BootMode> for u32 {
     }
 }
 
+#[derive(Clone, Copy)]
 pub enum SaveType {
     None,
     Eeprom4k,
@@ -315,7 +316,7 @@ impl TryFrom<u32> for SaveType {
             4 => Self::Flashram,
             5 => Self::SramBanked,
             6 => Self::Sram1m,
-            _ => return Err(Error::new("Unknown save type code")),
+            _ => return Err(Error::Protocol("Unknown save type code".into())),
         })
     }
 }
@@ -357,7 +358,7 @@ impl TryFrom<u32> for CicSeed {
         } else if value == 0xFFFF {
             Self::Auto
         } else {
-            return Err(Error::new("Unknown CIC seed code"));
+            return Err(Error::Protocol("Unknown CIC seed code".into()));
         })
     }
 }
@@ -371,6 +372,7 @@ impl From<CicSeed> for u32 {
     }
 }
 
+#[derive(Clone, Copy)]
 pub enum TvType {
     PAL,
     NTSC,
@@ -397,7 +399,7 @@ impl TryFrom<u32> for TvType {
             1 => Self::NTSC,
             2 => Self::MPAL,
             3 => Self::Passthrough,
-            _ => return Err(Error::new("Unknown TV type code")),
+            _ => return Err(Error::Protocol("Unknown TV type code".into())),
         })
     }
 }
@@ -433,7 +435,7 @@ impl TryFrom<u32> for DdDriveType {
         Ok(match value {
             0 => Self::Retail,
             1 => Self::Development,
-            _ => return Err(Error::new("Unknown 64DD drive type code")),
+            _ => return Err(Error::Protocol("Unknown 64DD drive type code".into())),
         })
     }
 }
@@ -447,6 +449,14 @@ impl From<DdDriveType> for u32 {
     }
 }
 
+/// Location of the 64DD disk image file on the SD card
+pub struct DdSdDisk {
+    /// Offset of the block data in the disk image file for every track/head/block index, bit 31 marks writable blocks
+    pub thb_table: Vec<u32>,
+    /// SD card sector number for every 512 byte sector of the disk image file
+    pub sector_table: Vec<u32>,
+}
+
 pub enum DdDiskState {
     Ejected,
     Inserted,
@@ -470,7 +480,7 @@ impl TryFrom<u32> for DdDiskState {
             0 => Self::Ejected,
             1 => Self::Inserted,
             2 => Self::Changed,
-            _ => return Err(Error::new("Unknown 64DD disk state code")),
+            _ => return Err(Error::Protocol("Unknown 64DD disk state code".into())),
         })
     }
 }
@@ -562,7 +572,7 @@ impl TryFrom<u32> for ButtonMode {
             1 => Self::N64Irq,
             2 => Self::UsbPacket,
             3 => Self::DdDiskSwap,
-            _ => return Err(Error::new("Unknown button mode code")),
+            _ => return Err(Error::Protocol("Unknown button mode code".into())),
         })
     }
 }
@@ -636,7 +646,7 @@ impl TryFrom<AsynchronousPacket> for DataPacket {
             b'I' => Self::IsViewer64(value.data),
             b'S' => Self::SaveWriteback(value.data.try_into()?),
             b'F' => Self::UpdateStatus(value.data.try_into()?),
-            _ => return Err(Error::new("Unknown data packet code")),
+            _ => return Err(Error::Protocol("Unknown data packet code".into())),
         })
     }
 }
@@ -664,7 +674,9 @@ impl TryFrom<Vec<u8>> for AuxMessage {
     type Error = Error;
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
         if value.len() != 4 {
-            return Err(Error::new("Invalid data length for AUX data packet"));
+            return Err(Error::Protocol(
+                "Invalid data length for AUX data packet".into(),
+            ));
         }
         Ok(match u32::from_be_bytes(value[0..4].try_into().unwrap()) {
             0xFF000000 => AuxMessage::Ping,
@@ -684,14 +696,16 @@ impl TryFrom<Vec<u8>> for DebugPacket {
     type Error = Error;
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
         if value.len() < 4 {
-            return Err(Error::new("Couldn't extract header from debug packet"));
+            return Err(Error::Protocol(
+                "Couldn't extract header from debug packet".into(),
+            ));
         }
         let header = u32::from_be_bytes(value[0..4].try_into().unwrap());
         let datatype = ((header >> 24) & 0xFF) as u8;
         let length = (header & 0x00FFFFFF) as usize;
         let data = value[4..].to_vec();
         if data.len() != length {
-            return Err(Error::new("Debug packet length did not match"));
+            return Err(Error::Protocol("Debug packet length did not match".into()));
         }
         Ok(Self { datatype, data })
     }
@@ -711,7 +725,9 @@ impl TryFrom<Vec<u8>> for DiskPacket {
     type Error = Error;
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
         if value.len() < 12 {
-            return Err(Error::new("Couldn't extract block info from disk packet"));
+            return Err(Error::Protocol(
+                "Couldn't extract block info from disk packet".into(),
+            ));
         }
         let command = u32::from_be_bytes(value[0..4].try_into().unwrap());
         let address = u32::from_be_bytes(value[4..8].try_into().unwrap());
@@ -732,7 +748,7 @@ impl TryFrom<Vec<u8>> for DiskPacket {
                 kind: DiskPacketKind::Write,
                 info: disk_block,
             },
-            _ => return Err(Error::new("Unknown disk packet command code")),
+            _ => return Err(Error::Protocol("Unknown disk packet command code".into())),
         })
     }
 }
@@ -759,6 +775,7 @@ impl From<SdCardOp> for [u32; 2] {
     }
 }
 
+#[derive(Debug)]
 pub enum SdCardResult {
     OK,
     NoCardInSlot,
@@ -835,8 +852,8 @@ impl TryFrom<Vec<u8>> for SdCardResult {
     type Error = Error;
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
         if value.len() < 4 {
-            return Err(Error::new(
-                "Incorrect data length for SD card result data packet",
+            return Err(Error::Protocol(
+                "Incorrect data length for SD card result data packet".into(),
             ));
         }
         Ok(match u32::from_be_bytes(value[0..4].try_into().unwrap()) {
@@ -871,7 +888,7 @@ impl TryFrom<Vec<u8>> for SdCardResult {
             28 => Self::Acmd41OCR,
             29 => Self::Acmd41Timeout,
             30 => Self::Locked,
-            _ => return Err(Error::new("Unknown SD card result code")),
+            _ => return Err(Error::Protocol("Unknown SD card result code".into())),
         })
     }
 }
@@ -917,8 +934,8 @@ impl TryFrom<Vec<u8>> for SdCardStatus {
     type Error = Error;
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
         if value.len() != 8 {
-            return Err(Error::new(
-                "Incorrect data length for SD card status data packet",
+            return Err(Error::Protocol(
+                "Incorrect data length for SD card status data packet".into(),
             ));
         }
         let status = u32::from_be_bytes(value[4..8].try_into().unwrap());
@@ -940,8 +957,8 @@ impl TryFrom<Vec<u8>> for SdCardInfo {
     type Error = Error;
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
         if value.len() != 32 {
-            return Err(Error::new(
-                "Incorrect data length for SD card info data packet",
+            return Err(Error::Protocol(
+                "Incorrect data length for SD card info data packet".into(),
             ));
         }
         let csd = u128::from_be_bytes(value[0..16].try_into().unwrap());
@@ -967,7 +984,7 @@ impl TryFrom<Vec<u8>> for SdCardInfo {
                     sectors: (c_size + 1) * 1024,
                 })
             }
-            _ => Err(Error::new("Unknown CSD structure value")),
+            _ => Err(Error::Protocol("Unknown CSD structure value".into())),
         }
     }
 }
@@ -995,8 +1012,8 @@ impl TryFrom<Vec<u8>> for SaveWriteback {
     type Error = Error;
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
         if value.len() < 4 {
-            return Err(Error::new(
-                "Couldn't extract save info from save writeback packet",
+            return Err(Error::Protocol(
+                "Couldn't extract save info from save writeback packet".into(),
             ));
         }
         let save: SaveType = u32::from_be_bytes(value[0..4].try_into().unwrap()).try_into()?;
@@ -1040,7 +1057,7 @@ impl TryFrom<u32> for FirmwareStatus {
             4 => Self::ErrUnknownChunk,
             5 => Self::ErrRead,
             6 => Self::ErrAddress,
-            _ => return Err(Error::new("Unknown firmware status code")),
+            _ => return Err(Error::Protocol("Unknown firmware status code".into())),
         })
     }
 }
@@ -1069,8 +1086,8 @@ impl TryFrom<Vec<u8>> for UpdateStatus {
     type Error = Error;
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
         if value.len() != 4 {
-            return Err(Error::new(
-                "Incorrect data length for update status data packet",
+            return Err(Error::Protocol(
+                "Incorrect data length for update status data packet".into(),
             ));
         }
         Ok(match u32::from_be_bytes(value[0..4].try_into().unwrap()) {
@@ -1079,7 +1096,7 @@ impl TryFrom<Vec<u8>> for UpdateStatus {
             3 => Self::Bootloader,
             0x80 => Self::Done,
             0xFF => Self::Err,
-            _ => return Err(Error::new("Unknown update status code")),
+            _ => return Err(Error::Protocol("Unknown update status code".into())),
         })
     }
 }
@@ -1244,7 +1261,9 @@ impl TryFrom<Vec<u8>> for FpgaDebugData {
     type Error = Error;
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
         if value.len() != 8 {
-            return Err(Error::new("Invalid data length for FPGA debug data"));
+            return Err(Error::Protocol(
+                "Invalid data length for FPGA debug data".into(),
+            ));
         }
         let data: &[u8; 8] = &value[0..8].try_into().unwrap();
         Ok(FpgaDebugData {
@@ -1277,7 +1296,9 @@ impl TryFrom<Vec<u8>> for DiagnosticData {
     type Error = Error;
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
         if value.len() < 4 {
-            return Err(Error::new("Invalid data length for diagnostic data"));
+            return Err(Error::Protocol(
+                "Invalid data length for diagnostic data".into(),
+            ));
         }
         let raw_version = u32::from_be_bytes(value[0..4].try_into().unwrap());
         let unversioned = raw_version & (1 << 31) == 0;
@@ -1285,7 +1306,9 @@ impl TryFrom<Vec<u8>> for DiagnosticData {
 
         if unversioned {
             if value.len() != 16 {
-                return Err(Error::new("Invalid data length for V0 diagnostic data"));
+                return Err(Error::Protocol(
+                    "Invalid data length for V0 diagnostic data".into(),
+                ));
             }
             return Ok(DiagnosticData::V0(DiagnosticDataV0 {
                 cic: u32::from_be_bytes(value[0..4].try_into().unwrap()),
@@ -1298,7 +1321,9 @@ impl TryFrom<Vec<u8>> for DiagnosticData {
         match version {
             1 => {
                 if value.len() != 16 {
-                    return Err(Error::new("Invalid data length for V1 diagnostic data"));
+                    return Err(Error::Protocol(
+                        "Invalid data length for V1 diagnostic data".into(),
+                    ));
                 }
                 let raw_voltage = u32::from_be_bytes(value[4..8].try_into().unwrap()) as f32;
                 let raw_temperature = u32::from_be_bytes(value[8..12].try_into().unwrap()) as f32;
@@ -1373,7 +1398,7 @@ macro_rules! get_config {
         if let Config::$config(value) = $sc64.command_config_get(ConfigId::$config)? {
             Ok(value)
         } else {
-            Err(Error::new("Unexpected config type"))
+            Err(Error::Protocol("Unexpected config type".into()))
         }
     }};
 }
@@ -1385,7 +1410,7 @@ macro_rules! get_setting {
         if let Setting::$setting(value) = $sc64.command_setting_get(SettingId::$setting)? {
             Ok(value)
         } else {
-            Err(Error::new("Unexpected setting type"))
+            Err(Error::Protocol("Unexpected setting type".into()))
         }
     }};
 }
@@ -0,0 +1,158 @@
+use sc64::{ff, SaveType, SC64};
+use std::{
+    fs::File,
+    io::{Cursor, Read, Write},
+    path::PathBuf,
+};
+
+const SD_CARD_IMAGE_LENGTH: u64 = 64 * 1024 * 1024;
+
+/// SD card image file removed when the test finishes
+struct SdCardImage {
+    path: PathBuf,
+}
+
+impl SdCardImage {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("sc64-sim-{name}-{}.img", std::process::id()));
+        File::create(&path)
+            .unwrap()
+            .set_len(SD_CARD_IMAGE_LENGTH)
+            .unwrap();
+        SdCardImage { path }
+    }
+
+    fn port(&self) -> String {
+        format!("sim://{}", self.path.display())
+    }
+}
+
+impl Drop for SdCardImage {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+fn pattern(length: usize, seed: u8) -> Vec<u8> {
+    (0..length)
+        .map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed))
+        .collect()
+}
+
+fn open(port: &str) -> SC64 {
+    let mut sc64 = SC64::open_local(Some(port.to_string())).unwrap();
+    sc64.check_firmware_version().unwrap();
+    sc64.reset_state().unwrap();
+    sc64
+}
+
+#[test]
+fn upload_rom() {
+    let mut sc64 = open("sim://");
+    let rom = pattern(1024 * 1024, 0x40);
+
+    sc64.upload_rom(&mut Cursor::new(&rom), rom.len(), false)
+        .unwrap();
+
+    let mut data = vec![];
+    sc64.dump_memory(&mut data, 0, rom.len()).unwrap();
+    assert!(data == rom);
+}
+
+#[test]
+fn byte_swapped_rom_is_stored_big_endian() {
+    let mut sc64 = open("sim://");
+    let mut rom = pattern(64 * 1024, 0x11);
+    rom[0..4].copy_from_slice(&[0x80, 0x37, 0x12, 0x40]);
+    let swapped: Vec<u8> = rom.chunks_exact(2).flat_map(|c| [c[1], c[0]]).collect();
+
+    sc64.upload_rom(&mut Cursor::new(&swapped), swapped.len(), false)
+        .unwrap();
+
+    let mut data = vec![];
+    sc64.dump_memory(&mut data, 0, rom.len()).unwrap();
+    assert!(data == rom);
+}
+
+#[test]
+fn save_upload_and_download() {
+    let mut sc64 = open("sim://");
+
+    for (save_type, length) in [
+        (SaveType::Eeprom4k, 512),
+        (SaveType::Eeprom16k, 2 * 1024),
+        (SaveType::Sram, 32 * 1024),
+        (SaveType::Flashram, 128 * 1024),
+    ] {
+        let save = pattern(length, length as u8);
+        sc64.set_save_type(save_type).unwrap();
+        sc64.upload_save(&mut Cursor::new(&save), save.len())
+            .unwrap();
+
+        let mut data = vec![];
+        sc64.download_save(&mut data).unwrap();
+        assert!(data == save);
+    }
+
+    sc64.set_save_type(SaveType::Sram).unwrap();
+    assert!(sc64
+        .upload_save(&mut Cursor::new(vec![0u8; 512]), 512)
+        .is_err());
+}
+
+#[test]
+fn firmware_backup_and_info() {
+    let mut sc64 = open("sim://");
+
+    let backup = sc64.backup_firmware().unwrap();
+    let firmware = sc64::firmware::verify(&backup).unwrap();
+    assert!(firmware
+        .chunk_lengths()
+        .iter()
+        .any(|(_, length)| length.is_some()));
+
+    sc64.update_firmware(&backup, false).unwrap();
+
+    let mut corrupted = backup.clone();
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 0xFF;
+    assert!(sc64::firmware::verify(&corrupted).is_err());
+}
+
+#[test]
+fn sd_card_files() {
+    let image = SdCardImage::new("sd");
+    let mut sc64 = open(&image.port());
+    sc64.init_sd_card().unwrap();
+    sc64.reset_state().unwrap();
+
+    let mut ff = ff::FatFs::new(sc64).unwrap();
+    ff.mkfs().unwrap();
+    ff.mkdir("/saves").unwrap();
+
+    let contents = pattern(100 * 1024, 0x5A);
+    ff.create("/saves/game.sav")
+        .unwrap()
+        .write_all(&contents)
+        .unwrap();
+
+    let entries = ff.list("/").unwrap();
+    assert!(entries
+        .iter()
+        .any(|entry| entry.name == "saves" && matches!(entry.info, ff::EntryInfo::Directory)));
+
+    let entries = ff.list("/saves").unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].name, "game.sav");
+    assert!(matches!(
+        entries[0].info,
+        ff::EntryInfo::File { size } if size == contents.len() as u64
+    ));
+
+    let mut data = vec![];
+    ff.open("/saves/game.sav")
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+    assert!(data == contents);
+}
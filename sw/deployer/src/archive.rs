@@ -0,0 +1,200 @@
+use flate2::read::GzDecoder;
+use sevenz_rust::{Password, SevenZReader};
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{Read, Seek},
+    path::Path,
+};
+use zip::ZipArchive;
+
+const ZIP_MAGIC: &[u8] = &[0x50, 0x4B, 0x03, 0x04];
+const SEVEN_ZIP_MAGIC: &[u8] = &[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C];
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Rom,
+    Save,
+    Disk,
+}
+
+impl FileKind {
+    fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Self::Rom => &["z64", "n64", "v64", "rom", "bin"],
+            Self::Save => &["sav", "eep", "sra", "fla", "srm"],
+            Self::Disk => &["ndd", "d64"],
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        Path::new(name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+            .is_some_and(|extension| self.extensions().contains(&extension.as_str()))
+    }
+}
+
+impl Display for FileKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Rom => "ROM",
+            Self::Save => "save",
+            Self::Disk => "64DD disk",
+        })
+    }
+}
+
+enum ArchiveFormat {
+    Zip,
+    SevenZip,
+    Gzip,
+}
+
+fn detect_format(file: &mut File) -> Result<Option<ArchiveFormat>, sc64::Error> {
+    let mut magic = vec![];
+    file.take(SEVEN_ZIP_MAGIC.len() as u64)
+        .read_to_end(&mut magic)?;
+    file.rewind()?;
+    Ok(if magic.starts_with(ZIP_MAGIC) {
+        Some(ArchiveFormat::Zip)
+    } else if magic.starts_with(SEVEN_ZIP_MAGIC) {
+        Some(ArchiveFormat::SevenZip)
+    } else if magic.starts_with(GZIP_MAGIC) {
+        Some(ArchiveFormat::Gzip)
+    } else {
+        None
+    })
+}
+
+fn archive_error<E: Display>(archive_name: &str, error: E) -> sc64::Error {
+    sc64::Error::InvalidInput(format!("Couldn't read archive [{archive_name}]: {error}"))
+}
+
+fn select_entry(
+    archive_name: &str,
+    names: &[String],
+    entry: Option<&str>,
+    kind: FileKind,
+) -> Result<String, sc64::Error> {
+    if let Some(entry) = entry {
+        return names
+            .iter()
+            .find(|name| {
+                name.as_str() == entry
+                    || Path::new(name)
+                        .file_name()
+                        .map(|name| name.to_string_lossy())
+                        == Some(entry.into())
+            })
+            .cloned()
+            .ok_or(sc64::Error::InvalidInput(format!(
+                "Entry [{entry}] not found in archive [{archive_name}]"
+            )));
+    }
+
+    let candidates: Vec<&String> = names.iter().filter(|name| kind.matches(name)).collect();
+    match (candidates.as_slice(), names) {
+        ([name], _) => Ok(name.to_string()),
+        ([], [name]) => Ok(name.to_string()),
+        ([], _) => Err(sc64::Error::InvalidInput(format!(
+            "No {kind} file found in archive [{archive_name}]"
+        ))),
+        (candidates, _) => Err(sc64::Error::InvalidInput(format!(
+            "Archive [{archive_name}] contains multiple {kind} files ({}){}",
+            candidates
+                .iter()
+                .map(|name| format!("[{name}]"))
+                .collect::<Vec<String>>()
+                .join(", "),
+            if kind == FileKind::Rom {
+                ", select one with --entry argument"
+            } else {
+                ""
+            }
+        ))),
+    }
+}
+
+fn extract_zip(
+    file: File,
+    archive_name: &str,
+    entry: Option<&str>,
+    kind: FileKind,
+) -> Result<(String, Vec<u8>), sc64::Error> {
+    let mut archive = ZipArchive::new(file).map_err(|error| archive_error(archive_name, error))?;
+    let names: Vec<String> = archive
+        .file_names()
+        .filter(|name| !name.ends_with('/'))
+        .map(|name| name.to_string())
+        .collect();
+    let name = select_entry(archive_name, &names, entry, kind)?;
+    let mut data = vec![];
+    archive
+        .by_name(&name)
+        .map_err(|error| archive_error(archive_name, error))?
+        .read_to_end(&mut data)?;
+    Ok((name, data))
+}
+
+fn extract_seven_zip(
+    file: File,
+    archive_name: &str,
+    entry: Option<&str>,
+    kind: FileKind,
+) -> Result<(String, Vec<u8>), sc64::Error> {
+    let length = file.metadata()?.len();
+    let mut archive = SevenZReader::new(file, length, Password::empty())
+        .map_err(|error| archive_error(archive_name, error))?;
+    let names: Vec<String> = archive
+        .archive()
+        .files
+        .iter()
+        .filter(|file| !file.is_directory())
+        .map(|file| file.name().to_string())
+        .collect();
+    let name = select_entry(archive_name, &names, entry, kind)?;
+    let mut data = vec![];
+    archive
+        .for_each_entries(|file, reader| {
+            if file.name() == name {
+                reader.read_to_end(&mut data)?;
+                return Ok(false);
+            }
+            // Entries in solid blocks are compressed together, skipped entries have to be decompressed anyway
+            std::io::copy(reader, &mut std::io::sink())?;
+            Ok(true)
+        })
+        .map_err(|error| archive_error(archive_name, error))?;
+    Ok((name, data))
+}
+
+fn extract_gzip(file: File, path: &Path) -> Result<(String, Vec<u8>), sc64::Error> {
+    let mut decoder = GzDecoder::new(file);
+    let mut data = vec![];
+    decoder.read_to_end(&mut data)?;
+    let name = decoder
+        .header()
+        .and_then(|header| header.filename())
+        .map(|name| String::from_utf8_lossy(name).to_string())
+        .unwrap_or(path.file_stem().unwrap().to_string_lossy().to_string());
+    Ok((name, data))
+}
+
+/// Returns name and contents of the selected file if provided path points to an archive
+pub fn extract(
+    path: &Path,
+    entry: Option<&str>,
+    kind: FileKind,
+) -> Result<Option<(String, Vec<u8>)>, sc64::Error> {
+    let mut file = File::open(path)?;
+    let archive_name = path.file_name().unwrap().to_string_lossy().to_string();
+    let (name, data) = match detect_format(&mut file)? {
+        Some(ArchiveFormat::Zip) => extract_zip(file, &archive_name, entry, kind)?,
+        Some(ArchiveFormat::SevenZip) => extract_seven_zip(file, &archive_name, entry, kind)?,
+        Some(ArchiveFormat::Gzip) => extract_gzip(file, path)?,
+        None => return Ok(None),
+    };
+    Ok(Some((format!("{archive_name}/{name}"), data)))
+}
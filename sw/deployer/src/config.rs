@@ -0,0 +1,228 @@
+use crate::{Cli, Commands, SaveFormat, SaveType, TvType};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+const CONFIG_FILE_NAME: &str = "sc64.toml";
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Config {
+    port: Option<String>,
+    remote: Option<String>,
+    database: Option<PathBuf>,
+    profiles: HashMap<String, Profile>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct Profile {
+    port: Option<String>,
+    remote: Option<String>,
+    reboot: Option<bool>,
+    save: Option<PathBuf>,
+    save_type: Option<String>,
+    save_format: Option<String>,
+    direct: Option<bool>,
+    no_shadow: Option<bool>,
+    tv: Option<String>,
+    cic_seed: Option<u8>,
+    fix_crc: Option<bool>,
+    isv: Option<u32>,
+    euc_jp: Option<bool>,
+    no_writeback: Option<bool>,
+    init: Option<String>,
+}
+
+struct ConfigFile {
+    path: PathBuf,
+    config: Config,
+}
+
+impl ConfigFile {
+    fn load(path: PathBuf) -> Result<Option<Self>, sc64::Error> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(&path)?;
+        let mut config: Config = toml::from_str(&contents).map_err(|error| {
+            sc64::Error::InvalidInput(format!(
+                "Couldn't parse config file [{}]: {}",
+                path.display(),
+                error.message()
+            ))
+        })?;
+        let directory = path.parent().unwrap_or(Path::new("."));
+        if let Some(database) = &config.database {
+            config.database = Some(directory.join(database));
+        }
+        for profile in config.profiles.values_mut() {
+            if let Some(save) = &profile.save {
+                profile.save = Some(directory.join(save));
+            }
+        }
+        Ok(Some(ConfigFile { path, config }))
+    }
+}
+
+fn find_project_config() -> Option<PathBuf> {
+    let current_dir = std::env::current_dir().ok()?;
+    current_dir
+        .ancestors()
+        .map(|directory| directory.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+fn find_user_config() -> Option<PathBuf> {
+    dirs::config_dir().map(|directory| directory.join("sc64").join(CONFIG_FILE_NAME))
+}
+
+fn find_user_database() -> Option<PathBuf> {
+    dirs::config_dir()
+        .map(|directory| directory.join("sc64").join("database"))
+        .filter(|path| path.is_dir())
+}
+
+fn parse_value<T: ValueEnum>(file: &ConfigFile, key: &str, value: &str) -> Result<T, sc64::Error> {
+    T::from_str(value, true).map_err(|_| {
+        sc64::Error::InvalidInput(format!(
+            "Invalid value [{value}] for [{key}] in config file [{}]",
+            file.path.display()
+        ))
+    })
+}
+
+fn apply_connection(cli: &mut Cli, port: &Option<String>, remote: &Option<String>) {
+    if cli.port.is_some() || cli.remote.is_some() {
+        return;
+    }
+    if remote.is_some() {
+        cli.remote = remote.clone();
+    } else {
+        cli.port = port.clone();
+    }
+}
+
+/// Profile value is used only when neither the flag nor its counterpart was given on the command line
+fn apply_flag(flag: &mut bool, counterpart: bool, value: Option<bool>) {
+    if !*flag && !counterpart {
+        *flag = value.unwrap_or(false);
+    }
+}
+
+fn apply_profile(cli: &mut Cli, file: &ConfigFile, profile: &Profile) -> Result<(), sc64::Error> {
+    apply_connection(cli, &profile.port, &profile.remote);
+
+    let save_type: Option<SaveType> = match &profile.save_type {
+        Some(value) => Some(parse_value(file, "save-type", value)?),
+        None => None,
+    };
+    let save_format: Option<SaveFormat> = match &profile.save_format {
+        Some(value) => Some(parse_value(file, "save-format", value)?),
+        None => None,
+    };
+    let tv: Option<TvType> = match &profile.tv {
+        Some(value) => Some(parse_value(file, "tv", value)?),
+        None => None,
+    };
+
+    match &mut cli.command {
+        Commands::Upload(args) => {
+            apply_flag(&mut args.reboot, args.no_reboot, profile.reboot);
+            apply_flag(&mut args.direct, args.no_direct, profile.direct);
+            apply_flag(&mut args.no_shadow, args.shadow, profile.no_shadow);
+            args.save = args.save.take().or(profile.save.clone());
+            args.save_type = args.save_type.take().or(save_type);
+            if args.save.is_some() {
+                args.save_format = args.save_format.take().or(save_format);
+            }
+            if !args.direct {
+                args.tv = args.tv.take().or(tv);
+            }
+            args.cic_seed = args.cic_seed.or(profile.cic_seed);
+            apply_flag(&mut args.fix_crc, args.no_fix_crc, profile.fix_crc);
+        }
+        Commands::_64DD(args) => {
+            apply_flag(&mut args.reboot, args.no_reboot, profile.reboot);
+            apply_flag(&mut args.direct, args.no_direct, profile.direct);
+            if args.rom.is_some() {
+                args.save = args.save.take().or(profile.save.clone());
+                args.save_type = args.save_type.take().or(save_type);
+            }
+            if !args.direct {
+                args.tv = args.tv.take().or(tv);
+            }
+            args.cic_seed = args.cic_seed.or(profile.cic_seed);
+        }
+        Commands::Watch(args) => {
+            apply_flag(&mut args.direct, args.no_direct, profile.direct);
+            apply_flag(&mut args.no_shadow, args.shadow, profile.no_shadow);
+            args.save = args.save.take().or(profile.save.clone());
+            args.save_type = args.save_type.take().or(save_type);
+            if args.save.is_some() {
+                args.save_format = args.save_format.take().or(save_format);
+            }
+            if !args.direct {
+                args.tv = args.tv.take().or(tv);
+            }
+            args.cic_seed = args.cic_seed.or(profile.cic_seed);
+            apply_flag(&mut args.fix_crc, args.no_fix_crc, profile.fix_crc);
+            args.isv = args.isv.or(profile.isv);
+            apply_flag(&mut args.euc_jp, args.no_euc_jp, profile.euc_jp);
+            apply_flag(&mut args.no_writeback, args.writeback, profile.no_writeback);
+            args.init = args.init.take().or(profile.init.clone());
+        }
+        Commands::Debug(args) => {
+            args.save = args.save.take().or(profile.save.clone());
+            args.isv = args.isv.or(profile.isv);
+            apply_flag(&mut args.euc_jp, args.no_euc_jp, profile.euc_jp);
+            apply_flag(&mut args.no_writeback, args.writeback, profile.no_writeback);
+            args.init = args.init.take().or(profile.init.clone());
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+pub fn apply(cli: &mut Cli) -> Result<(), sc64::Error> {
+    let mut files = vec![];
+    for path in [find_project_config(), find_user_config()]
+        .into_iter()
+        .flatten()
+    {
+        if let Some(file) = ConfigFile::load(path)? {
+            files.push(file);
+        }
+    }
+
+    if let Some(name) = cli.profile.clone() {
+        let found = files.iter().find_map(|file| {
+            file.config
+                .profiles
+                .get(&name)
+                .map(|profile| (file, profile.clone()))
+        });
+        match found {
+            Some((file, profile)) => apply_profile(cli, file, &profile)?,
+            None => {
+                return Err(sc64::Error::InvalidInput(format!(
+                    "Profile [{name}] not found in any {CONFIG_FILE_NAME} file"
+                )))
+            }
+        }
+    }
+
+    for file in files.iter() {
+        apply_connection(cli, &file.config.port, &file.config.remote);
+        if let Some(database) = &file.config.database {
+            cli.database.push(database.clone());
+        }
+    }
+    cli.database.extend(find_user_database());
+
+    Ok(())
+}
@@ -0,0 +1,257 @@
+use colored::Colorize;
+use std::{
+    collections::VecDeque,
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Time the drive reports no disk before the next one is inserted, gives the game a chance to notice the swap
+const DISK_SWAP_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy)]
+pub enum DiskCommand {
+    Eject,
+    /// Disk number starting from 1
+    Insert(usize),
+    Next,
+}
+
+impl DiskCommand {
+    pub fn parse(command: &str) -> Result<Self, String> {
+        let tokens: Vec<&str> = command.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["eject"] => Ok(Self::Eject),
+            ["next"] => Ok(Self::Next),
+            ["insert", number] => number
+                .parse()
+                .map(Self::Insert)
+                .map_err(|_| format!("Invalid disk number [{number}]")),
+            _ => Err(format!(
+                "Unknown disk command [{command}], available commands: eject, insert <number>, next"
+            )),
+        }
+    }
+}
+
+/// Tracks which disk is inserted and drives the 64DD disk state through the swap sequence
+pub struct DiskChanger {
+    names: Vec<String>,
+    selected: usize,
+    inserted: Option<usize>,
+    pending_insert: Option<Instant>,
+    changed: bool,
+}
+
+impl DiskChanger {
+    pub fn new(names: Vec<String>) -> Self {
+        DiskChanger {
+            names,
+            selected: 0,
+            inserted: None,
+            pending_insert: None,
+            changed: false,
+        }
+    }
+
+    /// Index of the disk currently visible to the console
+    pub fn inserted(&self) -> Option<usize> {
+        self.inserted
+    }
+
+    /// Inserts first disk without reporting disk change, used before the console boots
+    pub fn insert_first(&mut self, sc64: &mut sc64::SC64) -> Result<(), sc64::Error> {
+        self.selected = 0;
+        self.inserted = Some(0);
+        sc64.set_64dd_disk_state(sc64::DdDiskState::Inserted)?;
+        self.print("Disk inserted", 0);
+        Ok(())
+    }
+
+    pub fn execute(
+        &mut self,
+        sc64: &mut sc64::SC64,
+        command: DiskCommand,
+    ) -> Result<(), sc64::Error> {
+        match command {
+            DiskCommand::Eject => {
+                self.pending_insert = None;
+                self.eject(sc64)?;
+            }
+            DiskCommand::Insert(number) => {
+                if number == 0 || number > self.names.len() {
+                    println!(
+                        "{}: {}",
+                        "[64DD]".bold(),
+                        format!(
+                            "Disk number [{number}] is out of range (1 - {})",
+                            self.names.len()
+                        )
+                        .bright_red()
+                    );
+                    return Ok(());
+                }
+                self.swap(sc64, number - 1)?;
+            }
+            DiskCommand::Next => {
+                let next = (self.inserted.unwrap_or(self.selected) + 1) % self.names.len();
+                self.swap(sc64, next)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Mirrors SC64 button behavior: ejects inserted disk, or inserts the next one when drive is empty
+    pub fn button(&mut self, sc64: &mut sc64::SC64) -> Result<(), sc64::Error> {
+        if self.inserted.is_some() || self.pending_insert.is_some() {
+            self.pending_insert = None;
+            self.eject(sc64)
+        } else {
+            self.selected = (self.selected + 1) % self.names.len();
+            self.insert(sc64)
+        }
+    }
+
+    /// Finishes delayed disk insertion, should be called periodically
+    pub fn poll(&mut self, sc64: &mut sc64::SC64) -> Result<(), sc64::Error> {
+        if self
+            .pending_insert
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.pending_insert = None;
+            self.insert(sc64)?;
+        }
+        Ok(())
+    }
+
+    /// Console accessing the disk means it has noticed the change, drive can report disk as simply inserted
+    pub fn disk_accessed(&mut self, sc64: &mut sc64::SC64) -> Result<(), sc64::Error> {
+        if self.changed {
+            self.changed = false;
+            sc64.set_64dd_disk_state(sc64::DdDiskState::Inserted)?;
+        }
+        Ok(())
+    }
+
+    fn swap(&mut self, sc64: &mut sc64::SC64, index: usize) -> Result<(), sc64::Error> {
+        if self.inserted == Some(index) {
+            self.print("Disk already inserted", index);
+            return Ok(());
+        }
+        self.selected = index;
+        if self.inserted.is_some() {
+            self.eject(sc64)?;
+            self.pending_insert = Some(Instant::now() + DISK_SWAP_DELAY);
+        } else if self.pending_insert.is_none() {
+            self.insert(sc64)?;
+        }
+        Ok(())
+    }
+
+    fn eject(&mut self, sc64: &mut sc64::SC64) -> Result<(), sc64::Error> {
+        if let Some(index) = self.inserted.take() {
+            self.changed = false;
+            sc64.set_64dd_disk_state(sc64::DdDiskState::Ejected)?;
+            self.print("Disk ejected", index);
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, sc64: &mut sc64::SC64) -> Result<(), sc64::Error> {
+        self.inserted = Some(self.selected);
+        self.changed = true;
+        sc64.set_64dd_disk_state(sc64::DdDiskState::Changed)?;
+        self.print("Disk inserted", self.selected);
+        Ok(())
+    }
+
+    fn print(&self, message: &str, index: usize) {
+        println!(
+            "{}: {message} [{}]",
+            "[64DD]".bold(),
+            self.names[index].bright_green()
+        );
+    }
+}
+
+enum Trigger {
+    Reads(u64),
+    Time(Duration),
+}
+
+/// Sequence of disk commands executed after number of block reads or elapsed time, counted from the previous step
+pub struct DiskScript {
+    steps: VecDeque<(Trigger, DiskCommand)>,
+    reads: u64,
+    step_start: Instant,
+}
+
+impl DiskScript {
+    /// Each line has `<count> reads|seconds <command>` format, empty lines and lines starting with `#` are ignored
+    pub fn load(path: &Path, disks: usize) -> Result<Self, sc64::Error> {
+        let mut steps = VecDeque::new();
+        for (number, line) in fs::read_to_string(path)?.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |message: String| {
+                sc64::Error::InvalidInput(format!(
+                    "Disk script [{}] line {}: {message}",
+                    path.display(),
+                    number + 1
+                ))
+            };
+            let mut tokens = line.splitn(3, char::is_whitespace);
+            let (Some(count), Some(unit), Some(command)) =
+                (tokens.next(), tokens.next(), tokens.next())
+            else {
+                return Err(invalid("expected <count> reads|seconds <command>".into()));
+            };
+            let count: u64 = count
+                .parse()
+                .map_err(|_| invalid(format!("invalid count [{count}]")))?;
+            let trigger = match unit {
+                "reads" => Trigger::Reads(count),
+                "seconds" => Trigger::Time(Duration::from_secs(count)),
+                _ => return Err(invalid(format!("unknown unit [{unit}]"))),
+            };
+            let command = DiskCommand::parse(command.trim()).map_err(invalid)?;
+            if let DiskCommand::Insert(number) = command {
+                if number == 0 || number > disks {
+                    return Err(invalid(format!(
+                        "disk number [{number}] is out of range (1 - {disks})"
+                    )));
+                }
+            }
+            steps.push_back((trigger, command));
+        }
+        Ok(DiskScript {
+            steps,
+            reads: 0,
+            step_start: Instant::now(),
+        })
+    }
+
+    pub fn block_read(&mut self) {
+        self.reads += 1;
+    }
+
+    /// Returns command of the current step when its trigger condition is met
+    pub fn next_command(&mut self) -> Option<DiskCommand> {
+        let ready = match self.steps.front()? {
+            (Trigger::Reads(reads), _) => self.reads >= *reads,
+            (Trigger::Time(time), _) => self.step_start.elapsed() >= *time,
+        };
+        if !ready {
+            return None;
+        }
+        self.reads = 0;
+        self.step_start = Instant::now();
+        self.steps.pop_front().map(|(_, command)| command)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
@@ -0,0 +1,199 @@
+use colored::Colorize;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+const HOT_LBAS_COUNT: usize = 10;
+const FAILED_REQUESTS_COUNT: usize = 20;
+
+/// Single disk request received from the 64DD emulation and our reply to it
+pub struct Request {
+    pub disk: Option<usize>,
+    pub is_read: bool,
+    pub track: u32,
+    pub head: u32,
+    pub block: u32,
+    pub lba: Option<usize>,
+    pub zone: Option<usize>,
+    pub success: bool,
+    pub latency: Duration,
+}
+
+impl Request {
+    fn kind(&self) -> &'static str {
+        if self.is_read {
+            "R"
+        } else {
+            "W"
+        }
+    }
+}
+
+#[derive(Default)]
+struct ZoneStats {
+    reads: u64,
+    writes: u64,
+}
+
+/// Records disk requests to the optional trace file and collects session statistics
+pub struct DiskTrace {
+    file: Option<BufWriter<File>>,
+    start: Instant,
+    reads: u64,
+    writes: u64,
+    failed: u64,
+    total_latency: Duration,
+    max_latency: Duration,
+    zones: HashMap<usize, ZoneStats>,
+    lbas: HashMap<(usize, usize), u64>,
+    failed_requests: HashMap<(Option<usize>, &'static str, u32, u32, u32), u64>,
+}
+
+impl DiskTrace {
+    pub fn new(path: Option<&Path>) -> Result<Self, sc64::Error> {
+        let file = match path {
+            Some(path) => {
+                let mut file = BufWriter::new(File::create(path)?);
+                writeln!(
+                    file,
+                    "time,disk,kind,track,head,block,lba,zone,result,latency_us"
+                )?;
+                Some(file)
+            }
+            None => None,
+        };
+        Ok(DiskTrace {
+            file,
+            start: Instant::now(),
+            reads: 0,
+            writes: 0,
+            failed: 0,
+            total_latency: Duration::ZERO,
+            max_latency: Duration::ZERO,
+            zones: HashMap::new(),
+            lbas: HashMap::new(),
+            failed_requests: HashMap::new(),
+        })
+    }
+
+    pub fn record(&mut self, request: &Request) -> Result<(), sc64::Error> {
+        if let Some(file) = self.file.as_mut() {
+            let optional = |value: Option<usize>| value.map_or(String::new(), |v| v.to_string());
+            writeln!(
+                file,
+                "{:.6},{},{},{},{},{},{},{},{},{}",
+                self.start.elapsed().as_secs_f64(),
+                optional(request.disk.map(|disk| disk + 1)),
+                request.kind(),
+                request.track,
+                request.head,
+                request.block,
+                optional(request.lba),
+                optional(request.zone),
+                if request.success { "ok" } else { "failed" },
+                request.latency.as_micros()
+            )?;
+        }
+
+        if request.is_read {
+            self.reads += 1;
+        } else {
+            self.writes += 1;
+        }
+        self.total_latency += request.latency;
+        self.max_latency = self.max_latency.max(request.latency);
+
+        if !request.success {
+            self.failed += 1;
+            *self
+                .failed_requests
+                .entry((
+                    request.disk,
+                    request.kind(),
+                    request.track,
+                    request.head,
+                    request.block,
+                ))
+                .or_default() += 1;
+            return Ok(());
+        }
+
+        if let Some(zone) = request.zone {
+            let stats = self.zones.entry(zone).or_default();
+            if request.is_read {
+                stats.reads += 1;
+            } else {
+                stats.writes += 1;
+            }
+        }
+        if let (Some(disk), Some(lba)) = (request.disk, request.lba) {
+            *self.lbas.entry((disk, lba)).or_default() += 1;
+        }
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), sc64::Error> {
+        if let Some(file) = self.file.as_mut() {
+            file.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn print_summary(&self, disk_names: &[String]) {
+        let requests = self.reads + self.writes;
+        println!("{}", "[64DD]: Session summary:".bold());
+        println!(
+            " Requests:          {requests} ({} reads, {} writes, {} failed)",
+            self.reads, self.writes, self.failed
+        );
+        if requests == 0 {
+            return;
+        }
+        println!(
+            " Service time:      {:.3} ms average, {:.3} ms max",
+            self.total_latency.as_secs_f64() * 1000.0 / requests as f64,
+            self.max_latency.as_secs_f64() * 1000.0
+        );
+
+        let mut zones: Vec<(&usize, &ZoneStats)> = self.zones.iter().collect();
+        zones.sort_by_key(|(zone, _)| **zone);
+        if !zones.is_empty() {
+            println!(" Zone accesses:");
+            for (zone, stats) in zones {
+                println!(
+                    "  Zone {zone:>2}: {:>6} reads, {:>6} writes",
+                    stats.reads, stats.writes
+                );
+            }
+        }
+
+        let mut lbas: Vec<(&(usize, usize), &u64)> = self.lbas.iter().collect();
+        lbas.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        if !lbas.is_empty() {
+            println!(" Most accessed LBAs:");
+            for ((disk, lba), count) in lbas.into_iter().take(HOT_LBAS_COUNT) {
+                println!("  LBA {lba:>4} [{}]: {count}", disk_names[*disk]);
+            }
+        }
+
+        let mut failed: Vec<_> = self.failed_requests.iter().collect();
+        failed.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        if !failed.is_empty() {
+            println!(" Failed requests:");
+            for ((disk, kind, track, head, block), count) in
+                failed.into_iter().take(FAILED_REQUESTS_COUNT)
+            {
+                let disk = disk.map_or("No disk".to_string(), |disk| disk_names[disk].clone());
+                println!(
+                    "  {}",
+                    format!("[{kind}] {track:4}:{head}:{block} [{disk}]: {count}").bright_red()
+                );
+            }
+        }
+    }
+}
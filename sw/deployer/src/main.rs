@@ -1,27 +1,66 @@
-mod debug;
-mod disk;
-mod n64;
-mod sc64;
+mod archive;
+mod config;
+mod disk_changer;
+mod disk_trace;
+mod store;
 
 use chrono::Local;
 use clap::{Args, Parser, Subcommand, ValueEnum};
 use clap_num::{maybe_hex, maybe_hex_range};
 use colored::Colorize;
 use panic_message::panic_message;
+use sc64::{debug, disk, n64};
+use serde_json::{json, Value};
 use std::{
     fs::File,
-    io::{stdin, stdout, Read, Write},
+    io::{stdin, stdout, Cursor, Read, Seek, SeekFrom, Write},
     panic,
-    path::PathBuf,
+    path::{Component, Path, PathBuf},
     process,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
+    time::{Duration, Instant, SystemTime},
 };
 
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if json_output() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+macro_rules! status_inline {
+    ($($arg:tt)*) => {
+        if json_output() {
+            eprint!($($arg)*)
+        } else {
+            print!($($arg)*)
+        }
+    };
+}
+
+const EXIT_CODES_HELP: &str = "Exit codes:
+  0   Success
+  1   Unexpected internal error
+  2   Invalid command line usage
+  3   Invalid input (file or argument)
+  4   Device not found or connection failed
+  5   Timed out while waiting for the device
+  6   Device communication protocol error
+  7   Unsupported firmware or firmware update failure
+  8   SD card error
+  9   SD card filesystem (FatFs) error
+  10  Host I/O error";
+
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, after_long_help = EXIT_CODES_HELP)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
@@ -33,6 +72,18 @@ struct Cli {
     /// Connect to SC64 device on provided remote address
     #[arg(short, long, conflicts_with = "port")]
     remote: Option<String>,
+
+    /// Print command result as a single JSON document (status messages go to stderr)
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Use named profile from sc64.toml (project directory or user config directory)
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Load additional game database file or directory (ini/TOML), entries override embedded database
+    #[arg(long, global = true)]
+    database: Vec<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -55,6 +106,9 @@ enum Commands {
     /// Enter debug mode
     Debug(DebugArgs),
 
+    /// Upload ROM, then redeploy it on every change while running debug mode
+    Watch(WatchArgs),
+
     /// Dump data from arbitrary location in SC64 memory space
     Dump(DumpArgs),
 
@@ -82,6 +136,24 @@ enum Commands {
         command: FirmwareCommands,
     },
 
+    /// Inspect N64 ROM files
+    Rom {
+        #[command(subcommand)]
+        command: RomCommands,
+    },
+
+    /// Convert save files and manage save history
+    Save {
+        #[command(subcommand)]
+        command: SaveCommands,
+    },
+
+    /// Inspect, convert and create 64DD disk images
+    Disk {
+        #[command(subcommand)]
+        command: DiskCommands,
+    },
+
     /// Test SC64 hardware
     Test,
 
@@ -95,9 +167,13 @@ struct UploadArgs {
     rom: PathBuf,
 
     /// Attempt to reboot the console (requires specific support in the running game)
-    #[arg(short = 'a', long)]
+    #[arg(short = 'a', long, overrides_with = "no_reboot")]
     reboot: bool,
 
+    /// Do not reboot the console even when enabled by the selected profile
+    #[arg(long, overrides_with = "reboot")]
+    no_reboot: bool,
+
     /// Path to the save file
     #[arg(short, long)]
     save: Option<PathBuf>,
@@ -106,14 +182,26 @@ struct UploadArgs {
     #[arg(short = 't', long)]
     save_type: Option<SaveType>,
 
+    /// Format of the save file (detected from file size when not provided)
+    #[arg(long, requires = "save")]
+    save_format: Option<SaveFormat>,
+
     /// Use direct boot mode (skip bootloader)
-    #[arg(short, long)]
+    #[arg(short, long, overrides_with = "no_direct")]
     direct: bool,
 
+    /// Do not use direct boot mode even when enabled by the selected profile or game database
+    #[arg(long, overrides_with = "direct")]
+    no_direct: bool,
+
     /// Do not put last 128 kiB of ROM inside flash memory (can corrupt non EEPROM saves)
-    #[arg(short, long)]
+    #[arg(short, long, overrides_with = "shadow")]
     no_shadow: bool,
 
+    /// Put last 128 kiB of ROM inside flash memory even when disabled by the selected profile
+    #[arg(long, overrides_with = "no_shadow")]
+    shadow: bool,
+
     /// Force TV type
     #[arg(long, conflicts_with = "direct")]
     tv: Option<TvType>,
@@ -121,6 +209,22 @@ struct UploadArgs {
     /// Force CIC seed
     #[arg(long, value_parser = |s: &str| maybe_hex::<u8>(s))]
     cic_seed: Option<u8>,
+
+    /// Recalculate ROM header checksum (CRC1/CRC2) before uploading (6101/6102/7101/7102, 6103/7103, 6105/7105, 6106/7106 and 8303 CICs)
+    #[arg(long, overrides_with = "no_fix_crc")]
+    fix_crc: bool,
+
+    /// Do not recalculate ROM header checksum even when enabled by the selected profile
+    #[arg(long, overrides_with = "fix_crc")]
+    no_fix_crc: bool,
+
+    /// Apply patch file (IPS, BPS, APS or xdelta) to the ROM before uploading, can be used multiple times
+    #[arg(long)]
+    patch: Vec<PathBuf>,
+
+    /// Name of the ROM file to use when ROM path points to an archive with multiple files
+    #[arg(long)]
+    entry: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -133,6 +237,10 @@ enum DownloadCommands {
 struct DownloadArgs {
     /// Path to the file
     path: PathBuf,
+
+    /// Convert downloaded save to provided format
+    #[arg(long)]
+    save_format: Option<SaveFormat>,
 }
 
 #[derive(Args)]
@@ -140,17 +248,57 @@ struct _64DDArgs {
     /// Path to the 64DD IPL file
     ddipl: PathBuf,
 
-    /// Path to the 64DD disk file (.ndd format, can be specified multiple times)
+    /// Path to the 64DD disk file (.ndd or .d64 format, can be specified multiple times)
     disk: Vec<PathBuf>,
 
+    /// Write disk changes to the overlay file next to the disk image instead of the image itself (all disks or comma separated disk numbers, starting from 1)
+    #[arg(long, num_args = 0.., require_equals = true, value_delimiter = ',', value_name = "DISKS")]
+    overlay: Option<Vec<usize>>,
+
+    /// Keep disk changes only in memory, overlay files and disk images are never modified
+    #[arg(long)]
+    volatile: bool,
+
+    /// Reject disk writes like on a write protected disk (all disks or comma separated disk numbers, starting from 1)
+    #[arg(long, num_args = 0.., require_equals = true, value_delimiter = ',', value_name = "DISKS")]
+    read_only: Option<Vec<usize>>,
+
+    /// Treat disk files without write permission as read-only instead of failing to open them
+    #[arg(long)]
+    honor_permissions: bool,
+
+    /// Path to the script with disk changes executed after number of block reads or seconds
+    #[arg(long)]
+    disk_script: Option<PathBuf>,
+
+    /// Record every disk request to the provided CSV file
+    #[arg(long)]
+    trace: Option<PathBuf>,
+
+    /// Copy disks to the SD card and serve them from there, PC can be disconnected afterwards
+    #[arg(long, conflicts_with_all = ["overlay", "volatile", "honor_permissions", "disk_script", "trace"])]
+    sd: bool,
+
+    /// Directory on the SD card where disks are copied to
+    #[arg(long, requires = "sd", default_value = "/64dd")]
+    sd_dir: PathBuf,
+
+    /// Replace disk stored on the SD card when it doesn't match the provided disk (progress saved on the SD card is lost)
+    #[arg(long, requires = "sd")]
+    sd_overwrite: bool,
+
     /// Path to the ROM file
     #[arg(short, long)]
     rom: Option<PathBuf>,
 
     /// Attempt to reboot the console (requires specific support in the running game)
-    #[arg(short = 'a', long)]
+    #[arg(short = 'a', long, overrides_with = "no_reboot")]
     reboot: bool,
 
+    /// Do not reboot the console even when enabled by the selected profile
+    #[arg(long, overrides_with = "reboot")]
+    no_reboot: bool,
+
     /// Path to the save file (also used by save writeback mechanism)
     #[arg(short, long, requires = "rom")]
     save: Option<PathBuf>,
@@ -159,10 +307,22 @@ struct _64DDArgs {
     #[arg(short = 't', long, requires = "rom")]
     save_type: Option<SaveType>,
 
+    /// Apply patch file (IPS, BPS, APS or xdelta) to the ROM before uploading, can be used multiple times
+    #[arg(long, requires = "rom")]
+    patch: Vec<PathBuf>,
+
+    /// Name of the ROM file to use when ROM path points to an archive with multiple files
+    #[arg(long, requires = "rom")]
+    entry: Option<String>,
+
     /// Use direct boot mode (skip bootloader)
-    #[arg(short, long)]
+    #[arg(short, long, overrides_with = "no_direct")]
     direct: bool,
 
+    /// Do not use direct boot mode even when enabled by the selected profile or game database
+    #[arg(long, overrides_with = "direct")]
+    no_direct: bool,
+
     /// Force TV type
     #[arg(long, conflicts_with = "direct")]
     tv: Option<TvType>,
@@ -170,6 +330,10 @@ struct _64DDArgs {
     /// Force CIC seed
     #[arg(long, value_parser = |s: &str| maybe_hex::<u8>(s))]
     cic_seed: Option<u8>,
+
+    /// Do not record written back saves in the save history
+    #[arg(long)]
+    no_save_history: bool,
 }
 
 #[derive(Args)]
@@ -183,16 +347,122 @@ struct DebugArgs {
     isv: Option<u32>,
 
     /// Use EUC-JP encoding for text printing
-    #[arg(long)]
+    #[arg(long, overrides_with = "no_euc_jp")]
     euc_jp: bool,
 
+    /// Do not use EUC-JP encoding even when enabled by the selected profile
+    #[arg(long, overrides_with = "euc_jp")]
+    no_euc_jp: bool,
+
     /// Do not enable save writeback via USB
+    #[arg(long, overrides_with = "writeback")]
+    no_writeback: bool,
+
+    /// Enable save writeback via USB even when disabled by the selected profile
+    #[arg(long, overrides_with = "no_writeback")]
+    writeback: bool,
+
+    /// Do not record written back saves in the save history
     #[arg(long)]
+    no_save_history: bool,
+
+    /// List of commands to send after connecting to the SC64, semicolon separated (;)
+    #[arg(long)]
+    init: Option<String>,
+}
+
+#[derive(Args)]
+struct WatchArgs {
+    /// Path to the ROM file
+    rom: PathBuf,
+
+    /// Path to the save file (also used by save writeback mechanism)
+    #[arg(short, long)]
+    save: Option<PathBuf>,
+
+    /// Override autodetected save type
+    #[arg(short = 't', long)]
+    save_type: Option<SaveType>,
+
+    /// Format of the save file (detected from file size when not provided)
+    #[arg(long, requires = "save")]
+    save_format: Option<SaveFormat>,
+
+    /// Path to the 64DD IPL file (enables 64DD IPL only mode)
+    #[arg(long)]
+    ddipl: Option<PathBuf>,
+
+    /// Use direct boot mode (skip bootloader)
+    #[arg(short, long, overrides_with = "no_direct")]
+    direct: bool,
+
+    /// Do not use direct boot mode even when enabled by the selected profile or game database
+    #[arg(long, overrides_with = "direct")]
+    no_direct: bool,
+
+    /// Do not put last 128 kiB of ROM inside flash memory (can corrupt non EEPROM saves)
+    #[arg(short, long, overrides_with = "shadow")]
+    no_shadow: bool,
+
+    /// Put last 128 kiB of ROM inside flash memory even when disabled by the selected profile
+    #[arg(long, overrides_with = "no_shadow")]
+    shadow: bool,
+
+    /// Force TV type
+    #[arg(long, conflicts_with = "direct")]
+    tv: Option<TvType>,
+
+    /// Force CIC seed
+    #[arg(long, value_parser = |s: &str| maybe_hex::<u8>(s))]
+    cic_seed: Option<u8>,
+
+    /// Recalculate ROM header checksum (CRC1/CRC2) before uploading (6101/6102/7101/7102, 6103/7103, 6105/7105, 6106/7106 and 8303 CICs)
+    #[arg(long, overrides_with = "no_fix_crc")]
+    fix_crc: bool,
+
+    /// Do not recalculate ROM header checksum even when enabled by the selected profile
+    #[arg(long, overrides_with = "fix_crc")]
+    no_fix_crc: bool,
+
+    /// Apply patch file (IPS, BPS, APS or xdelta) to the ROM before uploading, can be used multiple times
+    #[arg(long)]
+    patch: Vec<PathBuf>,
+
+    /// Name of the ROM file to use when ROM path points to an archive with multiple files
+    #[arg(long)]
+    entry: Option<String>,
+
+    /// Enable IS-Viewer64 and set listening address at ROM offset (in most cases it's fixed at 0x03FF0000)
+    #[arg(long, value_name = "offset", value_parser = |s: &str| maybe_hex_range::<u32>(s, 0x00000004, 0x03FF0000))]
+    isv: Option<u32>,
+
+    /// Use EUC-JP encoding for text printing
+    #[arg(long, overrides_with = "no_euc_jp")]
+    euc_jp: bool,
+
+    /// Do not use EUC-JP encoding even when enabled by the selected profile
+    #[arg(long, overrides_with = "euc_jp")]
+    no_euc_jp: bool,
+
+    /// Do not enable save writeback via USB
+    #[arg(long, overrides_with = "writeback")]
     no_writeback: bool,
 
+    /// Enable save writeback via USB even when disabled by the selected profile
+    #[arg(long, overrides_with = "no_writeback")]
+    writeback: bool,
+
+    /// Do not record written back saves in the save history
+    #[arg(long)]
+    no_save_history: bool,
+
     /// List of commands to send after connecting to the SC64, semicolon separated (;)
     #[arg(long)]
     init: Option<String>,
+
+    /// Interval between file change checks in milliseconds
+    #[arg(long, default_value_t = 500)]
+    interval: u64,
 }
 
 #[derive(Args)]
@@ -298,6 +568,195 @@ enum FirmwareCommands {
     Update(FirmwareUpdateArgs),
 }
 
+#[derive(Subcommand)]
+enum RomCommands {
+    /// Print ROM header, detected CIC and save type
+    Info(RomArgs),
+
+    /// Recalculate ROM header checksum (CRC1/CRC2) for the detected CIC (6101/6102/7101/7102, 6103/7103, 6105/7105, 6106/7106 and 8303)
+    FixCrc(RomFixCrcArgs),
+}
+
+#[derive(Args)]
+struct RomArgs {
+    /// Path to the ROM file
+    rom: PathBuf,
+}
+
+#[derive(Args)]
+struct RomFixCrcArgs {
+    /// Path to the ROM file
+    rom: PathBuf,
+
+    /// Write fixed ROM to provided file instead of modifying it in place
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Force CIC seed (required when IPL3 isn't recognized)
+    #[arg(long, value_parser = |s: &str| maybe_hex::<u8>(s))]
+    cic_seed: Option<u8>,
+}
+
+#[derive(Subcommand)]
+enum SaveCommands {
+    /// Convert save file between flashcart and emulator formats
+    Convert(SaveConvertArgs),
+
+    /// List saves recorded by the save writeback mechanism (all games if ROM is not provided)
+    History(SaveHistoryArgs),
+
+    /// Restore save from the history to the SC64 or to a file
+    Restore(SaveRestoreArgs),
+
+    /// Compare two saves (history entry numbers or file paths)
+    Diff(SaveDiffArgs),
+
+    /// Periodically download save from the SC64 and print changes
+    Watch(SaveWatchArgs),
+}
+
+#[derive(Args)]
+struct SaveHistoryArgs {
+    /// Path to the ROM file
+    rom: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct SaveRestoreArgs {
+    /// Path to the ROM file
+    rom: PathBuf,
+
+    /// History entry number (0 is the most recent save)
+    #[arg(default_value_t = 0)]
+    entry: usize,
+
+    /// Write save to provided file instead of uploading it to the SC64
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Format of the output save file
+    #[arg(long, requires = "output")]
+    save_format: Option<SaveFormat>,
+}
+
+#[derive(Args)]
+struct SaveDiffArgs {
+    /// First save (history entry number or path to the save file)
+    a: String,
+
+    /// Second save (history entry number or path to the save file)
+    b: String,
+
+    /// Path to the ROM file (required for history entry numbers)
+    #[arg(long)]
+    rom: Option<PathBuf>,
+
+    /// Save type used to split data into EEPROM blocks, SRAM banks or FlashRAM pages (detected when not provided)
+    #[arg(short = 't', long)]
+    save_type: Option<SaveType>,
+}
+
+#[derive(Args)]
+struct SaveWatchArgs {
+    /// Interval between save downloads in milliseconds
+    #[arg(long, default_value_t = 1000)]
+    interval: u64,
+}
+
+#[derive(Args)]
+struct SaveConvertArgs {
+    /// Path to the input save file
+    input: PathBuf,
+
+    /// Path to the output save file (input name with extension matching the output format if not provided)
+    output: Option<PathBuf>,
+
+    /// Format of the input save file (detected from file size when not provided)
+    #[arg(long)]
+    from: Option<SaveFormat>,
+
+    /// Format of the output save file
+    #[arg(long)]
+    to: SaveFormat,
+
+    /// Save type (detected from file size when not provided)
+    #[arg(short = 't', long)]
+    save_type: Option<SaveType>,
+}
+
+#[derive(Subcommand)]
+enum DiskCommands {
+    /// Print information stored in the disk system area
+    Info(DiskInfoArgs),
+
+    /// Convert disk image between .ndd and .d64 formats
+    Convert(DiskConvertArgs),
+
+    /// Create blank formatted disk image
+    Create(DiskCreateArgs),
+
+    /// Apply changes stored in the overlay file to the disk image and remove the overlay
+    Commit(DiskOverlayArgs),
+
+    /// Remove the overlay file without applying stored changes
+    Discard(DiskOverlayArgs),
+
+    /// Check every system and ID LBA copy stored in the .ndd disk image
+    Verify(DiskSystemAreaArgs),
+
+    /// Rebuild damaged system and ID LBA copies of the .ndd disk image from the valid ones
+    Repair(DiskSystemAreaArgs),
+}
+
+#[derive(Args)]
+struct DiskSystemAreaArgs {
+    /// Path to the 64DD disk file
+    disk: PathBuf,
+}
+
+#[derive(Args)]
+struct DiskOverlayArgs {
+    /// Path to the 64DD disk file
+    disk: PathBuf,
+}
+
+#[derive(Args)]
+struct DiskInfoArgs {
+    /// Path to the 64DD disk file
+    disk: PathBuf,
+}
+
+#[derive(Args)]
+struct DiskConvertArgs {
+    /// Path to the input 64DD disk file
+    input: PathBuf,
+
+    /// Path to the output 64DD disk file (input name with extension matching the output format if not provided)
+    output: Option<PathBuf>,
+
+    /// Format of the output disk file (detected from output file extension when not provided)
+    #[arg(long)]
+    to: Option<DiskImageFormat>,
+}
+
+#[derive(Args)]
+struct DiskCreateArgs {
+    /// Path to the output 64DD disk file
+    output: PathBuf,
+
+    /// Disk type (defines ROM and RAM area sizes)
+    #[arg(short = 't', long = "type", value_parser = clap::value_parser!(u8).range(0..=6))]
+    disk_type: u8,
+
+    /// Create development disk instead of the retail one
+    #[arg(long)]
+    development: bool,
+
+    /// Format of the output disk file (detected from output file extension when not provided)
+    #[arg(long)]
+    format: Option<DiskImageFormat>,
+}
+
 #[derive(Args)]
 struct FirmwareArgs {
     /// Path to the firmware file
@@ -360,23 +819,78 @@ impl From<SaveType> for sc64::SaveType {
     }
 }
 
+#[derive(Clone, ValueEnum)]
+enum SaveFormat {
+    /// SC64 memory layout (also used by most flashcarts)
+    Raw,
+    /// SRAM and FlashRAM with bytes swapped in 32-bit words
+    Swapped,
+    /// Project64 .eep/.sra/.fla files
+    Project64,
+    /// mupen64plus (RetroArch) combined .srm file
+    Mupen64plus,
+    /// EverDrive-64 .eep/.srm/.fla files
+    Everdrive,
+}
+
+impl From<SaveFormat> for sc64::save::SaveFormat {
+    fn from(value: SaveFormat) -> Self {
+        match value {
+            SaveFormat::Raw => Self::Raw,
+            SaveFormat::Swapped => Self::Swapped,
+            SaveFormat::Project64 => Self::Project64,
+            SaveFormat::Mupen64plus => Self::Mupen64Plus,
+            SaveFormat::Everdrive => Self::EverDrive,
+        }
+    }
+}
+
+#[derive(Clone, ValueEnum)]
+enum DiskImageFormat {
+    /// Full disk dump including whole system area
+    Ndd,
+    /// System area header followed by the user area
+    D64,
+}
+
+impl From<DiskImageFormat> for disk::ImageFormat {
+    fn from(value: DiskImageFormat) -> Self {
+        match value {
+            DiskImageFormat::Ndd => Self::Ndd,
+            DiskImageFormat::D64 => Self::D64,
+        }
+    }
+}
+
 #[derive(Clone, ValueEnum)]
 enum TvType {
     PAL,
     NTSC,
     MPAL,
+    /// Select TV type based on the region code in the ROM header
+    Auto,
 }
 
-impl From<TvType> for sc64::TvType {
-    fn from(value: TvType) -> Self {
-        match value {
-            TvType::PAL => Self::PAL,
-            TvType::NTSC => Self::NTSC,
-            TvType::MPAL => Self::MPAL,
-        }
+impl TvType {
+    fn resolve<T: Read + Seek>(&self, rom: &mut T) -> Result<sc64::TvType, sc64::Error> {
+        Ok(match self {
+            Self::PAL => sc64::TvType::PAL,
+            Self::NTSC => sc64::TvType::NTSC,
+            Self::MPAL => sc64::TvType::MPAL,
+            Self::Auto => {
+                let (header, _) = sc64::rom::read_boot(rom)?;
+                status!(
+                    "ROM region code [{}] ({})",
+                    header.region_code as char,
+                    header.region_name()
+                );
+                header.tv_type()
+            }
+        })
     }
 }
 
+#[derive(Clone)]
 enum Connection {
     Local(Option<String>),
     Remote(String),
@@ -393,8 +907,25 @@ fn main() {
     #[cfg(windows)]
     colored::control::set_virtual_terminal(true).ok();
 
-    match panic::catch_unwind(|| handle_command(&cli.command, cli.port, cli.remote)) {
-        Ok(_) => {}
+    JSON_OUTPUT.store(cli.json, Ordering::Relaxed);
+
+    match panic::catch_unwind(|| handle_command(cli)) {
+        Ok(Ok(())) => {}
+        Ok(Err(error)) => {
+            if json_output() {
+                let document = json!({
+                    "error": {
+                        "kind": format!("{:?}", error.kind()),
+                        "message": format_error(&error),
+                        "exit_code": exit_code(&error),
+                    }
+                });
+                eprintln!("{document}");
+            } else {
+                eprintln!("{}", format_error(&error).red());
+            }
+            process::exit(exit_code(&error));
+        }
         Err(payload) => {
             eprintln!("{}", panic_message(&payload).red());
             process::exit(1);
@@ -402,37 +933,101 @@ fn main() {
     }
 }
 
-fn handle_command(command: &Commands, port: Option<String>, remote: Option<String>) {
-    let connection = if let Some(remote) = remote {
+fn format_error(error: &sc64::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(error) = source {
+        message.push_str(&format!(": {error}"));
+        source = error.source();
+    }
+    message
+}
+
+fn exit_code(error: &sc64::Error) -> i32 {
+    match error.kind() {
+        sc64::ErrorKind::InvalidInput => 3,
+        sc64::ErrorKind::Connection => 4,
+        sc64::ErrorKind::Timeout => 5,
+        sc64::ErrorKind::Protocol => 6,
+        sc64::ErrorKind::Firmware => 7,
+        sc64::ErrorKind::SdCard => 8,
+        sc64::ErrorKind::FileSystem => 9,
+        sc64::ErrorKind::Io => 10,
+    }
+}
+
+fn handle_command(mut cli: Cli) -> Result<(), sc64::Error> {
+    config::apply(&mut cli)?;
+    let command = &cli.command;
+    let connection = if let Some(remote) = cli.remote {
         Connection::Remote(remote)
     } else {
-        Connection::Local(port)
+        Connection::Local(cli.port)
+    };
+    let interactive = match command {
+        // Disks served from the SD card and 64DD IPL only mode finish without serving disks over USB
+        Commands::_64DD(args) => !args.sd && !args.disk.is_empty(),
+        Commands::Debug(_)
+        | Commands::Watch(_)
+        | Commands::Server(_)
+        | Commands::Save {
+            command: SaveCommands::Watch(_),
+        } => true,
+        _ => false,
     };
-    let result = match command {
+    if json_output() && interactive {
+        return Err(sc64::Error::InvalidInput(
+            "JSON output is not supported by interactive commands".into(),
+        ));
+    }
+    match command {
         Commands::List => handle_list_command(),
-        Commands::Upload(args) => handle_upload_command(connection, args),
+        Commands::Upload(args) => {
+            handle_upload_command(connection, args, &n64::Database::load(&cli.database)?)
+        }
         Commands::Download { command } => handle_download_command(connection, command),
-        Commands::_64DD(args) => handle_64dd_command(connection, args),
+        Commands::Save { command } => handle_save_command(connection, command),
+        Commands::Disk { command } => handle_disk_command(command),
+        Commands::_64DD(args) => {
+            handle_64dd_command(connection, args, &n64::Database::load(&cli.database)?)
+        }
         Commands::Debug(args) => handle_debug_command(connection, args),
+        Commands::Watch(args) => {
+            handle_watch_command(connection, args, &n64::Database::load(&cli.database)?)
+        }
         Commands::Dump(args) => handle_dump_command(connection, args),
         Commands::SD { command } => handle_sd_command(connection, command),
         Commands::Info => handle_info_command(connection),
         Commands::Reset => handle_reset_command(connection),
         Commands::Set { command } => handle_set_command(connection, command),
         Commands::Firmware { command } => handle_firmware_command(connection, command),
+        Commands::Rom { command } => {
+            handle_rom_command(command, &n64::Database::load(&cli.database)?)
+        }
         Commands::Test => handle_test_command(connection),
         Commands::Server(args) => handle_server_command(connection, args),
-    };
-    match result {
-        Ok(()) => {}
-        Err(error) => panic!("{error}"),
-    };
+    }
 }
 
 fn handle_list_command() -> Result<(), sc64::Error> {
     let devices = sc64::list_local_devices()?;
 
-    println!("{}", "Found devices:".bold());
+    if json_output() {
+        let devices: Vec<Value> = devices
+            .iter()
+            .map(|d| {
+                json!({
+                    "serial": d.serial,
+                    "port": d.port,
+                    "backend": d.backend.to_string(),
+                })
+            })
+            .collect();
+        print_json(json!({ "devices": devices }));
+        return Ok(());
+    }
+
+    println!("{}", "Found devices:".bold());
     for (i, d) in devices.iter().enumerate() {
         let index = i + 1;
         println!(
@@ -446,11 +1041,15 @@ fn handle_list_command() -> Result<(), sc64::Error> {
     Ok(())
 }
 
-fn handle_upload_command(connection: Connection, args: &UploadArgs) -> Result<(), sc64::Error> {
+fn handle_upload_command(
+    connection: Connection,
+    args: &UploadArgs,
+    database: &n64::Database,
+) -> Result<(), sc64::Error> {
     let mut sc64 = init_sc64(connection, true)?;
 
     if args.reboot && !sc64.try_notify_via_aux(sc64::AuxMessage::Halt)? {
-        println!(
+        status!(
             "{}",
             "Warning: no response for [Halt] AUX message".bright_yellow()
         );
@@ -458,57 +1057,157 @@ fn handle_upload_command(connection: Connection, args: &UploadArgs) -> Result<()
 
     sc64.reset_state()?;
 
-    let (mut rom_file, rom_name, rom_length) = open_file(&args.rom)?;
+    let deployment = deploy_rom(
+        &mut sc64,
+        &RomDeployment {
+            rom: &args.rom,
+            entry: args.entry.as_deref(),
+            patch: &args.patch,
+            fix_crc: args.fix_crc,
+            no_shadow: args.no_shadow,
+            save: args.save.as_ref(),
+            save_type: args.save_type.clone(),
+            save_format: args.save_format.clone(),
+            direct: args.direct,
+            no_direct: args.no_direct,
+            tv: args.tv.clone(),
+            cic_seed: args.cic_seed,
+        },
+        database,
+    )?;
+
+    if args.reboot && !sc64.try_notify_via_aux(sc64::AuxMessage::Reboot)? {
+        status!(
+            "{}",
+            "Warning: no response for [Reboot] AUX message".bright_yellow()
+        );
+    }
+
+    if json_output() {
+        print_json(deployment);
+    }
+
+    Ok(())
+}
+
+/// ROM upload options shared by `upload` and `watch` commands
+struct RomDeployment<'a> {
+    rom: &'a PathBuf,
+    entry: Option<&'a str>,
+    patch: &'a [PathBuf],
+    fix_crc: bool,
+    no_shadow: bool,
+    save: Option<&'a PathBuf>,
+    save_type: Option<SaveType>,
+    save_format: Option<SaveFormat>,
+    direct: bool,
+    no_direct: bool,
+    tv: Option<TvType>,
+    cic_seed: Option<u8>,
+}
+
+/// Uploads ROM and save, then configures save type, boot mode, TV type and CIC, returns summary for the JSON output
+fn deploy_rom(
+    sc64: &mut sc64::SC64,
+    deployment: &RomDeployment,
+    database: &n64::Database,
+) -> Result<Value, sc64::Error> {
+    let (rom_file, rom_name, rom_length) =
+        open_input_file(deployment.rom, deployment.entry, archive::FileKind::Rom)?;
+    let (mut rom_file, rom_length) = prepare_rom(
+        rom_file,
+        rom_length,
+        deployment.patch,
+        deployment.fix_crc,
+        deployment.cic_seed,
+    )?;
 
     log_wait(format!("Uploading ROM [{rom_name}]"), || {
-        sc64.upload_rom(&mut rom_file, rom_length, args.no_shadow)
+        sc64.upload_rom(&mut rom_file, rom_length, deployment.no_shadow)
     })?;
 
-    let save: SaveType = if let Some(save_type) = args.save_type.clone() {
-        save_type
-    } else {
-        let (save_type, title) = n64::guess_save_type(&mut rom_file)?;
-        if let Some(title) = title {
-            println!("ROM title: {title}");
-        };
-        save_type.into()
-    };
+    let detection = detect_rom_settings(&mut rom_file, database)?;
+    let settings = &detection.settings;
+    let direct = use_direct_boot(
+        deployment.direct,
+        deployment.no_direct,
+        deployment.tv.is_some(),
+        settings,
+    );
+    let cic_seed = deployment.cic_seed.or(settings.cic_seed);
+
+    let save: SaveType = deployment
+        .save_type
+        .clone()
+        .unwrap_or(detection.save_type().into());
     let save_type: sc64::SaveType = save.into();
-    println!("Save type set to [{save_type}]");
+    status!("Save type set to [{save_type}]");
+    let save_type_name = save_type.to_string();
     sc64.set_save_type(save_type)?;
 
-    if args.save.is_some() {
-        let (mut save_file, save_name, save_length) = open_file(&args.save.as_ref().unwrap())?;
-
-        log_wait(format!("Uploading save [{save_name}]"), || {
-            sc64.upload_save(&mut save_file, save_length)
-        })?;
+    let mut save_upload = None;
+    if let Some(save) = deployment.save {
+        let (mut save_file, save_name, _) = open_input_file(save, None, archive::FileKind::Save)?;
+        let mut data = vec![];
+        save_file.read_to_end(&mut data)?;
+        let save_format = deployment
+            .save_format
+            .clone()
+            .map(|format| format.into())
+            .unwrap_or(sc64::save::SaveFormat::detect(&data));
+        let save = sc64::save::import(&data, save_format, save_type)?;
+        let save_length = save.len();
+
+        log_wait(
+            format!("Uploading save [{save_name}] ({save_format})"),
+            || sc64.upload_save(&mut Cursor::new(save), save_length),
+        )?;
+
+        save_upload = Some(json!({
+            "name": save_name,
+            "length": save_length,
+            "format": save_format.to_string(),
+        }));
     }
 
-    let boot_mode = if args.direct {
+    let boot_mode = if direct {
         sc64::BootMode::DirectRom
     } else {
         sc64::BootMode::Rom
     };
-    println!("Boot mode set to [{boot_mode}]");
+    status!("Boot mode set to [{boot_mode}]");
+    let boot_mode_name = boot_mode.to_string();
     sc64.set_boot_mode(boot_mode)?;
 
-    if let Some(tv) = args.tv.clone() {
-        let tv_type: sc64::TvType = tv.into();
-        println!("TV type set to [{tv_type}]");
-        sc64.set_tv_type(tv_type)?;
-    }
-
-    sc64.calculate_cic_parameters(args.cic_seed)?;
-
-    if args.reboot && !sc64.try_notify_via_aux(sc64::AuxMessage::Reboot)? {
-        println!(
-            "{}",
-            "Warning: no response for [Reboot] AUX message".bright_yellow()
-        );
+    let tv = match &deployment.tv {
+        Some(tv) => Some(tv.resolve(&mut rom_file)?),
+        None if !direct => settings.tv_type,
+        None => None,
+    };
+    let mut tv_type = None;
+    if let Some(tv) = tv {
+        status!("TV type set to [{tv}]");
+        tv_type = Some(tv.to_string());
+        sc64.set_tv_type(tv)?;
     }
 
-    Ok(())
+    let cic = sc64.calculate_cic_parameters(cic_seed)?;
+    print_cic_detection(&cic, cic_seed);
+
+    Ok(json!({
+        "rom": {
+            "name": rom_name,
+            "length": rom_length,
+            "title": settings.title,
+            "md5": detection.md5,
+            "database_source": detection.source.to_string(),
+        },
+        "save": save_upload,
+        "save_type": save_type_name,
+        "boot_mode": boot_mode_name,
+        "tv_type": tv_type,
+        "cic": cic.as_ref().map(cic_json),
+    }))
 }
 
 fn handle_download_command(
@@ -519,25 +1218,60 @@ fn handle_download_command(
 
     match command {
         DownloadCommands::Save(args) => {
-            let (mut file, name) = create_file(&args.path)?;
+            let save_type = sc64.get_save_type()?;
+            let mut save = vec![];
 
-            log_wait(format!("Downloading save [{name}]"), || {
-                sc64.download_save(&mut file)
+            log_wait(format!("Downloading save [{save_type}]"), || {
+                sc64.download_save(&mut save)
             })?;
+
+            let save_format = args
+                .save_format
+                .clone()
+                .map_or(sc64::save::SaveFormat::Raw, |format| format.into());
+            let data = sc64::save::export(&save, save_format, save_type)?;
+
+            let (mut file, name) = create_file(&args.path)?;
+            file.write_all(&data)?;
+            status!("Save written to [{name}] ({save_format})");
+
+            if json_output() {
+                print_json(json!({
+                    "save": { "name": name, "format": save_format.to_string() }
+                }));
+            }
         }
     }
 
     Ok(())
 }
 
-fn handle_64dd_command(connection: Connection, args: &_64DDArgs) -> Result<(), sc64::Error> {
+fn handle_64dd_command(
+    connection: Connection,
+    args: &_64DDArgs,
+    database: &n64::Database,
+) -> Result<(), sc64::Error> {
     const MAX_ROM_LENGTH: usize = 32 * 1024 * 1024;
 
+    let sd_disks = if args.sd {
+        if args.disk.is_empty() {
+            return Err(sc64::Error::InvalidInput(
+                "At least one disk is required to serve disks from the SD card".into(),
+            ));
+        }
+        Some(copy_disks_to_sd_card(
+            init_sc64(connection.clone(), true)?,
+            args,
+        )?)
+    } else {
+        None
+    };
+
     let mut sc64 = init_sc64(connection, true)?;
 
     let mut debug_handler = debug::Handler::new();
 
-    println!(
+    status!(
         "{}\n{}\n{}\n{}",
         "========== [WARNING] ==========".bold().bright_yellow(),
         "Do not use this mode when real 64DD accessory is connected to the N64".bright_yellow(),
@@ -547,7 +1281,7 @@ fn handle_64dd_command(connection: Connection, args: &_64DDArgs) -> Result<(), s
     );
 
     if args.reboot && !sc64.try_notify_via_aux(sc64::AuxMessage::Halt)? {
-        println!(
+        status!(
             "{}",
             "Warning: no response for [Halt] AUX message".bright_yellow()
         );
@@ -555,85 +1289,224 @@ fn handle_64dd_command(connection: Connection, args: &_64DDArgs) -> Result<(), s
 
     sc64.reset_state()?;
 
+    let mut rom_settings = n64::GameSettings::default();
+    // Uploaded ROM (or DDIPL when there's no ROM) defines the region used by --tv auto
+    let mut boot_file: Option<Box<dyn ReadSeek>> = None;
+    let mut rom_upload = None;
+    let mut save_type_name = None;
+
     if let Some(rom) = &args.rom {
-        let (mut rom_file, rom_name, rom_length) = open_file(rom)?;
+        let (rom_file, rom_name, rom_length) =
+            open_input_file(rom, args.entry.as_deref(), archive::FileKind::Rom)?;
+        let (mut rom_file, rom_length) =
+            prepare_rom(rom_file, rom_length, &args.patch, false, None)?;
         if rom_length > MAX_ROM_LENGTH {
-            return Err(sc64::Error::new("ROM file size too big for 64DD mode"));
+            return Err(sc64::Error::InvalidInput(
+                "ROM file size too big for 64DD mode".into(),
+            ));
         }
         log_wait(format!("Uploading ROM [{rom_name}]"), || {
             sc64.upload_rom(&mut rom_file, rom_length, false)
         })?;
 
-        let save: SaveType = if let Some(save_type) = args.save_type.clone() {
-            save_type
-        } else {
-            let (save_type, title) = n64::guess_save_type(&mut rom_file)?;
-            if let Some(title) = title {
-                println!("ROM title: {title}");
-            };
-            save_type.into()
-        };
+        let detection = detect_rom_settings(&mut rom_file, database)?;
+        let save: SaveType = args
+            .save_type
+            .clone()
+            .unwrap_or(detection.save_type().into());
         let save_type: sc64::SaveType = save.into();
-        println!("Save type set to [{save_type}]");
+        status!("Save type set to [{save_type}]");
+        save_type_name = Some(save_type.to_string());
         sc64.set_save_type(save_type)?;
+        rom_upload = Some(json!({
+            "name": rom_name,
+            "length": rom_length,
+            "title": detection.settings.title,
+            "md5": detection.md5,
+            "database_source": detection.source.to_string(),
+        }));
+        rom_settings = detection.settings;
 
         if args.save.is_some() {
-            let (mut save_file, save_name, save_length) = open_file(&args.save.as_ref().unwrap())?;
+            let (mut save_file, save_name, save_length) =
+                open_input_file(args.save.as_ref().unwrap(), None, archive::FileKind::Save)?;
 
             log_wait(format!("Uploading save [{save_name}]"), || {
                 sc64.upload_save(&mut save_file, save_length)
             })?;
         }
+
+        boot_file = Some(rom_file);
     }
 
-    let (mut ddipl_file, ddipl_name, ddipl_length) = open_file(&args.ddipl)?;
+    let (mut ddipl_file, ddipl_name, ddipl_length) =
+        open_input_file(&args.ddipl, None, archive::FileKind::Rom)?;
 
     log_wait(format!("Uploading DDIPL [{ddipl_name}]"), || {
         sc64.upload_ddipl(&mut ddipl_file, ddipl_length)
     })?;
+    let mut boot_file = boot_file.unwrap_or(ddipl_file);
+
+    let direct = use_direct_boot(
+        args.direct,
+        args.no_direct,
+        args.tv.is_some(),
+        &rom_settings,
+    );
+    let cic_seed = args.cic_seed.or(rom_settings.cic_seed);
 
     let boot_mode = if args.rom.is_some() {
-        if args.direct {
+        if direct {
             sc64::BootMode::DirectRom
         } else {
             sc64::BootMode::Rom
         }
     } else {
-        if args.direct {
+        if direct {
             sc64::BootMode::DirectDdIpl
         } else {
             sc64::BootMode::DdIpl
         }
     };
-    println!("Boot mode set to [{boot_mode}]");
+    status!("Boot mode set to [{boot_mode}]");
+    let boot_mode_name = boot_mode.to_string();
     sc64.set_boot_mode(boot_mode)?;
 
-    if let Some(tv) = args.tv.clone() {
-        let tv_type: sc64::TvType = tv.into();
-        println!("TV type set to [{tv_type}]");
+    let tv_type = match &args.tv {
+        Some(tv) => Some(tv.resolve(&mut boot_file)?),
+        None if !direct => rom_settings.tv_type,
+        None => None,
+    };
+    let mut tv_type_name = None;
+    if let Some(tv_type) = tv_type {
+        status!("TV type set to [{tv_type}]");
+        tv_type_name = Some(tv_type.to_string());
         sc64.set_tv_type(tv_type)?;
     }
 
-    sc64.calculate_cic_parameters(args.cic_seed)?;
+    let cic = sc64.calculate_cic_parameters(cic_seed)?;
+    print_cic_detection(&cic, cic_seed);
+
+    // Serving disks over USB is interactive, JSON output is available only when the command finishes here
+    let summary = |dd_mode: String, drive_type: Option<String>, disks: Vec<Value>| {
+        json!({
+            "rom": rom_upload,
+            "ddipl": ddipl_name,
+            "save_type": save_type_name,
+            "boot_mode": boot_mode_name,
+            "tv_type": tv_type_name,
+            "cic": cic.as_ref().map(cic_json),
+            "dd_mode": dd_mode,
+            "drive_type": drive_type,
+            "disks": disks,
+        })
+    };
 
     if args.disk.len() == 0 {
         let dd_mode = sc64::DdMode::DdIpl;
-        println!("64DD mode set to [{dd_mode}]");
+        status!("64DD mode set to [{dd_mode}]");
+        let dd_mode_name = dd_mode.to_string();
         sc64.configure_64dd(dd_mode, None)?;
+        if json_output() {
+            print_json(summary(dd_mode_name, None, vec![]));
+        }
         return Ok(());
     }
 
-    let disk_paths: Vec<String> = args
-        .disk
-        .iter()
-        .map(|path| path.to_string_lossy().to_string())
-        .collect();
-    let disk_names: Vec<String> = args
-        .disk
-        .iter()
-        .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
-        .collect();
-    let mut disks = disk::open_multiple(&disk_paths)?;
+    if let Some((format, sd_disks, disks)) = sd_disks {
+        let drive_type = match format {
+            disk::Format::Retail => sc64::DdDriveType::Retail,
+            disk::Format::Development => sc64::DdDriveType::Development,
+        };
+        let dd_mode = sc64::DdMode::Full;
+        status!("64DD mode set to [{dd_mode} / {drive_type}], disks are served from the SD card");
+        let (dd_mode_name, drive_type_name) = (dd_mode.to_string(), drive_type.to_string());
+        sc64.configure_64dd_sd(drive_type, &sd_disks)?;
+        if sd_disks.len() > 1 {
+            status!(
+                "{}: {}",
+                "[64DD]".bold(),
+                "Press button on the back of SC64 device to cycle through provided disks"
+                    .bold()
+                    .bright_green()
+            );
+        }
+        if args.reboot && !sc64.try_notify_via_aux(sc64::AuxMessage::Reboot)? {
+            status!(
+                "{}",
+                "Warning: no response for [Reboot] AUX message".bright_yellow()
+            );
+        }
+        status!("SC64 can be disconnected from the PC now");
+        if json_output() {
+            print_json(summary(dd_mode_name, Some(drive_type_name), disks));
+        }
+        return Ok(());
+    }
+
+    check_disk_numbers("overlay", &args.overlay, args.disk.len())?;
+    check_disk_numbers("read-only", &args.read_only, args.disk.len())?;
+
+    let mut disks = vec![];
+    let mut disk_names = vec![];
+    for (index, path) in args.disk.iter().enumerate() {
+        let use_overlay =
+            is_disk_selected(&args.overlay, index) || (args.overlay.is_none() && args.volatile);
+        let read_only = is_disk_selected(&args.read_only, index);
+        if let Some((name, data)) = archive::extract(path, None, archive::FileKind::Disk)? {
+            println!(
+                "{}",
+                format!("Warning: disk [{name}] is loaded from an archive, changes won't be saved")
+                    .bright_yellow()
+            );
+            let mut disk = disk::open_memory(data)?;
+            disk.set_read_only(read_only);
+            disks.push(disk);
+            disk_names.push(name);
+        } else {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            let path = path.to_string_lossy().to_string();
+            let mut disk = if read_only {
+                disk::open_read_only(&path)?
+            } else {
+                match disk::open(&path) {
+                    Err(sc64::Error::Io(error))
+                        if error.kind() == std::io::ErrorKind::PermissionDenied
+                            && args.honor_permissions =>
+                    {
+                        println!(
+                            "{}",
+                            format!("Warning: disk [{name}] file is not writable").bright_yellow()
+                        );
+                        disk::open_read_only(&path)?
+                    }
+                    result => result?,
+                }
+            };
+            if disk.is_read_only() {
+                println!("Disk [{name}] is write protected");
+            } else if use_overlay && args.volatile {
+                disk.set_overlay(disk::Overlay::volatile())?;
+                println!("Disk [{name}] changes are kept only in memory");
+            } else if use_overlay {
+                let overlay_path = disk::overlay_path(&path);
+                disk.set_overlay(disk::Overlay::open(&overlay_path)?)?;
+                println!(
+                    "Disk [{name}] changes are written to the overlay [{overlay_path}] ({} blocks already stored)",
+                    disk.get_overlay().map_or(0, |overlay| overlay.len())
+                );
+            }
+            disks.push(disk);
+            disk_names.push(name);
+        }
+    }
+    disk::check_formats(&disks)?;
+
+    let mut disk_script = args
+        .disk_script
+        .as_ref()
+        .map(|path| disk_changer::DiskScript::load(path, args.disk.len()))
+        .transpose()?;
 
     let drive_type = match disks[0].get_format() {
         disk::Format::Retail => sc64::DdDriveType::Retail,
@@ -651,15 +1524,18 @@ fn handle_64dd_command(connection: Connection, args: &_64DDArgs) -> Result<(), s
             .bold()
             .bright_green()
     );
-
-    let mut selected_disk_index: usize = 0;
-    let mut selected_disk = Some(&mut disks[selected_disk_index]);
     println!(
-        "{}: Disk inserted [{}]",
+        "{}: {}",
         "[64DD]".bold(),
-        disk_names[selected_disk_index].bright_green()
+        "Type /eject, /insert <number> or /next to change disks from the terminal"
+            .bold()
+            .bright_green()
     );
-    sc64.set_64dd_disk_state(sc64::DdDiskState::Inserted)?;
+    debug_handler.enable_commands();
+
+    let mut disk_trace = disk_trace::DiskTrace::new(args.trace.as_deref())?;
+    let mut disk_changer = disk_changer::DiskChanger::new(disk_names.clone());
+    disk_changer.insert_first(&mut sc64)?;
 
     sc64.set_save_writeback(true)?;
 
@@ -672,13 +1548,40 @@ fn handle_64dd_command(connection: Connection, args: &_64DDArgs) -> Result<(), s
 
     let exit = setup_exit_flag();
     while !exit.load(Ordering::Relaxed) {
+        disk_changer.poll(&mut sc64)?;
+        if let Some(script) = disk_script.as_mut() {
+            if let Some(command) = script.next_command() {
+                disk_changer.execute(&mut sc64, command)?;
+                if script.is_finished() {
+                    println!("{}: Disk script finished", "[64DD]".bold());
+                }
+            }
+        }
         if let Some(data_packet) = sc64.receive_data_packet()? {
             match data_packet {
                 sc64::DataPacket::DiskRequest(mut disk_packet) => {
+                    let request_start = Instant::now();
                     let track = disk_packet.info.track;
                     let head = disk_packet.info.head;
                     let block = disk_packet.info.block;
-                    if let Some(ref mut disk) = selected_disk {
+                    let is_read = matches!(disk_packet.kind, sc64::DiskPacketKind::Read);
+                    let mut request = disk_trace::Request {
+                        disk: disk_changer.inserted(),
+                        is_read,
+                        track,
+                        head,
+                        block,
+                        lba: None,
+                        zone: None,
+                        success: false,
+                        latency: Duration::ZERO,
+                    };
+                    if let Some(index) = disk_changer.inserted() {
+                        disk_changer.disk_accessed(&mut sc64)?;
+                        let disk = &mut disks[index];
+                        request.lba = disk.get_lba(track, head, block);
+                        request.zone = disk.get_zone(track, head, block);
+                        let mut write_result = None;
                         let (reply_packet, rw) = match disk_packet.kind {
                             sc64::DiskPacketKind::Read => (
                                 disk.read_block(track, head, block)?.map(|data| {
@@ -687,56 +1590,60 @@ fn handle_64dd_command(connection: Connection, args: &_64DDArgs) -> Result<(), s
                                 }),
                                 "[R]".bright_blue(),
                             ),
-                            sc64::DiskPacketKind::Write => (
-                                disk.write_block(track, head, block, &disk_packet.info.data)?
-                                    .map(|_| disk_packet),
-                                "[W]".bright_yellow(),
-                            ),
+                            // Firmware has no write protect error reply yet, such writes fail like invalid blocks
+                            sc64::DiskPacketKind::Write => {
+                                write_result = Some(disk.write_block(
+                                    track,
+                                    head,
+                                    block,
+                                    &disk_packet.info.data,
+                                )?);
+                                (
+                                    (write_result == Some(disk::WriteResult::Written))
+                                        .then_some(disk_packet),
+                                    "[W]".bright_yellow(),
+                                )
+                            }
                         };
-                        let lba = if let Some(lba) = disk.get_lba(track, head, block) {
+                        let lba = if let Some(lba) = request.lba {
                             format!("{lba}")
                         } else {
                             "Invalid".to_string()
                         };
-                        let message = format!("{track:4}:{head}:{block} | LBA: {lba}");
+                        let mut message = format!("{track:4}:{head}:{block} | LBA: {lba}");
+                        if write_result == Some(disk::WriteResult::WriteProtected) {
+                            message.push_str(" | Write protected");
+                        }
                         if reply_packet.is_some() {
                             println!("{}: {} {}", "[64DD]".bold(), rw, message.green());
                         } else {
                             println!("{}: {} {}", "[64DD]".bold(), rw, message.red());
                         }
+                        if let (true, Some(script)) =
+                            (is_read && reply_packet.is_some(), disk_script.as_mut())
+                        {
+                            script.block_read();
+                        }
+                        request.success = reply_packet.is_some();
                         sc64.reply_disk_packet(reply_packet)?;
                     } else {
                         sc64.reply_disk_packet(None)?;
                     }
+                    request.latency = request_start.elapsed();
+                    disk_trace.record(&request)?;
                 }
                 sc64::DataPacket::Button => {
-                    if selected_disk.is_some() {
-                        sc64.set_64dd_disk_state(sc64::DdDiskState::Ejected)?;
-                        selected_disk = None;
-                        println!(
-                            "{}: Disk ejected [{}]",
-                            "[64DD]".bold(),
-                            disk_names[selected_disk_index].green()
-                        );
-                    } else {
-                        selected_disk_index += 1;
-                        if selected_disk_index >= disks.len() {
-                            selected_disk_index = 0;
-                        }
-                        selected_disk = Some(&mut disks[selected_disk_index]);
-                        println!(
-                            "{}: Disk inserted [{}]",
-                            "[64DD]".bold(),
-                            disk_names[selected_disk_index].bright_green()
-                        );
-                        sc64.set_64dd_disk_state(sc64::DdDiskState::Inserted)?;
-                    }
+                    disk_changer.button(&mut sc64)?;
                 }
                 sc64::DataPacket::DebugData(debug_packet) => {
                     debug_handler.handle_debug_packet(debug_packet);
                 }
                 sc64::DataPacket::SaveWriteback(save_writeback) => {
-                    debug_handler.handle_save_writeback(save_writeback, &args.save);
+                    let stored =
+                        !args.no_save_history && record_save_writeback(&mut sc64, &save_writeback);
+                    if args.save.is_some() || !stored {
+                        debug_handler.handle_save_writeback(save_writeback, &args.save);
+                    }
                 }
                 sc64::DataPacket::DataFlushed => {
                     debug_handler.handle_data_flushed();
@@ -746,25 +1653,179 @@ fn handle_64dd_command(connection: Connection, args: &_64DDArgs) -> Result<(), s
         } else if let Some(user_input) = debug_handler.process_user_input() {
             match user_input {
                 debug::UserInput::Packet(debug_packet) => sc64.send_debug_packet(debug_packet)?,
+                debug::UserInput::Command(command) => {
+                    match disk_changer::DiskCommand::parse(&command) {
+                        Ok(command) => disk_changer.execute(&mut sc64, command)?,
+                        Err(error) => println!("{}: {}", "[64DD]".bold(), error.bright_red()),
+                    }
+                }
                 debug::UserInput::EOF => break,
             }
         }
     }
 
+    disk_trace.flush()?;
+    disk_trace.print_summary(&disk_names);
+    if let Some(trace) = &args.trace {
+        println!("Disk requests trace saved to [{}]", trace.display());
+    }
+
     sc64.reset_state()?;
 
     Ok(())
 }
 
-fn handle_debug_command(connection: Connection, args: &DebugArgs) -> Result<(), sc64::Error> {
-    let mut sc64 = init_sc64(connection, true)?;
+/// Copies disks to the SD card (unless already present there) and returns their locations for the SD mode
+/// together with the summary of every disk for the JSON output
+fn copy_disks_to_sd_card(
+    mut sc64: sc64::SC64,
+    args: &_64DDArgs,
+) -> Result<(disk::Format, Vec<sc64::DdSdDisk>, Vec<Value>), sc64::Error> {
+    if args.disk.len() > sc64::DD_SD_MAX_DISKS {
+        return Err(sc64::Error::InvalidInput(format!(
+            "Up to {} disks can be served from the SD card",
+            sc64::DD_SD_MAX_DISKS
+        )));
+    }
+    check_disk_numbers("read-only", &args.read_only, args.disk.len())?;
+    sc64.check_64dd_sd_support()?;
+
+    let directory = args
+        .sd_dir
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .fold(PathBuf::from("/"), |path, component| path.join(component));
+
+    let mut disks = vec![];
+    let mut sd_paths: Vec<PathBuf> = vec![];
+    for (index, path) in args.disk.iter().enumerate() {
+        let (name, mut disk) =
+            if let Some((name, data)) = archive::extract(path, None, archive::FileKind::Disk)? {
+                (name, disk::open_memory(data)?)
+            } else {
+                let name = path.file_name().unwrap().to_string_lossy().to_string();
+                (name, disk::open_read_only(&path.to_string_lossy())?)
+            };
+        disk.set_read_only(is_disk_selected(&args.read_only, index));
+        // Disks extracted from archives are named after the archive entry, only the file name is used
+        let file_name = Path::new(&name).file_name().unwrap_or_default();
+        let sd_path =
+            directory.join(Path::new(file_name).with_extension(disk::ImageFormat::Ndd.extension()));
+        if sd_paths.contains(&sd_path) {
+            return Err(sc64::Error::InvalidInput(format!(
+                "More than one disk would be stored on the SD card as [{}]",
+                sd_path.display()
+            )));
+        }
+        sd_paths.push(sd_path);
+        disks.push((name, disk));
+    }
+    let (names, disks): (Vec<String>, Vec<disk::Disk>) = disks.into_iter().unzip();
+    disk::check_formats(&disks)?;
+    let format = *disks[0].get_format();
 
-    let mut debug_handler = debug::Handler::new();
+    match sc64.init_sd_card()? {
+        sc64::SdCardResult::OK => {}
+        error => return Err(sc64::Error::SdCard(error)),
+    }
 
-    if args.euc_jp {
-        debug_handler.set_text_encoding(debug::Encoding::EUCJP);
+    sc64.reset_state()?;
+
+    let mut ff = sc64::ff::FatFs::new(sc64)?;
+
+    for directory in directory
+        .ancestors()
+        .collect::<Vec<&Path>>()
+        .iter()
+        .rev()
+        .skip(1)
+    {
+        match ff.mkdir(directory) {
+            Ok(()) | Err(sc64::ff::Error::Exist) => {}
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    let mut sd_disks = vec![];
+    let mut summary = vec![];
+    for ((name, mut disk), sd_path) in names.into_iter().zip(disks).zip(sd_paths) {
+        let sd_path_name = sd_path.to_string_lossy();
+        let mut data = vec![];
+        disk.write_image(&mut data, disk::ImageFormat::Ndd)?;
+        let copy = match ff.stat(&sd_path) {
+            Ok(sc64::ff::Entry {
+                info: sc64::ff::EntryInfo::File { size },
+                ..
+            }) if size as usize == data.len() => {
+                // Only the RAM area can differ, it holds the progress saved on the SD card copy
+                let matches = log_wait(
+                    format!("Comparing disk [{name}] with the SD card copy [{sd_path_name}]"),
+                    || {
+                        let mut file = ff.open(&sd_path)?;
+                        let mut buffer = vec![];
+                        for range in disk.get_ndd_read_only_ranges() {
+                            buffer.resize(range.len(), 0);
+                            file.seek(SeekFrom::Start(range.start as u64))?;
+                            file.read_exact(&mut buffer)?;
+                            if buffer != data[range] {
+                                return Ok(false);
+                            }
+                        }
+                        Ok::<bool, sc64::Error>(true)
+                    },
+                )?;
+                if matches {
+                    status!(
+                        "Disk [{name}] found on the SD card [{sd_path_name}], using stored copy"
+                    );
+                } else if !args.sd_overwrite {
+                    return Err(sc64::Error::InvalidInput(format!(
+                        "Disk stored on the SD card [{sd_path_name}] doesn't match [{name}], use --sd-overwrite argument to replace it"
+                    )));
+                }
+                !matches
+            }
+            Ok(_) if args.sd_overwrite => true,
+            Ok(_) => {
+                return Err(sc64::Error::InvalidInput(format!(
+                    "Path [{sd_path_name}] on the SD card doesn't contain a valid disk image, use --sd-overwrite argument to replace it"
+                )));
+            }
+            Err(sc64::ff::Error::NoFile) => true,
+            Err(error) => return Err(error.into()),
+        };
+        if copy {
+            log_wait(
+                format!("Copying disk [{name}] to the SD card [{sd_path_name}]"),
+                || {
+                    let mut file = ff.create(&sd_path)?;
+                    file.write_all(&data)?;
+                    file.flush()?;
+                    Ok::<(), sc64::Error>(())
+                },
+            )?;
+        }
+        if disk.is_read_only() {
+            status!("Disk [{name}] is write protected");
+        }
+        sd_disks.push(sc64::DdSdDisk {
+            thb_table: disk.get_thb_table(),
+            sector_table: ff.open(&sd_path)?.sectors()?,
+        });
+        summary.push(json!({
+            "name": name,
+            "sd_path": sd_path_name,
+            "copied": copy,
+            "read_only": disk.is_read_only(),
+        }));
     }
 
+    Ok((format, sd_disks, summary))
+}
+
+fn handle_debug_command(connection: Connection, args: &DebugArgs) -> Result<(), sc64::Error> {
+    let mut sc64 = init_sc64(connection, true)?;
+
     if args.isv.is_some() {
         sc64.configure_is_viewer_64(args.isv)?;
         println!(
@@ -781,15 +1842,63 @@ fn handle_debug_command(connection: Connection, args: &DebugArgs) -> Result<(),
 
     println!("{}: Started", "[Debug]".bold());
 
-    if let Some(init) = args.init.clone() {
+    run_debug_loop(
+        &mut sc64,
+        &DebugSession {
+            euc_jp: args.euc_jp,
+            init: args.init.as_deref(),
+            save: &args.save,
+            no_save_history: args.no_save_history,
+        },
+        |_, _| Ok(()),
+    )?;
+
+    if !args.no_writeback {
+        sc64.set_save_writeback(false)?;
+    }
+    if args.isv.is_some() {
+        sc64.configure_is_viewer_64(None)?;
+        println!("{}: Stopped listening", "[IS-Viewer 64]".bold());
+    }
+
+    println!("{}: Stopped", "[Debug]".bold());
+
+    Ok(())
+}
+
+/// Debug terminal options shared by `debug` and `watch` commands
+struct DebugSession<'a> {
+    euc_jp: bool,
+    init: Option<&'a str>,
+    save: &'a Option<PathBuf>,
+    no_save_history: bool,
+}
+
+/// Prints debug data and IS-Viewer 64 messages and stores written back saves until Ctrl-C or end of the user input,
+/// `poll` runs before every packet check and is told whether a save was written back since its last call
+fn run_debug_loop<F: FnMut(&mut sc64::SC64, bool) -> Result<(), sc64::Error>>(
+    sc64: &mut sc64::SC64,
+    session: &DebugSession,
+    mut poll: F,
+) -> Result<(), sc64::Error> {
+    let mut debug_handler = debug::Handler::new();
+
+    if session.euc_jp {
+        debug_handler.set_text_encoding(debug::Encoding::EUCJP);
+    }
+
+    if let Some(init) = session.init {
         for command in init.split(";") {
             println!("{}: {}", "[Init]".bold(), command);
             debug_handler.send_external_input(command);
         }
     }
 
+    let mut save_written = false;
     let exit = setup_exit_flag();
     while !exit.load(Ordering::Relaxed) {
+        poll(sc64, save_written)?;
+        save_written = false;
         if let Some(data_packet) = sc64.receive_data_packet()? {
             match data_packet {
                 sc64::DataPacket::DebugData(debug_packet) => {
@@ -799,7 +1908,12 @@ fn handle_debug_command(connection: Connection, args: &DebugArgs) -> Result<(),
                     debug_handler.handle_is_viewer_64(&message);
                 }
                 sc64::DataPacket::SaveWriteback(save_writeback) => {
-                    debug_handler.handle_save_writeback(save_writeback, &args.save);
+                    let stored =
+                        !session.no_save_history && record_save_writeback(sc64, &save_writeback);
+                    if session.save.is_some() || !stored {
+                        debug_handler.handle_save_writeback(save_writeback, session.save);
+                    }
+                    save_written = true;
                 }
                 sc64::DataPacket::DataFlushed => {
                     debug_handler.handle_data_flushed();
@@ -809,24 +1923,162 @@ fn handle_debug_command(connection: Connection, args: &DebugArgs) -> Result<(),
         } else if let Some(user_input) = debug_handler.process_user_input() {
             match user_input {
                 debug::UserInput::Packet(debug_packet) => sc64.send_debug_packet(debug_packet)?,
+                debug::UserInput::Command(_) => {}
                 debug::UserInput::EOF => break,
             }
         }
     }
 
+    Ok(())
+}
+
+fn handle_watch_command(
+    connection: Connection,
+    args: &WatchArgs,
+    database: &n64::Database,
+) -> Result<(), sc64::Error> {
+    let mut sc64 = init_sc64(connection, true)?;
+
+    let watched_paths: Vec<&PathBuf> = [Some(&args.rom), args.save.as_ref(), args.ddipl.as_ref()]
+        .into_iter()
+        .flatten()
+        .chain(args.patch.iter())
+        .collect();
+    let mut modified_times = get_modified_times(&watched_paths);
+
+    deploy_watched_files(&mut sc64, args, database)?;
+
+    println!(
+        "{}: Watching for changes [{}]",
+        "[Watch]".bold(),
+        watched_paths
+            .iter()
+            .map(|path| path.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(", ")
+            .bright_blue()
+    );
+
+    let interval = Duration::from_millis(args.interval);
+    let mut last_check = Instant::now();
+
+    run_debug_loop(
+        &mut sc64,
+        &DebugSession {
+            euc_jp: args.euc_jp,
+            init: args.init.as_deref(),
+            save: &args.save,
+            no_save_history: args.no_save_history,
+        },
+        |sc64, save_written| {
+            // Written back save is not a change made by the build
+            if save_written {
+                modified_times = get_modified_times(&watched_paths);
+            }
+            if last_check.elapsed() < interval {
+                return Ok(());
+            }
+            last_check = Instant::now();
+            if get_modified_times(&watched_paths) != modified_times {
+                // Give the build a moment to finish writing before reading the files back
+                std::thread::sleep(interval);
+                modified_times = get_modified_times(&watched_paths);
+                println!("{}: Change detected, redeploying", "[Watch]".bold());
+                if let Err(error) = deploy_watched_files(sc64, args, database) {
+                    println!(
+                        "{}: Deploy failed: {}",
+                        "[Watch]".bold(),
+                        format_error(&error).bright_red()
+                    );
+                }
+            }
+            Ok(())
+        },
+    )?;
+
     if !args.no_writeback {
         sc64.set_save_writeback(false)?;
     }
     if args.isv.is_some() {
         sc64.configure_is_viewer_64(None)?;
-        println!("{}: Stopped listening", "[IS-Viewer 64]".bold());
     }
 
-    println!("{}: Stopped", "[Debug]".bold());
+    println!("{}: Stopped", "[Watch]".bold());
+
+    Ok(())
+}
+
+fn deploy_watched_files(
+    sc64: &mut sc64::SC64,
+    args: &WatchArgs,
+    database: &n64::Database,
+) -> Result<(), sc64::Error> {
+    if !sc64.try_notify_via_aux(sc64::AuxMessage::Halt)? {
+        status!(
+            "{}",
+            "Warning: no response for [Halt] AUX message".bright_yellow()
+        );
+    }
+
+    sc64.reset_state()?;
+
+    deploy_rom(
+        sc64,
+        &RomDeployment {
+            rom: &args.rom,
+            entry: args.entry.as_deref(),
+            patch: &args.patch,
+            fix_crc: args.fix_crc,
+            no_shadow: args.no_shadow,
+            // Save file is created by the save writeback when it doesn't exist yet
+            save: args.save.as_ref().filter(|path| path.exists()),
+            save_type: args.save_type.clone(),
+            save_format: args.save_format.clone(),
+            direct: args.direct,
+            no_direct: args.no_direct,
+            tv: args.tv.clone(),
+            cic_seed: args.cic_seed,
+        },
+        database,
+    )?;
+
+    if let Some(ddipl) = &args.ddipl {
+        let (mut ddipl_file, ddipl_name, ddipl_length) =
+            open_input_file(ddipl, None, archive::FileKind::Rom)?;
+
+        log_wait(format!("Uploading DDIPL [{ddipl_name}]"), || {
+            sc64.upload_ddipl(&mut ddipl_file, ddipl_length)
+        })?;
+
+        let dd_mode = sc64::DdMode::DdIpl;
+        status!("64DD mode set to [{dd_mode}]");
+        sc64.configure_64dd(dd_mode, None)?;
+    }
+
+    if args.isv.is_some() {
+        sc64.configure_is_viewer_64(args.isv)?;
+    }
+    if !args.no_writeback {
+        sc64.set_save_writeback(true)?;
+    }
+
+    if !sc64.try_notify_via_aux(sc64::AuxMessage::Reboot)? {
+        status!(
+            "{}",
+            "Warning: no response for [Reboot] AUX message".bright_yellow()
+        );
+    }
 
     Ok(())
 }
 
+fn get_modified_times(paths: &[&PathBuf]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
 fn handle_dump_command(connection: Connection, args: &DumpArgs) -> Result<(), sc64::Error> {
     let mut sc64 = init_sc64(connection, true)?;
 
@@ -840,6 +2092,14 @@ fn handle_dump_command(connection: Connection, args: &DumpArgs) -> Result<(), sc
         || sc64.dump_memory(&mut dump_file, args.address, args.length),
     )?;
 
+    if json_output() {
+        print_json(json!({
+            "address": args.address,
+            "length": args.length,
+            "name": dump_name,
+        }));
+    }
+
     Ok(())
 }
 
@@ -848,15 +2108,11 @@ fn handle_sd_command(connection: Connection, command: &SDCommands) -> Result<(),
 
     match sc64.init_sd_card()? {
         sc64::SdCardResult::OK => {}
-        error => {
-            return Err(sc64::Error::new(
-                format!("Couldn't init the SD card: {error}").as_str(),
-            ))
-        }
+        error => return Err(sc64::Error::SdCard(error)),
     }
 
     if sc64.is_console_powered_on()? {
-        println!(
+        status!(
             "{}\n{}\n{}",
             "========== [WARNING] ==========".bold().bright_yellow(),
             "The console is powered on. To avoid potential data corruption it's strongly"
@@ -867,7 +2123,10 @@ fn handle_sd_command(connection: Connection, command: &SDCommands) -> Result<(),
         let answer = prompt(format!("{}", "Continue anyways? [y/N] ".bold()));
         if answer.to_ascii_lowercase() != "y" {
             sc64.deinit_sd_card()?;
-            println!("{}", "SD card access aborted".red());
+            status!("{}", "SD card access aborted".red());
+            if json_output() {
+                print_json(json!({ "aborted": true }));
+            }
             return Ok(());
         }
     }
@@ -878,7 +2137,13 @@ fn handle_sd_command(connection: Connection, command: &SDCommands) -> Result<(),
 
     match command {
         SDCommands::List { path } => {
-            for item in ff.list(path.clone().unwrap_or(PathBuf::from("/")))? {
+            let entries = ff.list(path.clone().unwrap_or(PathBuf::from("/")))?;
+            if json_output() {
+                let entries: Vec<Value> = entries.iter().map(entry_json).collect();
+                print_json(json!({ "entries": entries }));
+                return Ok(());
+            }
+            for item in entries {
                 let sc64::ff::Entry {
                     info,
                     datetime,
@@ -888,42 +2153,56 @@ fn handle_sd_command(connection: Connection, command: &SDCommands) -> Result<(),
                     sc64::ff::EntryInfo::Directory => ("/".to_owned() + &name).bright_blue(),
                     sc64::ff::EntryInfo::File { size: _ } => name.bright_green(),
                 };
-                println!("{info} {datetime} | {}", name.bold());
+                status!("{info} {datetime} | {}", name.bold());
             }
         }
         SDCommands::Stat { path } => {
+            let entry = ff.stat(path)?;
+            if json_output() {
+                print_json(entry_json(&entry));
+                return Ok(());
+            }
             let sc64::ff::Entry {
                 info,
                 datetime,
                 name,
-            } = ff.stat(path)?;
+            } = entry;
             let name = match info {
                 sc64::ff::EntryInfo::Directory => ("/".to_owned() + &name).bright_blue(),
                 sc64::ff::EntryInfo::File { size: _ } => name.bright_green(),
             };
-            println!("{info} {datetime} | {}", name.bold());
+            status!("{info} {datetime} | {}", name.bold());
         }
         SDCommands::Move { src, dst } => {
             ff.rename(src, dst)?;
-            println!(
+            status!(
                 "Successfully moved {} to {}",
                 src.to_str().unwrap_or_default().bright_green(),
                 dst.to_str().unwrap_or_default().bright_green()
             );
+            if json_output() {
+                print_json(json!({ "moved": { "src": src, "dst": dst } }));
+            }
         }
         SDCommands::Delete { path } => {
             ff.delete(path)?;
-            println!(
+            status!(
                 "Successfully deleted {}",
                 path.to_str().unwrap_or_default().bright_green()
             );
+            if json_output() {
+                print_json(json!({ "deleted": path }));
+            }
         }
         SDCommands::CreateDirectory { path } => {
             ff.mkdir(path)?;
-            println!(
+            status!(
                 "Successfully created {}",
                 path.to_str().unwrap_or_default().bright_green()
             );
+            if json_output() {
+                print_json(json!({ "created": path }));
+            }
         }
         SDCommands::Download { src, dst } => {
             let dst = &dst.clone().unwrap_or(
@@ -951,6 +2230,9 @@ fn handle_sd_command(connection: Connection, command: &SDCommands) -> Result<(),
                     }
                 },
             )?;
+            if json_output() {
+                print_json(json!({ "downloaded": { "src": src, "dst": dst } }));
+            }
         }
         SDCommands::Upload { src, dst } => {
             let dst = &dst.clone().unwrap_or(
@@ -978,6 +2260,9 @@ fn handle_sd_command(connection: Connection, command: &SDCommands) -> Result<(),
                     }
                 },
             )?;
+            if json_output() {
+                print_json(json!({ "uploaded": { "src": src, "dst": dst } }));
+            }
         }
         SDCommands::Format => {
             let answer = prompt(format!(
@@ -985,10 +2270,16 @@ fn handle_sd_command(connection: Connection, command: &SDCommands) -> Result<(),
                 "Do you really want to format the SD card? [y/N] ".bold()
             ));
             if answer.to_ascii_lowercase() != "y" {
-                println!("{}", "Format operation aborted".red());
+                status!("{}", "Format operation aborted".red());
+                if json_output() {
+                    print_json(json!({ "aborted": true }));
+                }
                 return Ok(());
             }
             log_wait(format!("Formatting the SD card"), || ff.mkfs())?;
+            if json_output() {
+                print_json(json!({ "formatted": true }));
+            }
         }
     }
 
@@ -1001,34 +2292,88 @@ fn handle_info_command(connection: Connection) -> Result<(), sc64::Error> {
     let (major, minor, revision) = sc64.check_firmware_version()?;
     let state = sc64.get_device_state()?;
 
-    println!("{}", "SummerCart64 state information:".bold());
-    println!(" Firmware version:  v{}.{}.{}", major, minor, revision);
-    println!(" RTC datetime:      {}", state.datetime);
-    println!(" Boot mode:         {}", state.boot_mode);
-    println!(" Save type:         {}", state.save_type);
-    println!(" CIC seed:          {}", state.cic_seed);
-    println!(" TV type:           {}", state.tv_type);
-    println!(" Bootloader switch: {}", state.bootloader_switch);
-    println!(" ROM write:         {}", state.rom_write_enable);
-    println!(" ROM shadow:        {}", state.rom_shadow_enable);
-    println!(" ROM extended:      {}", state.rom_extended_enable);
-    println!(" 64DD mode:         {}", state.dd_mode);
-    println!(" 64DD SD card mode: {}", state.dd_sd_enable);
-    println!(" 64DD drive type:   {}", state.dd_drive_type);
-    println!(" 64DD disk state:   {}", state.dd_disk_state);
-    println!(" Button mode:       {}", state.button_mode);
-    println!(" Button state:      {}", state.button_state);
-    println!(" LED blink:         {}", state.led_enable);
-    println!(" IS-Viewer 64:      {}", state.isviewer);
-    println!(" SD card status:    {}", state.sd_card_status);
-    println!("{}", "SummerCart64 diagnostic information:".bold());
-    println!(" PI I/O access:     {}", state.fpga_debug_data.pi_io_access);
-    println!(
+    if json_output() {
+        let diagnostic_data = match &state.diagnostic_data {
+            sc64::DiagnosticData::V0(d) => json!({
+                "version": 0,
+                "cic": d.cic,
+                "rtc": d.rtc,
+                "led": d.led,
+                "gvr": d.gvr,
+            }),
+            sc64::DiagnosticData::V1(d) => json!({
+                "version": 1,
+                "voltage": d.voltage,
+                "temperature": d.temperature,
+            }),
+            sc64::DiagnosticData::Unknown => Value::Null,
+        };
+        let pi_fifo_flags = &state.fpga_debug_data.pi_fifo_flags;
+        print_json(json!({
+            "firmware_version": { "major": major, "minor": minor, "revision": revision },
+            "state": {
+                "datetime": state.datetime.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                "boot_mode": state.boot_mode.to_string(),
+                "save_type": state.save_type.to_string(),
+                "cic_seed": state.cic_seed.to_string(),
+                "tv_type": state.tv_type.to_string(),
+                "bootloader_switch": state.bootloader_switch.to_string(),
+                "rom_write_enable": state.rom_write_enable.to_string(),
+                "rom_shadow_enable": state.rom_shadow_enable.to_string(),
+                "rom_extended_enable": state.rom_extended_enable.to_string(),
+                "dd_mode": state.dd_mode.to_string(),
+                "dd_sd_enable": state.dd_sd_enable.to_string(),
+                "dd_drive_type": state.dd_drive_type.to_string(),
+                "dd_disk_state": state.dd_disk_state.to_string(),
+                "button_mode": state.button_mode.to_string(),
+                "button_state": state.button_state.to_string(),
+                "led_enable": state.led_enable.to_string(),
+                "isviewer": state.isviewer.to_string(),
+                "sd_card_status": state.sd_card_status.to_string(),
+            },
+            "diagnostics": {
+                "pi_io_access": state.fpga_debug_data.pi_io_access.to_string(),
+                "pi_fifo_flags": {
+                    "read_fifo_wait": pi_fifo_flags.read_fifo_wait,
+                    "read_fifo_failure": pi_fifo_flags.read_fifo_failure,
+                    "write_fifo_wait": pi_fifo_flags.write_fifo_wait,
+                    "write_fifo_failure": pi_fifo_flags.write_fifo_failure,
+                },
+                "cic_step": state.fpga_debug_data.cic_step.to_string(),
+                "diagnostic_data": diagnostic_data,
+            },
+        }));
+        return Ok(());
+    }
+
+    status!("{}", "SummerCart64 state information:".bold());
+    status!(" Firmware version:  v{}.{}.{}", major, minor, revision);
+    status!(" RTC datetime:      {}", state.datetime);
+    status!(" Boot mode:         {}", state.boot_mode);
+    status!(" Save type:         {}", state.save_type);
+    status!(" CIC seed:          {}", state.cic_seed);
+    status!(" TV type:           {}", state.tv_type);
+    status!(" Bootloader switch: {}", state.bootloader_switch);
+    status!(" ROM write:         {}", state.rom_write_enable);
+    status!(" ROM shadow:        {}", state.rom_shadow_enable);
+    status!(" ROM extended:      {}", state.rom_extended_enable);
+    status!(" 64DD mode:         {}", state.dd_mode);
+    status!(" 64DD SD card mode: {}", state.dd_sd_enable);
+    status!(" 64DD drive type:   {}", state.dd_drive_type);
+    status!(" 64DD disk state:   {}", state.dd_disk_state);
+    status!(" Button mode:       {}", state.button_mode);
+    status!(" Button state:      {}", state.button_state);
+    status!(" LED blink:         {}", state.led_enable);
+    status!(" IS-Viewer 64:      {}", state.isviewer);
+    status!(" SD card status:    {}", state.sd_card_status);
+    status!("{}", "SummerCart64 diagnostic information:".bold());
+    status!(" PI I/O access:     {}", state.fpga_debug_data.pi_io_access);
+    status!(
         " PI FIFO flags:     {}",
         state.fpga_debug_data.pi_fifo_flags
     );
-    println!(" Current CIC step:  {}", state.fpga_debug_data.cic_step);
-    println!(" Diagnostic data:   {}", state.diagnostic_data);
+    status!(" Current CIC step:  {}", state.fpga_debug_data.cic_step);
+    status!(" Diagnostic data:   {}", state.diagnostic_data);
 
     Ok(())
 }
@@ -1038,7 +2383,11 @@ fn handle_reset_command(connection: Connection) -> Result<(), sc64::Error> {
 
     sc64.reset_state()?;
 
-    println!("SC64 state has been reset");
+    status!("SC64 state has been reset");
+
+    if json_output() {
+        print_json(json!({ "reset": true }));
+    }
 
     Ok(())
 }
@@ -1050,23 +2399,34 @@ fn handle_set_command(connection: Connection, command: &SetCommands) -> Result<(
         SetCommands::Rtc => {
             let datetime = Local::now().naive_local();
             sc64.set_datetime(datetime)?;
-            println!(
+            status!(
                 "SC64 RTC datetime synchronized to: {}",
                 datetime.format("%Y-%m-%d %H:%M:%S").to_string().green()
             );
+            if json_output() {
+                print_json(json!({
+                    "datetime": datetime.format("%Y-%m-%dT%H:%M:%S").to_string()
+                }));
+            }
         }
 
         SetCommands::BlinkOn => {
             sc64.set_led_blink(true)?;
-            println!(
+            status!(
                 "SC64 LED I/O activity blinking set to {}",
                 "enabled".green()
             );
+            if json_output() {
+                print_json(json!({ "led_blink": true }));
+            }
         }
 
         SetCommands::BlinkOff => {
             sc64.set_led_blink(false)?;
-            println!("SC64 LED I/O activity blinking set to {}", "disabled".red());
+            status!("SC64 LED I/O activity blinking set to {}", "disabled".red());
+            if json_output() {
+                print_json(json!({ "led_blink": false }));
+            }
         }
     }
 
@@ -1085,8 +2445,12 @@ fn handle_firmware_command(
             firmware_file.read_exact(&mut firmware)?;
 
             let metadata = sc64::firmware::verify(&firmware)?;
-            println!("{}", "Firmware metadata:".bold());
-            println!("{}", format!("{}", metadata).bright_blue().to_string());
+            if json_output() {
+                print_json(json!({ "metadata": firmware_json(&metadata) }));
+                return Ok(());
+            }
+            status!("{}", "Firmware metadata:".bold());
+            status!("{}", format!("{}", metadata).bright_blue().to_string());
 
             Ok(())
         }
@@ -1102,11 +2466,18 @@ fn handle_firmware_command(
             )?;
 
             let metadata = sc64::firmware::verify(&firmware)?;
-            println!("{}", "Firmware metadata:".bold());
-            println!("{}", format!("{}", metadata).bright_blue().to_string());
+            status!("{}", "Firmware metadata:".bold());
+            status!("{}", format!("{}", metadata).bright_blue().to_string());
 
             backup_file.write_all(&firmware)?;
 
+            if json_output() {
+                print_json(json!({
+                    "name": backup_name,
+                    "metadata": firmware_json(&metadata),
+                }));
+            }
+
             Ok(())
         }
 
@@ -1119,21 +2490,24 @@ fn handle_firmware_command(
             update_file.read_exact(&mut firmware)?;
 
             let metadata = sc64::firmware::verify(&firmware)?;
-            println!("{}", "Firmware metadata:".bold());
-            println!("{}", format!("{}", metadata).bright_blue().to_string());
-            println!("{}", "Firmware file verification was successful".green());
+            status!("{}", "Firmware metadata:".bold());
+            status!("{}", format!("{}", metadata).bright_blue().to_string());
+            status!("{}", "Firmware file verification was successful".green());
             if args.use_flash_memory {
-                println!(
+                status!(
                     "{}",
                     "Warning: using Flash memory to perform firmware update".yellow()
                 );
             }
             let answer = prompt(format!("{}", "Continue with update process? [y/N] ".bold()));
             if answer.to_ascii_lowercase() != "y" {
-                println!("{}", "Firmware update process aborted".red());
+                status!("{}", "Firmware update process aborted".red());
+                if json_output() {
+                    print_json(json!({ "aborted": true }));
+                }
                 return Ok(());
             }
-            println!(
+            status!(
                 "{}",
                 "Do not unplug SC64 from the computer, doing so might brick your device".yellow()
             );
@@ -1143,38 +2517,1101 @@ fn handle_firmware_command(
                 || sc64.update_firmware(&firmware, args.use_flash_memory),
             )?;
 
+            if json_output() {
+                print_json(json!({
+                    "name": update_name,
+                    "metadata": firmware_json(&metadata),
+                    "updated": true,
+                }));
+            }
+
             Ok(())
         }
     }
 }
 
+fn handle_rom_command(command: &RomCommands, database: &n64::Database) -> Result<(), sc64::Error> {
+    match command {
+        RomCommands::Info(args) => {
+            let (mut rom_file, rom_name, rom_length) = open_file(&args.rom)?;
+
+            let (header, ipl3) = sc64::rom::read_boot(&mut rom_file)?;
+            let detection = n64::identify(&mut rom_file, database)?;
+            let settings = &detection.settings;
+            let mut checksum_data = vec![];
+            rom_file.rewind()?;
+            (&mut rom_file)
+                .take(sc64::rom::CHECKSUM_DATA_LENGTH as u64)
+                .read_to_end(&mut checksum_data)?;
+            let mut cic = sc64::cic::sign_ipl3(&ipl3, settings.cic_seed)?;
+            if cic.variant.is_none() && settings.cic_seed.is_none() {
+                if let Ok(Some(seed)) = sc64::rom::find_checksum_seed(&checksum_data) {
+                    cic = sc64::cic::sign_ipl3(&ipl3, Some(seed))?;
+                    cic.seed_found = true;
+                }
+            }
+            let cic_seed = settings.cic_seed.or(cic.seed_found.then_some(cic.seed));
+            let checksum = sc64::rom::calculate_checksum(&checksum_data, cic_seed);
+            let save: SaveType = detection.save_type().into();
+            let save_type: sc64::SaveType = save.into();
+
+            if json_output() {
+                print_json(json!({
+                    "name": rom_name,
+                    "length": rom_length,
+                    "byte_order": header.byte_order.to_string(),
+                    "header": {
+                        "pi_config": header.pi_config,
+                        "clock_rate": header.clock_rate,
+                        "entry_point": header.entry_point,
+                        "libultra_version": header.libultra_version.to_string(),
+                        "crc1": header.crc1,
+                        "crc2": header.crc2,
+                        "name": header.name,
+                        "game_code": header.game_code(),
+                        "media_type": header.media_type_name(),
+                        "region": header.region_name(),
+                        "tv_type": header.tv_type().to_string(),
+                        "version": header.version,
+                    },
+                    "cic": cic_json(&cic),
+                    "checksum": match &checksum {
+                        Ok(checksum) => json!({
+                            "valid": checksum.is_valid(),
+                            "calculated": [checksum.calculated.0, checksum.calculated.1],
+                        }),
+                        Err(error) => json!({ "error": error.to_string() }),
+                    },
+                    "md5": detection.md5,
+                    "save_type": save_type.to_string(),
+                    "database_title": settings.title,
+                    "database_source": detection.source.to_string(),
+                    "database_tv_type": settings.tv_type.map(|tv| tv.to_string()),
+                    "database_cic_seed": settings.cic_seed,
+                    "database_direct_boot": settings.direct_boot,
+                    "homebrew_header": detection.homebrew.map(|homebrew| json!({
+                        "save_type": homebrew.save_type.map(|save_type| {
+                            sc64::SaveType::from(SaveType::from(save_type)).to_string()
+                        }),
+                        "rtc": homebrew.rtc,
+                        "region_free": homebrew.region_free,
+                    })),
+                }));
+                return Ok(());
+            }
+
+            println!("{}", format!("ROM information [{rom_name}]:").bold());
+            println!(" File size:         {rom_length} bytes");
+            println!(" Byte order:        {}", header.byte_order);
+            println!(" Internal name:     {}", header.name.bright_green());
+            println!(
+                " Game code:         {} ({} / {})",
+                header.game_code(),
+                header.media_type_name(),
+                header.region_name()
+            );
+            println!(" TV type:           {}", header.tv_type());
+            println!(" Version:           {}", header.version);
+            println!(" PI config:         0x{:08X}", header.pi_config);
+            println!(" Clock rate:        0x{:08X}", header.clock_rate);
+            println!(" Entry point:       0x{:08X}", header.entry_point);
+            println!(" libultra version:  {}", header.libultra_version);
+            println!(
+                " CRC1 / CRC2:       0x{:08X} / 0x{:08X} ({})",
+                header.crc1,
+                header.crc2,
+                match &checksum {
+                    Ok(checksum) if checksum.is_valid() => "valid".bright_green(),
+                    Ok(checksum) => format!(
+                        "invalid, expected 0x{:08X} / 0x{:08X}",
+                        checksum.calculated.0, checksum.calculated.1
+                    )
+                    .bright_red(),
+                    Err(error) => format!("not checked: {error}").bright_yellow(),
+                }
+            );
+            match cic.variant {
+                Some(variant) => println!(
+                    " CIC:               {} (seed 0x{:02X}, IPL3 checksum 0x{:012X})",
+                    variant.to_string().bright_green(),
+                    cic.seed,
+                    cic.checksum
+                ),
+                None if cic.seed_found => println!(
+                    " CIC:               {} (seed 0x{:02X} matches the header checksum)",
+                    "Unknown IPL3".bright_yellow(),
+                    cic.seed
+                ),
+                None => println!(
+                    " CIC:               {} (fallback seed 0x{:02X})",
+                    "Unknown IPL3".bright_yellow(),
+                    cic.seed
+                ),
+            }
+            println!(" MD5:               {}", detection.md5);
+            println!(" Save type:         {save_type}");
+            match &settings.title {
+                Some(title) => println!(" Database match:    {}", title.bright_green()),
+                None => println!(" Database match:    None"),
+            }
+            println!(" Detected from:     {}", detection.source);
+            if let Some(tv_type) = settings.tv_type {
+                println!(" Database TV type:  {tv_type}");
+            }
+            if let Some(cic_seed) = settings.cic_seed {
+                println!(" Database CIC seed: 0x{cic_seed:02X}");
+            }
+            if let Some(direct_boot) = settings.direct_boot {
+                println!(
+                    " Direct boot:       {}",
+                    if direct_boot { "Yes" } else { "No" }
+                );
+            }
+            if let Some(homebrew) = &detection.homebrew {
+                println!(
+                    " Homebrew header:   {}",
+                    homebrew_header_description(homebrew)
+                );
+            }
+
+            Ok(())
+        }
+
+        RomCommands::FixCrc(args) => {
+            let (mut rom_file, rom_name, _) = open_file(&args.rom)?;
+
+            let mut rom = vec![];
+            rom_file.read_to_end(&mut rom)?;
+            drop(rom_file);
+
+            let checksum = sc64::rom::fix_checksum(&mut rom, args.cic_seed)?;
+            print_checksum(&checksum);
+
+            let output = args.output.as_ref().unwrap_or(&args.rom);
+            if !checksum.is_valid() || args.output.is_some() {
+                let (mut output_file, output_name) = create_file(output)?;
+                output_file.write_all(&rom)?;
+                status!("Fixed ROM written to [{output_name}]");
+            }
+
+            if json_output() {
+                print_json(json!({
+                    "name": rom_name,
+                    "seed": checksum.seed,
+                    "header": [checksum.header.0, checksum.header.1],
+                    "calculated": [checksum.calculated.0, checksum.calculated.1],
+                    "fixed": !checksum.is_valid(),
+                }));
+            }
+
+            Ok(())
+        }
+    }
+}
+
+fn handle_save_command(connection: Connection, command: &SaveCommands) -> Result<(), sc64::Error> {
+    match command {
+        SaveCommands::Convert(args) => {
+            let (mut input_file, input_name, input_length) = open_file(&args.input)?;
+            let mut data = vec![];
+            input_file.read_to_end(&mut data)?;
+            drop(input_file);
+
+            let from = args
+                .from
+                .clone()
+                .map(|format| format.into())
+                .unwrap_or(sc64::save::SaveFormat::detect(&data));
+            let to: sc64::save::SaveFormat = args.to.clone().into();
+
+            let save_type: sc64::SaveType = match &args.save_type {
+                Some(save_type) => save_type.clone().into(),
+                None if from == sc64::save::SaveFormat::Mupen64Plus => {
+                    return Err(sc64::Error::InvalidInput(
+                        "Save type must be provided when converting from mupen64plus SRM file"
+                            .into(),
+                    ))
+                }
+                None => sc64::save::detect_save_type(input_length).ok_or(
+                    sc64::Error::InvalidInput(format!(
+                        "Couldn't detect save type from file size (0x{input_length:X} bytes), provide it with --save-type argument"
+                    )),
+                )?,
+            };
+
+            let save = sc64::save::import(&data, from, save_type)?;
+            let converted = sc64::save::export(&save, to, save_type)?;
+
+            let output = match &args.output {
+                Some(output) => output.clone(),
+                None => {
+                    let output = args.input.with_extension(to.extension(save_type));
+                    if output == args.input {
+                        return Err(sc64::Error::InvalidInput(
+                            "Output file would overwrite the input file, provide output path explicitly".into(),
+                        ));
+                    }
+                    output
+                }
+            };
+            let (mut output_file, output_name) = create_file(&output)?;
+            output_file.write_all(&converted)?;
+
+            status!("Save type: {save_type}");
+            status!("Converted [{input_name}] ({from}) to [{output_name}] ({to})");
+
+            if json_output() {
+                print_json(json!({
+                    "input": { "name": input_name, "format": from.to_string() },
+                    "output": { "name": output_name, "format": to.to_string() },
+                    "save_type": save_type.to_string(),
+                }));
+            }
+
+            Ok(())
+        }
+        SaveCommands::History(args) => {
+            let Some(rom) = &args.rom else {
+                let indexes = store::SaveStore::list()?;
+                if json_output() {
+                    print_json(json!(indexes
+                        .iter()
+                        .map(|index| json!({
+                            "title": index.title,
+                            "game_code": index.game_code,
+                            "version": index.version,
+                            "crc1": index.crc1,
+                            "crc2": index.crc2,
+                            "entries": index.entries.len(),
+                        }))
+                        .collect::<Vec<Value>>()));
+                } else if indexes.is_empty() {
+                    println!("Save history is empty");
+                } else {
+                    for index in indexes {
+                        println!(
+                            "{} [{}] v{} [0x{:08X} / 0x{:08X}]: {} save(s), last at {}",
+                            index.title.bold(),
+                            index.game_code,
+                            index.version,
+                            index.crc1,
+                            index.crc2,
+                            index.entries.len(),
+                            index.entries.last().map_or("-", |entry| &entry.timestamp)
+                        );
+                    }
+                }
+                return Ok(());
+            };
+
+            let save_store = open_rom_save_store(rom)?;
+            let entries: Vec<&store::Entry> = save_store.entries().collect();
+            if json_output() {
+                print_json(json!(entries
+                    .iter()
+                    .enumerate()
+                    .map(|(number, entry)| json!({
+                        "entry": number,
+                        "timestamp": entry.timestamp,
+                        "save_type": save_type_name(entry.save_type),
+                        "length": entry.length,
+                        "md5": entry.hash,
+                    }))
+                    .collect::<Vec<Value>>()));
+            } else if entries.is_empty() {
+                println!(
+                    "No saves recorded in [{}]",
+                    save_store.directory().display()
+                );
+            } else {
+                println!("Saves recorded in [{}]:", save_store.directory().display());
+                for (number, entry) in entries.iter().enumerate() {
+                    println!(
+                        " {number:3}: {} | {} | {}",
+                        entry.timestamp,
+                        save_type_name(entry.save_type),
+                        entry.hash
+                    );
+                }
+            }
+
+            Ok(())
+        }
+        SaveCommands::Restore(args) => {
+            let save_store = open_rom_save_store(&args.rom)?;
+            let (save_type, save) = save_store.read(args.entry)?;
+
+            if let Some(output) = &args.output {
+                let save_format = args
+                    .save_format
+                    .clone()
+                    .map_or(sc64::save::SaveFormat::Raw, |format| format.into());
+                let data = sc64::save::export(&save, save_format, save_type)?;
+                let (mut file, name) = create_file(output)?;
+                file.write_all(&data)?;
+                status!("Save [{}] written to [{name}] ({save_format})", args.entry);
+            } else {
+                let mut sc64 = init_sc64(connection, true)?;
+                status!("Save type set to [{save_type}]");
+                sc64.set_save_type(save_type)?;
+                log_wait(format!("Restoring save [{}]", args.entry), || {
+                    sc64.upload_save(&mut Cursor::new(&save), save.len())
+                })?;
+            }
+
+            if json_output() {
+                print_json(json!({
+                    "entry": args.entry,
+                    "save_type": save_type.to_string(),
+                    "output": args.output.as_ref().map(|path| path.to_string_lossy()),
+                }));
+            }
+
+            Ok(())
+        }
+        SaveCommands::Diff(args) => {
+            let save_store = match &args.rom {
+                Some(rom) => Some(open_rom_save_store(rom)?),
+                None => None,
+            };
+            let (a_name, a, a_save_type) = load_save_for_diff(&args.a, save_store.as_ref())?;
+            let (b_name, b, b_save_type) = load_save_for_diff(&args.b, save_store.as_ref())?;
+
+            let save_type = args
+                .save_type
+                .clone()
+                .map(|save_type| save_type.into())
+                .or(a_save_type)
+                .or(b_save_type)
+                .or(sc64::save::detect_save_type(a.len()));
+
+            let regions = diff_regions(&a, &b);
+            let changed: usize = regions.iter().map(|(_, length)| length).sum();
+
+            if json_output() {
+                let unit_length = save_type
+                    .and_then(sc64::save::layout)
+                    .map(|(_, length)| length);
+                print_json(json!({
+                    "a": { "name": a_name, "length": a.len() },
+                    "b": { "name": b_name, "length": b.len() },
+                    "save_type": save_type.map(|save_type| save_type.to_string()),
+                    "changed": changed,
+                    "regions": regions
+                        .iter()
+                        .map(|(offset, length)| json!({
+                            "offset": offset,
+                            "length": length,
+                            "unit": unit_length.map(|unit_length| offset / unit_length),
+                        }))
+                        .collect::<Vec<Value>>(),
+                }));
+            } else {
+                match save_type {
+                    Some(save_type) => {
+                        println!("Comparing [{a_name}] with [{b_name}] ({save_type})")
+                    }
+                    None => println!("Comparing [{a_name}] with [{b_name}]"),
+                }
+                if a.len() != b.len() {
+                    println!(
+                        "{}",
+                        format!("Sizes differ (0x{:X} / 0x{:X} bytes)", a.len(), b.len())
+                            .bright_yellow()
+                    );
+                }
+                print_save_diff(&a, &b, save_type);
+            }
+
+            Ok(())
+        }
+        SaveCommands::Watch(args) => {
+            let mut sc64 = init_sc64(connection, true)?;
+
+            let save_type = sc64.get_save_type()?;
+            let mut previous = vec![];
+            sc64.download_save(&mut previous)?;
+
+            println!(
+                "{}: Watching [{save_type}] save, press Ctrl-C to stop",
+                "[Save]".bold()
+            );
+
+            let interval = Duration::from_millis(args.interval);
+            let exit = setup_exit_flag();
+            while !exit.load(Ordering::Relaxed) {
+                std::thread::sleep(interval);
+                let mut current = vec![];
+                sc64.download_save(&mut current)?;
+                if current != previous {
+                    println!(
+                        "{}: Save changed at {}",
+                        "[Save]".bold(),
+                        Local::now().format("%H:%M:%S%.3f")
+                    );
+                    print_save_diff(&previous, &current, Some(save_type));
+                    previous = current;
+                }
+            }
+
+            Ok(())
+        }
+    }
+}
+
+fn print_save_diff(a: &[u8], b: &[u8], save_type: Option<sc64::SaveType>) {
+    let regions = diff_regions(a, b);
+    if regions.is_empty() {
+        println!("{}", "Saves are identical".bright_green());
+        return;
+    }
+
+    let layout = save_type.and_then(sc64::save::layout);
+    let line_length = match layout {
+        Some((_, unit_length)) if unit_length < 16 => unit_length,
+        _ => 16,
+    };
+    let format_line = |data: &[u8], other: &[u8], offset: usize, changed: fn(String) -> String| {
+        (offset..(offset + line_length))
+            .map(|offset| match data.get(offset) {
+                Some(byte) if other.get(offset) != Some(byte) => changed(format!("{byte:02X}")),
+                Some(byte) => format!("{byte:02X}"),
+                None => "--".to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    };
+
+    let mut units = vec![];
+    let mut lines = vec![];
+    for (offset, length) in regions.iter() {
+        let first_line = offset / line_length;
+        let last_line = (offset + length - 1) / line_length;
+        for line in first_line..=last_line {
+            if lines.last() != Some(&line) {
+                lines.push(line);
+            }
+        }
+    }
+    for line in lines {
+        let offset = line * line_length;
+        if let Some((unit_name, unit_length)) = layout {
+            let unit = offset / unit_length;
+            if units.last() != Some(&unit) {
+                units.push(unit);
+                println!(
+                    "{}",
+                    format!("{unit_name} {unit} (0x{:06X})", unit * unit_length).bold()
+                );
+            }
+        }
+        println!(
+            " 0x{offset:06X} - {}",
+            format_line(a, b, offset, |byte| byte.bright_red().to_string())
+        );
+        println!(
+            "          + {}",
+            format_line(b, a, offset, |byte| byte.bright_green().to_string())
+        );
+    }
+
+    let changed: usize = regions.iter().map(|(_, length)| length).sum();
+    match layout {
+        Some((unit_name, _)) => println!(
+            "{changed} bytes changed in {} region(s), {} {}(s) affected",
+            regions.len(),
+            units.len(),
+            unit_name.to_lowercase()
+        ),
+        None => println!("{changed} bytes changed in {} region(s)", regions.len()),
+    }
+}
+
+fn save_type_name(save_type: u32) -> String {
+    sc64::SaveType::try_from(save_type).map_or("Unknown".into(), |save_type| save_type.to_string())
+}
+
+fn open_rom_save_store(rom: &PathBuf) -> Result<store::SaveStore, sc64::Error> {
+    let (mut rom_file, _, _) = open_file(rom)?;
+    let (header, _) = sc64::rom::read_boot(&mut rom_file)?;
+    store::SaveStore::open(&store::RomIdentity::from_header(&header))
+}
+
+fn load_save_for_diff(
+    source: &str,
+    save_store: Option<&store::SaveStore>,
+) -> Result<(String, Vec<u8>, Option<sc64::SaveType>), sc64::Error> {
+    let path = PathBuf::from(source);
+    if path.is_file() {
+        let (mut file, name, _) = open_file(&path)?;
+        let mut data = vec![];
+        file.read_to_end(&mut data)?;
+        return Ok((name, data, None));
+    }
+    match (source.parse::<usize>(), save_store) {
+        (Ok(number), Some(save_store)) => {
+            let (save_type, data) = save_store.read(number)?;
+            Ok((format!("history entry {number}"), data, Some(save_type)))
+        }
+        (Ok(_), None) => Err(sc64::Error::InvalidInput(
+            "ROM file must be provided to compare save history entries".into(),
+        )),
+        (Err(_), _) => Err(sc64::Error::InvalidInput(format!(
+            "Save file [{source}] not found"
+        ))),
+    }
+}
+
+/// Returns offset and length of every region that differs between two saves
+fn diff_regions(a: &[u8], b: &[u8]) -> Vec<(usize, usize)> {
+    let mut regions: Vec<(usize, usize)> = vec![];
+    for offset in 0..a.len().max(b.len()) {
+        if a.get(offset) == b.get(offset) {
+            continue;
+        }
+        match regions.last_mut() {
+            Some((start, length)) if *start + *length == offset => *length += 1,
+            _ => regions.push((offset, 1)),
+        }
+    }
+    regions
+}
+
+fn record_save_writeback(sc64: &mut sc64::SC64, save_writeback: &sc64::SaveWriteback) -> bool {
+    let mut store_save = || -> Result<(store::SaveStore, bool), sc64::Error> {
+        let mut header = vec![];
+        sc64.dump_memory(&mut header, 0, sc64::rom::HEADER_LENGTH)?;
+        let header = sc64::rom::Header::parse(&header)?;
+        let mut save_store = store::SaveStore::open(&store::RomIdentity::from_header(&header))?;
+        let added = save_store
+            .add(save_writeback.save, &save_writeback.data)?
+            .is_some();
+        Ok((save_store, added))
+    };
+    match store_save() {
+        Ok((save_store, added)) => {
+            let message = if added {
+                format!("Save recorded in [{}]", save_store.directory().display())
+            } else {
+                "Save is identical to the last recorded one".to_string()
+            };
+            println!("{}: {}", "[Save history]".bold(), message.bright_green());
+            true
+        }
+        Err(error) => {
+            println!(
+                "{}: {}",
+                "[Save history]".bold(),
+                format!("Couldn't record save: {error}").bright_red()
+            );
+            false
+        }
+    }
+}
+
+fn handle_disk_command(command: &DiskCommands) -> Result<(), sc64::Error> {
+    match command {
+        DiskCommands::Info(args) => {
+            let (disk, disk_name) = open_disk_image(&args.disk)?;
+            let info = disk.get_info();
+            let id_data = &info.id_data;
+            let printable = |data: &[u8]| -> String {
+                data.iter()
+                    .map(|&b| if b.is_ascii_graphic() { b as char } else { '.' })
+                    .collect()
+            };
+            let game_code = printable(&id_data[0..4]);
+            let company_code = printable(&id_data[0x10..0x12]);
+
+            if json_output() {
+                let zones: Vec<Value> = info
+                    .zones
+                    .iter()
+                    .map(|zone| {
+                        json!({
+                            "vzone": zone.vzone,
+                            "pzone": zone.pzone,
+                            "head": zone.head,
+                            "tracks": [zone.tracks.start, zone.tracks.end - 1],
+                            "lbas": [zone.lbas.start, zone.lbas.end - 1],
+                            "block_length": zone.block_length,
+                            "writable": zone.writable,
+                            "bad_tracks": zone.bad_tracks,
+                        })
+                    })
+                    .collect();
+                print_json(json!({
+                    "name": disk_name,
+                    "image_format": info.image_format.to_string(),
+                    "format": info.format.to_string(),
+                    "disk_type": info.disk_type,
+                    "rom_lbas": [info.rom_lbas.start, info.rom_lbas.end - 1],
+                    "ram_lbas": (!info.ram_lbas.is_empty())
+                        .then(|| [info.ram_lbas.start, info.ram_lbas.end - 1]),
+                    "zones": zones,
+                    "id": {
+                        "game_code": game_code,
+                        "version": id_data[4],
+                        "disk_number": id_data[5],
+                        "company_code": company_code,
+                        "data": id_data.iter().map(|b| format!("{b:02X}")).collect::<String>(),
+                    },
+                }));
+                return Ok(());
+            }
+
+            let bad_tracks: usize = info.zones.iter().map(|zone| zone.bad_tracks.len()).sum();
+
+            println!("{}", format!("Disk information [{disk_name}]:").bold());
+            println!(" Image format:  {}", info.image_format);
+            println!(" Disk format:   {}", info.format.to_string().bright_green());
+            println!(" Disk type:     {}", info.disk_type);
+            println!(
+                " ROM area:      LBA {} - {}",
+                info.rom_lbas.start,
+                info.rom_lbas.end - 1
+            );
+            if info.ram_lbas.is_empty() {
+                println!(" RAM area:      None");
+            } else {
+                println!(
+                    " RAM area:      LBA {} - {}",
+                    info.ram_lbas.start,
+                    info.ram_lbas.end - 1
+                );
+            }
+            println!(" Bad tracks:    {bad_tracks}");
+            println!(" Game code:     {game_code}");
+            println!(" Version:       {}", id_data[4]);
+            println!(" Disk number:   {}", id_data[5]);
+            println!(" Company code:  {company_code}");
+            println!("{}", "Zones:".bold());
+            for zone in info.zones.iter() {
+                println!(
+                    " {:>2} (physical {:>2}, head {}) | tracks {:>4} - {:>4} | LBA {:>4} - {:>4} | {:>5} bytes per block | {}",
+                    zone.vzone,
+                    zone.pzone,
+                    zone.head,
+                    zone.tracks.start,
+                    zone.tracks.end - 1,
+                    zone.lbas.start,
+                    zone.lbas.end - 1,
+                    zone.block_length,
+                    if zone.writable { "RAM" } else { "ROM" }
+                );
+                if !zone.bad_tracks.is_empty() {
+                    let tracks: Vec<String> = zone
+                        .bad_tracks
+                        .iter()
+                        .map(|track| track.to_string())
+                        .collect();
+                    println!(
+                        "    {}",
+                        format!("Bad tracks: {}", tracks.join(", ")).bright_yellow()
+                    );
+                }
+            }
+            println!("{}", "ID LBA:".bold());
+            for (line, chunk) in id_data.chunks(16).enumerate() {
+                let bytes: Vec<String> = chunk.iter().map(|b| format!("{b:02X}")).collect();
+                println!(
+                    " 0x{:02X}: {}  {}",
+                    line * 16,
+                    bytes.join(" "),
+                    printable(chunk)
+                );
+            }
+
+            Ok(())
+        }
+        DiskCommands::Convert(args) => {
+            let (mut disk, input_name) = open_disk_image(&args.input)?;
+            let from = *disk.get_image_format();
+            let to: disk::ImageFormat = match (&args.to, &args.output) {
+                (Some(format), _) => format.clone().into(),
+                (None, Some(output)) => disk_image_format_from_path(output).ok_or(
+                    sc64::Error::InvalidInput(
+                        "Couldn't detect output disk format from file extension, provide it with --to argument".into(),
+                    ),
+                )?,
+                (None, None) => match from {
+                    disk::ImageFormat::Ndd => disk::ImageFormat::D64,
+                    disk::ImageFormat::D64 => disk::ImageFormat::Ndd,
+                },
+            };
+
+            let output = match &args.output {
+                Some(output) => output.clone(),
+                None => {
+                    let output = args.input.with_extension(to.extension());
+                    if output == args.input {
+                        return Err(sc64::Error::InvalidInput(
+                            "Output file would overwrite the input file, provide output path explicitly".into(),
+                        ));
+                    }
+                    output
+                }
+            };
+            let (output_file, output_name) = create_file(&output)?;
+            disk.write_image(&mut std::io::BufWriter::new(output_file), to)?;
+
+            status!("Converted [{input_name}] ({from}) to [{output_name}] ({to})");
+
+            if json_output() {
+                print_json(json!({
+                    "input": { "name": input_name, "format": from.to_string() },
+                    "output": { "name": output_name, "format": to.to_string() },
+                }));
+            }
+
+            Ok(())
+        }
+        DiskCommands::Create(args) => {
+            let image_format: disk::ImageFormat = match &args.format {
+                Some(format) => format.clone().into(),
+                None => disk_image_format_from_path(&args.output).unwrap_or(disk::ImageFormat::Ndd),
+            };
+            let format = if args.development {
+                disk::Format::Development
+            } else {
+                disk::Format::Retail
+            };
+
+            let (output_file, output_name) = create_file(&args.output)?;
+            disk::create(
+                &mut std::io::BufWriter::new(output_file),
+                image_format,
+                format,
+                args.disk_type as usize,
+            )?;
+
+            status!(
+                "Created blank {format} disk [{output_name}] ({image_format}, disk type {})",
+                args.disk_type
+            );
+
+            if json_output() {
+                print_json(json!({
+                    "name": output_name,
+                    "image_format": image_format.to_string(),
+                    "format": format.to_string(),
+                    "disk_type": args.disk_type,
+                }));
+            }
+
+            Ok(())
+        }
+        DiskCommands::Commit(args) => {
+            let path = args.disk.to_string_lossy().to_string();
+            let overlay_path = disk::overlay_path(&path);
+            if !PathBuf::from(&overlay_path).is_file() {
+                return Err(sc64::Error::InvalidInput(format!(
+                    "Overlay file [{overlay_path}] doesn't exist"
+                )));
+            }
+            let mut disk = disk::open(&path)?;
+            disk.set_overlay(disk::Overlay::open(&overlay_path)?)?;
+            let blocks = disk.commit_overlay()?;
+            std::fs::remove_file(&overlay_path)?;
+
+            status!("Committed {blocks} blocks from [{overlay_path}] to [{path}]");
+
+            if json_output() {
+                print_json(
+                    json!({ "committed": { "disk": path, "overlay": overlay_path, "blocks": blocks } }),
+                );
+            }
+
+            Ok(())
+        }
+        DiskCommands::Discard(args) => {
+            let overlay_path = disk::overlay_path(&args.disk.to_string_lossy());
+            if !PathBuf::from(&overlay_path).is_file() {
+                return Err(sc64::Error::InvalidInput(format!(
+                    "Overlay file [{overlay_path}] doesn't exist"
+                )));
+            }
+            std::fs::remove_file(&overlay_path)?;
+
+            status!("Discarded changes stored in [{overlay_path}]");
+
+            if json_output() {
+                print_json(json!({ "discarded": overlay_path }));
+            }
+
+            Ok(())
+        }
+        DiskCommands::Verify(args) => {
+            let (mut file, disk_name, _) =
+                open_input_file(&args.disk, None, archive::FileKind::Disk)?;
+            let mut data = vec![];
+            file.read_to_end(&mut data)?;
+            let verification = disk::verify(&mut Cursor::new(data))?;
+
+            let disk_type = verification
+                .system_copies
+                .iter()
+                .find(|copy| Some(copy.lba) == verification.system_source)
+                .map(|copy| (copy.disk_type_byte & 0x0F) as usize);
+
+            // Report is printed in full before failing, scripts get exit code 3 on damaged disks
+            let result = if verification.is_damaged() {
+                Err(sc64::Error::InvalidInput(format!(
+                    "Disk [{disk_name}] has damaged system or ID LBA copies"
+                )))
+            } else {
+                Ok(())
+            };
+
+            if json_output() {
+                let system_lbas: Vec<Value> = verification
+                    .system_copies
+                    .iter()
+                    .map(|copy| {
+                        json!({
+                            "lba": copy.lba,
+                            "valid": copy.is_valid(),
+                            "used": Some(copy.lba) == verification.system_source,
+                            "mismatched_sectors": copy.mismatched_sectors,
+                            "format_byte": copy.format_byte,
+                            "disk_type_byte": copy.disk_type_byte,
+                            "defect_table_errors": copy.defect_table_errors,
+                            "defect_table_differs": copy.defect_table_differs,
+                        })
+                    })
+                    .collect();
+                let id_lbas: Vec<Value> = verification
+                    .id_copies
+                    .iter()
+                    .map(|copy| {
+                        json!({
+                            "lba": copy.lba,
+                            "valid": copy.is_valid(),
+                            "used": Some(copy.lba) == verification.id_source,
+                            "mismatched_sectors": copy.mismatched_sectors,
+                        })
+                    })
+                    .collect();
+                print_json(json!({
+                    "name": disk_name,
+                    "format": verification.format.map(|format| format.to_string()),
+                    "disk_type": disk_type,
+                    "system_lbas": system_lbas,
+                    "id_lbas": id_lbas,
+                    "damaged": verification.is_damaged(),
+                    "repairable": verification.is_repairable(),
+                }));
+                return result;
+            }
+
+            let print_copy = |lba: usize, used: bool, problems: Vec<String>| {
+                if problems.is_empty() {
+                    let state = if used { "OK (used)" } else { "OK" };
+                    println!("  LBA {lba:>2}: {}", state.bright_green());
+                } else {
+                    println!("  LBA {lba:>2}: {}", problems[0].bright_red());
+                    for problem in &problems[1..] {
+                        println!("          {}", problem.bright_red());
+                    }
+                }
+            };
+
+            println!(
+                "{}",
+                format!("System area verification [{disk_name}]:").bold()
+            );
+            match verification.format {
+                Some(format) => println!(" Disk format:   {}", format.to_string().bright_green()),
+                None => println!(" Disk format:   {}", "Unknown".bright_red()),
+            }
+            if let Some(disk_type) = disk_type {
+                println!(" Disk type:     {disk_type}");
+            }
+            println!(" System LBAs:");
+            for copy in verification.system_copies.iter() {
+                let mut problems: Vec<String> = Vec::new();
+                if !copy.mismatched_sectors.is_empty() {
+                    problems.push(format!(
+                        "Sector repetitions not matching the first sector: {}",
+                        format_sector_list(&copy.mismatched_sectors)
+                    ));
+                }
+                if copy.format_byte != 0x10 {
+                    problems.push(format!("Invalid format byte 0x{:02X}", copy.format_byte));
+                }
+                if (copy.disk_type_byte & 0xF0) != 0x10 {
+                    problems.push(format!(
+                        "Invalid disk type byte 0x{:02X}",
+                        copy.disk_type_byte
+                    ));
+                }
+                problems.extend(copy.defect_table_errors.iter().cloned());
+                print_copy(
+                    copy.lba,
+                    Some(copy.lba) == verification.system_source,
+                    problems,
+                );
+                if copy.defect_table_differs {
+                    println!(
+                        "          {}",
+                        format!(
+                            "Defect track table differs from the LBA {} copy",
+                            verification.system_source.unwrap_or_default()
+                        )
+                        .bright_yellow()
+                    );
+                }
+            }
+            println!(" ID LBAs:");
+            for copy in verification.id_copies.iter() {
+                let mut problems: Vec<String> = Vec::new();
+                if !copy.mismatched_sectors.is_empty() {
+                    problems.push(format!(
+                        "Sector repetitions not matching the first sector: {}",
+                        format_sector_list(&copy.mismatched_sectors)
+                    ));
+                }
+                print_copy(copy.lba, Some(copy.lba) == verification.id_source, problems);
+            }
+
+            if !verification.is_damaged() {
+                println!("{}", "All copies are valid".bright_green());
+            } else if verification.is_repairable() {
+                println!(
+                    "{}",
+                    "Damaged copies can be rebuilt with the [disk repair] command".bright_yellow()
+                );
+            } else {
+                println!(
+                    "{}",
+                    "No valid system or ID LBA copy found, disk can't be repaired".bright_red()
+                );
+            }
+
+            result
+        }
+        DiskCommands::Repair(args) => {
+            let path = args.disk.to_string_lossy().to_string();
+            let mut file = std::fs::File::options()
+                .read(true)
+                .write(true)
+                .open(&args.disk)?;
+            let repaired = disk::repair(&mut file)?;
+
+            if repaired.is_empty() {
+                status!("No damaged copies found in [{path}]");
+            } else {
+                let lbas: Vec<String> = repaired.iter().map(|lba| lba.to_string()).collect();
+                status!("Rebuilt LBAs {} in [{path}]", lbas.join(", "));
+            }
+
+            if json_output() {
+                print_json(json!({ "name": path, "repaired": repaired }));
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// Checks disk numbers provided to the argument selecting disks (empty list selects all disks)
+fn check_disk_numbers(
+    argument: &str,
+    numbers: &Option<Vec<usize>>,
+    count: usize,
+) -> Result<(), sc64::Error> {
+    if let Some(number) = numbers
+        .iter()
+        .flatten()
+        .find(|number| **number == 0 || **number > count)
+    {
+        return Err(sc64::Error::InvalidInput(format!(
+            "Disk number [{number}] provided to the {argument} argument is out of range"
+        )));
+    }
+    Ok(())
+}
+
+fn is_disk_selected(numbers: &Option<Vec<usize>>, index: usize) -> bool {
+    numbers
+        .as_ref()
+        .is_some_and(|numbers| numbers.is_empty() || numbers.contains(&(index + 1)))
+}
+
+fn open_disk_image(path: &PathBuf) -> Result<(disk::Disk, String), sc64::Error> {
+    let (mut file, name, _) = open_input_file(path, None, archive::FileKind::Disk)?;
+    let mut data = vec![];
+    file.read_to_end(&mut data)?;
+    Ok((disk::open_memory(data)?, name))
+}
+
+/// Formats sector numbers collapsing consecutive ones into ranges
+fn format_sector_list(sectors: &[usize]) -> String {
+    let mut ranges: Vec<String> = Vec::new();
+    let mut index = 0;
+    while index < sectors.len() {
+        let start = sectors[index];
+        let mut end = start;
+        while index + 1 < sectors.len() && sectors[index + 1] == end + 1 {
+            index += 1;
+            end += 1;
+        }
+        ranges.push(if start == end {
+            start.to_string()
+        } else {
+            format!("{start}-{end}")
+        });
+        index += 1;
+    }
+    ranges.join(", ")
+}
+
+fn disk_image_format_from_path(path: &Path) -> Option<disk::ImageFormat> {
+    match path
+        .extension()?
+        .to_string_lossy()
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "ndd" => Some(disk::ImageFormat::Ndd),
+        "d64" => Some(disk::ImageFormat::D64),
+        _ => None,
+    }
+}
+
 fn handle_test_command(connection: Connection) -> Result<(), sc64::Error> {
     let mut sc64 = init_sc64(connection, true)?;
 
     sc64.reset_state()?;
 
-    println!("{}: USB", "[SC64 Tests]".bold());
+    status!("{}: USB", "[SC64 Tests]".bold());
 
-    print!(" Performing USB read speed test... ");
+    status_inline!(" Performing USB read speed test... ");
     stdout().flush().unwrap();
     let usb_read_speed = sc64.test_usb_speed(sc64::SpeedTestDirection::Read)?;
-    println!("{}", format!("{usb_read_speed:.2} MiB/s",).bright_green());
+    status!("{}", format!("{usb_read_speed:.2} MiB/s",).bright_green());
 
-    print!(" Performing USB write speed test... ");
+    status_inline!(" Performing USB write speed test... ");
     stdout().flush().unwrap();
     let usb_write_speed = sc64.test_usb_speed(sc64::SpeedTestDirection::Write)?;
-    println!("{}", format!("{usb_write_speed:.2} MiB/s",).bright_green());
+    status!("{}", format!("{usb_write_speed:.2} MiB/s",).bright_green());
 
-    println!("{}: SD card", "[SC64 Tests]".bold());
+    status!("{}: SD card", "[SC64 Tests]".bold());
 
-    print!(" Performing SD card read speed test... ");
+    status_inline!(" Performing SD card read speed test... ");
     stdout().flush().unwrap();
-    match sc64.test_sd_card() {
-        Ok(sd_read_speed) => println!("{}", format!("{sd_read_speed:.2} MiB/s",).bright_green()),
-        Err(result) => println!("{}", format!("error! {result}").bright_red()),
-    }
+    let sd_test = match sc64.test_sd_card() {
+        Ok(sd_read_speed) => {
+            status!("{}", format!("{sd_read_speed:.2} MiB/s",).bright_green());
+            json!({ "read_speed": sd_read_speed })
+        }
+        Err(result) => {
+            status!("{}", format!("error! {result}").bright_red());
+            json!({ "error": result.to_string() })
+        }
+    };
 
-    println!("{}: SDRAM (pattern)", "[SC64 Tests]".bold());
+    status!("{}: SDRAM (pattern)", "[SC64 Tests]".bold());
 
     let sdram_pattern_tests = [
         (sc64::MemoryTestPattern::OwnAddress(false), None),
@@ -1225,6 +3662,7 @@ fn handle_test_command(connection: Connection) -> Result<(), sc64::Error> {
     let sdram_pattern_tests_count = sdram_pattern_tests.len();
 
     let mut sdram_tests_failed = false;
+    let mut sdram_test_results = vec![];
 
     for (i, (pattern, fade)) in sdram_pattern_tests.into_iter().enumerate() {
         let fadeout_text = if let Some(fade) = fade {
@@ -1232,32 +3670,56 @@ fn handle_test_command(connection: Connection) -> Result<(), sc64::Error> {
         } else {
             "".to_string()
         };
-        print!(
+        status_inline!(
             " ({} / {sdram_pattern_tests_count}) Testing {pattern}{fadeout_text}... ",
             i + 1
         );
         stdout().flush().unwrap();
 
+        let pattern_name = pattern.to_string();
         let result = sc64.test_sdram_pattern(pattern, fade)?;
 
+        sdram_test_results.push(json!({
+            "pattern": pattern_name,
+            "fadeout": fade,
+            "first_error": result.first_error.map(|(address, (written, read))| {
+                json!({ "address": address, "written": written, "read": read })
+            }),
+            "total_errors": result.all_errors.len(),
+        }));
+
         if let Some((address, (written, read))) = result.first_error {
             sdram_tests_failed = true;
-            println!("{}", "error!".bright_red());
-            println!("  Found a mismatch at address 0x{address:08X}",);
-            println!("   0x{written:08X} (W) != 0x{read:08X} (R)");
-            println!("   Total errors found: {}", result.all_errors.len());
+            status!("{}", "error!".bright_red());
+            status!("  Found a mismatch at address 0x{address:08X}",);
+            status!("   0x{written:08X} (W) != 0x{read:08X} (R)");
+            status!("   Total errors found: {}", result.all_errors.len());
         } else {
-            println!("{}", "ok".bright_green());
+            status!("{}", "ok".bright_green());
         }
     }
 
     if sdram_tests_failed {
-        println!(
+        status!(
             "{}",
             "Some SDRAM tests failed, SDRAM chip might be defective".bright_red()
         );
     } else {
-        println!("{}", "All SDRAM tests passed without error".bright_green());
+        status!("{}", "All SDRAM tests passed without error".bright_green());
+    }
+
+    if json_output() {
+        print_json(json!({
+            "usb": {
+                "read_speed": usb_read_speed,
+                "write_speed": usb_write_speed,
+            },
+            "sd_card": sd_test,
+            "sdram": {
+                "passed": !sdram_tests_failed,
+                "patterns": sdram_test_results,
+            },
+        }));
     }
 
     Ok(())
@@ -1304,6 +3766,39 @@ fn handle_server_command(connection: Connection, args: &ServerArgs) -> Result<()
     Ok(())
 }
 
+fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+fn print_json(document: Value) {
+    println!("{}", serde_json::to_string_pretty(&document).unwrap());
+}
+
+fn entry_json(entry: &sc64::ff::Entry) -> Value {
+    let (kind, size) = match entry.info {
+        sc64::ff::EntryInfo::Directory => ("directory", None),
+        sc64::ff::EntryInfo::File { size } => ("file", Some(size)),
+    };
+    json!({
+        "name": entry.name,
+        "type": kind,
+        "size": size,
+        "datetime": entry.datetime.format("%Y-%m-%dT%H:%M:%S").to_string(),
+    })
+}
+
+fn firmware_json(firmware: &sc64::firmware::Firmware) -> Value {
+    let chunks: serde_json::Map<String, Value> = firmware
+        .chunk_lengths()
+        .into_iter()
+        .map(|(name, length)| (name.to_string(), json!({ "length": length })))
+        .collect();
+    json!({
+        "update_info": firmware.update_info(),
+        "chunks": chunks,
+    })
+}
+
 fn init_sc64(connection: Connection, check_firmware: bool) -> Result<sc64::SC64, sc64::Error> {
     let mut sc64 = match connection {
         Connection::Local(port) => sc64::SC64::open_local(port),
@@ -1317,20 +3812,155 @@ fn init_sc64(connection: Connection, check_firmware: bool) -> Result<sc64::SC64,
     Ok(sc64)
 }
 
+trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+fn prepare_rom(
+    mut rom_file: Box<dyn ReadSeek>,
+    rom_length: usize,
+    patches: &[PathBuf],
+    fix_crc: bool,
+    cic_seed: Option<u8>,
+) -> Result<(Box<dyn ReadSeek>, usize), sc64::Error> {
+    if patches.is_empty() && !fix_crc {
+        return Ok((rom_file, rom_length));
+    }
+    let mut rom = vec![];
+    rom_file.read_to_end(&mut rom)?;
+    if !patches.is_empty() {
+        sc64::rom::ByteOrder::detect(&rom)?.to_big_endian(&mut rom);
+        for path in patches {
+            let (mut patch_file, patch_name, _) = open_file(path)?;
+            let mut patch = vec![];
+            patch_file.read_to_end(&mut patch)?;
+            let format = sc64::patch::PatchFormat::detect(&patch)?;
+            rom = log_wait(format!("Applying {format} patch [{patch_name}]"), || {
+                sc64::patch::apply_patch(&rom, &patch)
+            })?;
+        }
+    }
+    if fix_crc {
+        print_checksum(&sc64::rom::fix_checksum(&mut rom, cic_seed)?);
+    }
+    let length = rom.len();
+    Ok((Box::new(Cursor::new(rom)), length))
+}
+
+fn detect_rom_settings<T: Read + Seek>(
+    rom: &mut T,
+    database: &n64::Database,
+) -> Result<n64::Detection, sc64::Error> {
+    let detection = n64::identify(rom, database)?;
+    if let Some(title) = &detection.settings.title {
+        status!("ROM title: {title}");
+    }
+    if !matches!(detection.source, n64::DetectionSource::None) {
+        status!("ROM settings detected from {}", detection.source);
+    }
+    if let Some(homebrew) = &detection.homebrew {
+        status!(
+            "Homebrew ROM header: {}",
+            homebrew_header_description(homebrew)
+        );
+    }
+    Ok(detection)
+}
+
+fn homebrew_header_description(homebrew: &n64::HomebrewHeader) -> String {
+    let save_type = homebrew
+        .save_type
+        .map(|save_type| sc64::SaveType::from(SaveType::from(save_type)).to_string())
+        .unwrap_or("Unknown".to_string());
+    format!(
+        "save type [{save_type}], RTC [{}], region free [{}]",
+        if homebrew.rtc { "Yes" } else { "No" },
+        if homebrew.region_free { "Yes" } else { "No" }
+    )
+}
+
+/// Explicit --direct, --no-direct or --tv on the command line take precedence over the database
+fn use_direct_boot(direct: bool, no_direct: bool, tv: bool, settings: &n64::GameSettings) -> bool {
+    if direct || no_direct || tv || settings.direct_boot != Some(true) {
+        return direct;
+    }
+    status!("Direct boot enabled by game database (pass --no-direct to disable)");
+    true
+}
+
+fn print_cic_detection(signature: &Option<sc64::cic::Ipl3Signature>, custom_seed: Option<u8>) {
+    let Some(signature) = signature else {
+        return;
+    };
+    match (signature.variant, custom_seed) {
+        (Some(variant), None) => status!(
+            "Detected CIC: {} (seed 0x{:02X})",
+            variant.to_string().bright_green(),
+            signature.seed
+        ),
+        (Some(variant), Some(seed)) => {
+            status!("Detected CIC: {variant}, using provided seed 0x{seed:02X}")
+        }
+        (None, Some(seed)) => status!("Unknown IPL3 detected, using provided seed 0x{seed:02X}"),
+        (None, None) if signature.seed_found => status!(
+            "{}",
+            format!(
+                "Warning: unknown IPL3 detected (custom bootcode or iQue?), using seed 0x{:02X} matching the ROM header checksum",
+                signature.seed
+            )
+            .bright_yellow()
+        ),
+        (None, None) => status!(
+            "{}",
+            format!(
+                "Warning: unknown IPL3 detected (custom bootcode or iQue?), no seed matches the ROM header checksum, falling back to seed 0x{:02X}",
+                signature.seed
+            )
+            .bright_yellow()
+        ),
+    }
+}
+
+fn cic_json(signature: &sc64::cic::Ipl3Signature) -> Value {
+    json!({
+        "variant": signature.variant.map(|variant| variant.to_string()),
+        "seed": signature.seed,
+        "seed_found": signature.seed_found,
+        "checksum": signature.checksum,
+    })
+}
+
+fn print_checksum(checksum: &sc64::rom::Checksum) {
+    let (crc1, crc2) = checksum.header;
+    if checksum.is_valid() {
+        status!(
+            "ROM checksum is valid [0x{crc1:08X} / 0x{crc2:08X}] (CIC seed 0x{:02X})",
+            checksum.seed
+        );
+    } else {
+        let (fixed_crc1, fixed_crc2) = checksum.calculated;
+        status!(
+            "ROM checksum fixed [0x{crc1:08X} / 0x{crc2:08X}] -> [{}] (CIC seed 0x{:02X})",
+            format!("0x{fixed_crc1:08X} / 0x{fixed_crc2:08X}").bright_green(),
+            checksum.seed
+        );
+    }
+}
+
 fn log_wait<F: FnOnce() -> Result<T, E>, T, E>(message: String, operation: F) -> Result<T, E> {
-    print!("{}... ", message);
+    status_inline!("{}... ", message);
     stdout().flush().unwrap();
     let result = operation();
     if result.is_ok() {
-        println!("{}", "done".bold().bright_green());
+        status!("{}", "done".bold().bright_green());
     } else {
-        println!("{}", "error!".bold().bright_red());
+        status!("{}", "error!".bold().bright_red());
     }
     result
 }
 
 fn prompt(message: String) -> String {
-    print!("{message}");
+    status_inline!("{message}");
     stdout().flush().unwrap();
     let mut answer = String::new();
     stdin().read_line(&mut answer).unwrap();
@@ -1344,6 +3974,19 @@ fn open_file(path: &PathBuf) -> Result<(File, String, usize), sc64::Error> {
     Ok((file, name, length))
 }
 
+fn open_input_file(
+    path: &PathBuf,
+    entry: Option<&str>,
+    kind: archive::FileKind,
+) -> Result<(Box<dyn ReadSeek>, String, usize), sc64::Error> {
+    if let Some((name, data)) = archive::extract(path, entry, kind)? {
+        let length = data.len();
+        return Ok((Box::new(Cursor::new(data)), name, length));
+    }
+    let (file, name, length) = open_file(path)?;
+    Ok((Box::new(file), name, length))
+}
+
 fn create_file(path: &PathBuf) -> Result<(File, String), sc64::Error> {
     let name: String = path.file_name().unwrap().to_string_lossy().to_string();
     let file = File::create(path)?;
@@ -0,0 +1,209 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const INDEX_FILE_NAME: &str = "index.json";
+const HISTORY_LENGTH: usize = 64;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub timestamp: String,
+    pub hash: String,
+    pub save_type: u32,
+    pub length: usize,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Index {
+    pub title: String,
+    pub game_code: String,
+    #[serde(default)]
+    pub version: u8,
+    #[serde(default)]
+    pub crc1: u32,
+    #[serde(default)]
+    pub crc2: u32,
+    /// Ordered from the oldest to the newest writeback
+    pub entries: Vec<Entry>,
+}
+
+/// Title and game code are shared between ROM revisions and hacks, version and checksums tell them apart
+pub struct RomIdentity {
+    pub title: String,
+    pub game_code: String,
+    pub version: u8,
+    pub crc1: u32,
+    pub crc2: u32,
+}
+
+impl RomIdentity {
+    pub fn from_header(header: &sc64::rom::Header) -> Self {
+        RomIdentity {
+            title: header.name.clone(),
+            game_code: header.game_code(),
+            version: header.version,
+            crc1: header.crc1,
+            crc2: header.crc2,
+        }
+    }
+
+    fn key(&self) -> String {
+        let title = if self.title.is_empty() {
+            "Unknown"
+        } else {
+            &self.title
+        };
+        format!(
+            "{title} [{}] [v{}] [{:08X}-{:08X}]",
+            self.game_code, self.version, self.crc1, self.crc2
+        )
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || " -_[]".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+    }
+}
+
+fn root() -> Result<PathBuf, sc64::Error> {
+    dirs::data_dir()
+        .map(|directory| directory.join("sc64").join("saves"))
+        .ok_or(sc64::Error::InvalidInput(
+            "Couldn't determine user data directory for the save store".into(),
+        ))
+}
+
+fn load_index(directory: &Path) -> Result<Option<Index>, sc64::Error> {
+    let path = directory.join(INDEX_FILE_NAME);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let index = serde_json::from_str(&fs::read_to_string(&path)?).map_err(|error| {
+        sc64::Error::InvalidInput(format!(
+            "Couldn't parse save store index [{}]: {error}",
+            path.display()
+        ))
+    })?;
+    Ok(Some(index))
+}
+
+/// Rolling history of saves received by the save writeback mechanism, kept per ROM
+pub struct SaveStore {
+    directory: PathBuf,
+    index: Index,
+}
+
+impl SaveStore {
+    pub fn open(identity: &RomIdentity) -> Result<Self, sc64::Error> {
+        let directory = root()?.join(identity.key());
+        let index = load_index(&directory)?.unwrap_or(Index {
+            title: identity.title.clone(),
+            game_code: identity.game_code.clone(),
+            version: identity.version,
+            crc1: identity.crc1,
+            crc2: identity.crc2,
+            entries: vec![],
+        });
+        Ok(SaveStore { directory, index })
+    }
+
+    /// Lists all ROMs with saves in the store
+    pub fn list() -> Result<Vec<Index>, sc64::Error> {
+        let root = root()?;
+        if !root.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut indexes = vec![];
+        for entry in fs::read_dir(root)? {
+            if let Some(index) = load_index(&entry?.path())? {
+                indexes.push(index);
+            }
+        }
+        indexes.sort_by(|a, b| a.title.cmp(&b.title));
+        Ok(indexes)
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Returns entries ordered from the newest one, position in the list is the entry number
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.index.entries.iter().rev()
+    }
+
+    fn entry(&self, number: usize) -> Result<&Entry, sc64::Error> {
+        self.entries()
+            .nth(number)
+            .ok_or(sc64::Error::InvalidInput(format!(
+                "Save history entry [{number}] doesn't exist for [{}]",
+                self.index.title
+            )))
+    }
+
+    fn data_path(&self, hash: &str) -> PathBuf {
+        self.directory.join(format!("{hash}.sav"))
+    }
+
+    /// Adds save to the history, returns [None] if it's identical to the newest entry
+    pub fn add(
+        &mut self,
+        save_type: sc64::SaveType,
+        data: &[u8],
+    ) -> Result<Option<&Entry>, sc64::Error> {
+        let hash = format!("{:x}", md5::compute(data));
+        if self
+            .index
+            .entries
+            .last()
+            .is_some_and(|entry| entry.hash == hash)
+        {
+            return Ok(None);
+        }
+
+        fs::create_dir_all(&self.directory)?;
+        let path = self.data_path(&hash);
+        if !path.is_file() {
+            fs::write(path, data)?;
+        }
+
+        self.index.entries.push(Entry {
+            timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            hash,
+            save_type: save_type.into(),
+            length: data.len(),
+        });
+
+        if self.index.entries.len() > HISTORY_LENGTH {
+            let removed: Vec<Entry> = self
+                .index
+                .entries
+                .drain(..(self.index.entries.len() - HISTORY_LENGTH))
+                .collect();
+            for entry in removed {
+                if !self.index.entries.iter().any(|e| e.hash == entry.hash) {
+                    fs::remove_file(self.data_path(&entry.hash)).ok();
+                }
+            }
+        }
+
+        let index = serde_json::to_string_pretty(&self.index).unwrap();
+        fs::write(self.directory.join(INDEX_FILE_NAME), index)?;
+
+        Ok(self.index.entries.last())
+    }
+
+    pub fn read(&self, number: usize) -> Result<(sc64::SaveType, Vec<u8>), sc64::Error> {
+        let entry = self.entry(number)?;
+        let save_type = entry.save_type.try_into()?;
+        let data = fs::read(self.data_path(&entry.hash))?;
+        Ok((save_type, data))
+    }
+}